@@ -24,6 +24,35 @@ fn criterion_waters_benchmark(criterion: &mut Criterion) {
         b.iter(|| setup(&mut rng))
     });
     criterion.bench_function("waters derive", move |b| b.iter(|| Identity::derive(id)));
+    #[cfg(feature = "alloc")]
+    {
+        let ppk = PreparedPublicKey::new(&pk);
+
+        criterion.bench_function("waters prepare", |b| {
+            b.iter(|| PreparedPublicKey::new(black_box(&pk)))
+        });
+        criterion.bench_function("waters extract batch", |b| {
+            let mut rng = rand::thread_rng();
+            let ids: Vec<Identity> = (0..64)
+                .map(|i| Identity::derive_str(&format!("email:{}@example.com", i)))
+                .collect();
+            b.iter(|| extract_usk_batch(black_box(&pk), black_box(&sk), black_box(&ids), &mut rng))
+        });
+        criterion.bench_function("waters extract prepared", |b| {
+            let mut rng = rand::thread_rng();
+            b.iter(|| {
+                extract_usk_prepared(black_box(&ppk), black_box(&sk), black_box(&kid), &mut rng)
+            })
+        });
+        criterion.bench_function("waters encrypt prepared", |b| {
+            let mut rng = rand::thread_rng();
+            b.iter(|| encrypt_prepared(black_box(&ppk), black_box(&kid), black_box(&m), &mut rng))
+        });
+        criterion.bench_function("waters decrypt prepared", |b| {
+            let pusk = PreparedUserSecretKey::new(&usk);
+            b.iter(|| decrypt_prepared(black_box(&pusk), black_box(&c)))
+        });
+    }
     criterion.bench_function("waters extract", move |b| {
         let mut rng = rand::thread_rng();
         b.iter(|| extract_usk(black_box(&pk), black_box(&sk), black_box(&kid), &mut rng))
@@ -63,6 +92,28 @@ fn criterion_waters_naccache_benchmark(criterion: &mut Criterion) {
     criterion.bench_function("waters_naccache derive", move |b| {
         b.iter(|| Identity::derive(id))
     });
+    #[cfg(feature = "alloc")]
+    {
+        let ppk = PreparedPublicKey::new(&pk);
+
+        criterion.bench_function("waters_naccache prepare", |b| {
+            b.iter(|| PreparedPublicKey::new(black_box(&pk)))
+        });
+        criterion.bench_function("waters_naccache extract prepared", |b| {
+            let mut rng = rand::thread_rng();
+            b.iter(|| {
+                extract_usk_prepared(black_box(&ppk), black_box(&sk), black_box(&kid), &mut rng)
+            })
+        });
+        criterion.bench_function("waters_naccache encrypt prepared", |b| {
+            let mut rng = rand::thread_rng();
+            b.iter(|| encrypt_prepared(black_box(&ppk), black_box(&kid), black_box(&m), &mut rng))
+        });
+        criterion.bench_function("waters_naccache decrypt prepared", |b| {
+            let pusk = PreparedUserSecretKey::new(&usk);
+            b.iter(|| decrypt_prepared(black_box(&pusk), black_box(&c)))
+        });
+    }
     criterion.bench_function("waters_naccache extract", move |b| {
         let mut rng = rand::thread_rng();
         b.iter(|| extract_usk(black_box(&pk), black_box(&sk), black_box(&kid), &mut rng))
@@ -89,10 +140,14 @@ fn criterion_kiltz_vahlis_one_benchmark(criterion: &mut Criterion) {
     let ppk = pk.to_bytes();
 
     let (c, _k) = encrypt(&pk, &kid, &mut rng);
+    let (cpk, _) = setup_compact(&mut rng);
 
     criterion.bench_function("kiltz_vahlis_one unpack_pk", |b| {
         b.iter(|| PublicKey::from_bytes(&ppk))
     });
+    criterion.bench_function("kiltz_vahlis_one expand_pk", |b| {
+        b.iter(|| black_box(&cpk).expand())
+    });
     criterion.bench_function("kiltz_vahlis_one setup", |b| {
         let mut rng = rand::thread_rng();
         b.iter(|| setup(&mut rng))
@@ -100,6 +155,35 @@ fn criterion_kiltz_vahlis_one_benchmark(criterion: &mut Criterion) {
     criterion.bench_function("kiltz_vahlis_one derive", move |b| {
         b.iter(|| Identity::derive(id))
     });
+    #[cfg(feature = "alloc")]
+    {
+        let ppk = PreparedPublicKey::new(&pk);
+
+        criterion.bench_function("kiltz_vahlis_one prepare", |b| {
+            b.iter(|| PreparedPublicKey::new(black_box(&pk)))
+        });
+        criterion.bench_function("kiltz_vahlis_one extract batch", |b| {
+            let mut rng = rand::thread_rng();
+            let ids: Vec<Identity> = (0..64)
+                .map(|i| Identity::derive_str(&format!("email:{}@example.com", i)))
+                .collect();
+            b.iter(|| extract_usk_batch(black_box(&pk), black_box(&sk), black_box(&ids), &mut rng))
+        });
+        criterion.bench_function("kiltz_vahlis_one extract prepared", |b| {
+            let mut rng = rand::thread_rng();
+            b.iter(|| {
+                extract_usk_prepared(black_box(&ppk), black_box(&sk), black_box(&kid), &mut rng)
+            })
+        });
+        criterion.bench_function("kiltz_vahlis_one encrypt prepared", |b| {
+            let mut rng = rand::thread_rng();
+            b.iter(|| encrypt_prepared(black_box(&ppk), black_box(&kid), &mut rng))
+        });
+        criterion.bench_function("kiltz_vahlis_one decrypt prepared", |b| {
+            let pusk = PreparedUserSecretKey::new(&usk);
+            b.iter(|| decrypt_prepared(black_box(&pusk), black_box(&c)))
+        });
+    }
     criterion.bench_function("kiltz_vahlis_one extract", move |b| {
         let mut rng = rand::thread_rng();
         b.iter(|| extract_usk(black_box(&pk), black_box(&sk), black_box(&kid), &mut rng))
@@ -113,10 +197,167 @@ fn criterion_kiltz_vahlis_one_benchmark(criterion: &mut Criterion) {
     });
 }
 
+fn criterion_boneh_franklin_benchmark(criterion: &mut Criterion) {
+    use ibe::boneh_franklin::*;
+
+    let mut rng = rand::thread_rng();
+
+    let id = "email:w.geraedts@sarif.nl".as_bytes();
+    let kid = Identity::derive(id);
+
+    let m = Message::generate(&mut rng);
+
+    let (pk, sk) = setup(&mut rng);
+    let usk = extract_usk(&pk, &sk, &kid);
+
+    let c = encrypt(&pk, &kid, &m, &mut rng);
+
+    criterion.bench_function("boneh_franklin setup", |b| {
+        let mut rng = rand::thread_rng();
+        b.iter(|| setup(&mut rng))
+    });
+    criterion.bench_function("boneh_franklin derive", move |b| {
+        b.iter(|| Identity::derive(id))
+    });
+    criterion.bench_function("boneh_franklin extract", move |b| {
+        b.iter(|| extract_usk(black_box(&pk), black_box(&sk), black_box(&kid)))
+    });
+    criterion.bench_function("boneh_franklin encrypt", move |b| {
+        let mut rng = rand::thread_rng();
+        b.iter(|| encrypt(black_box(&pk), black_box(&kid), black_box(&m), &mut rng))
+    });
+    criterion.bench_function("boneh_franklin decrypt", move |b| {
+        b.iter(|| decrypt(black_box(&usk), black_box(&c)))
+    });
+}
+
+fn criterion_boneh_boyen_1_benchmark(criterion: &mut Criterion) {
+    use ibe::boneh_boyen_1::*;
+
+    let mut rng = rand::thread_rng();
+
+    let id = "email:w.geraedts@sarif.nl".as_bytes();
+    let kid = Identity::derive(id);
+
+    let m = Message::generate(&mut rng);
+
+    let (pk, sk) = setup(&mut rng);
+    let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+    let ppk = pk.to_bytes();
+
+    let c = encrypt(&pk, &kid, &m, &mut rng);
+    let (kc, _k) = encaps(&pk, &kid, &mut rng);
+
+    criterion.bench_function("boneh_boyen_1 unpack_pk", |b| {
+        b.iter(|| PublicKey::from_bytes(&ppk))
+    });
+    criterion.bench_function("boneh_boyen_1 setup", |b| {
+        let mut rng = rand::thread_rng();
+        b.iter(|| setup(&mut rng))
+    });
+    criterion.bench_function("boneh_boyen_1 derive", move |b| {
+        b.iter(|| Identity::derive(id))
+    });
+    criterion.bench_function("boneh_boyen_1 extract", move |b| {
+        let mut rng = rand::thread_rng();
+        b.iter(|| extract_usk(black_box(&pk), black_box(&sk), black_box(&kid), &mut rng))
+    });
+    criterion.bench_function("boneh_boyen_1 encrypt", move |b| {
+        let mut rng = rand::thread_rng();
+        b.iter(|| encrypt(black_box(&pk), black_box(&kid), black_box(&m), &mut rng))
+    });
+    criterion.bench_function("boneh_boyen_1 decrypt", |b| {
+        b.iter(|| decrypt(black_box(&usk), black_box(&c)))
+    });
+    criterion.bench_function("boneh_boyen_1 encaps", move |b| {
+        let mut rng = rand::thread_rng();
+        b.iter(|| encaps(black_box(&pk), black_box(&kid), &mut rng))
+    });
+    criterion.bench_function("boneh_boyen_1 decaps", move |b| {
+        b.iter(|| decaps(black_box(&usk), black_box(&kc)))
+    });
+}
+
+fn criterion_bbg_hibe_benchmark(criterion: &mut Criterion) {
+    use ibe::bbg_hibe::*;
+
+    let mut rng = rand::thread_rng();
+
+    let path = ["acme", "finance", "w.geraedts@sarif.nl"];
+    let kid = Identity::derive_path(&path).unwrap();
+    let parent = Identity::derive_path(&path[..2]).unwrap();
+
+    let m = Message::generate(&mut rng);
+
+    let (pk, sk) = setup(4, &mut rng);
+    let usk = extract_usk(&pk, &sk, &kid, &mut rng).unwrap();
+    let usk_parent = extract_usk(&pk, &sk, &parent, &mut rng).unwrap();
+
+    let c = encrypt(&pk, &kid, &m, &mut rng).unwrap();
+
+    criterion.bench_function("bbg_hibe setup", |b| {
+        let mut rng = rand::thread_rng();
+        b.iter(|| setup(4, &mut rng))
+    });
+    criterion.bench_function("bbg_hibe extract", move |b| {
+        let mut rng = rand::thread_rng();
+        b.iter(|| extract_usk(black_box(&pk), black_box(&sk), black_box(&kid), &mut rng))
+    });
+    criterion.bench_function("bbg_hibe delegate", move |b| {
+        let mut rng = rand::thread_rng();
+        b.iter(|| {
+            delegate(
+                black_box(&pk),
+                black_box(&usk_parent),
+                black_box(&kid),
+                &mut rng,
+            )
+        })
+    });
+    criterion.bench_function("bbg_hibe encrypt", move |b| {
+        let mut rng = rand::thread_rng();
+        b.iter(|| encrypt(black_box(&pk), black_box(&kid), black_box(&m), &mut rng))
+    });
+    criterion.bench_function("bbg_hibe decrypt", move |b| {
+        b.iter(|| decrypt(black_box(&usk), black_box(&c)))
+    });
+}
+
+fn criterion_fujisaki_okamoto_benchmark(criterion: &mut Criterion) {
+    use ibe::fujisaki_okamoto::{WatersFO, WatersNaccacheFO};
+    use ibe::{Derive, IBKEM};
+
+    fn bench<S: IBKEM>(criterion: &mut Criterion, name: &str) {
+        let mut rng = rand::thread_rng();
+
+        let kid = S::Identity::derive_str("email:w.geraedts@sarif.nl");
+
+        let (pk, sk) = S::setup(&mut rng);
+        let usk = S::extract_usk(&pk, &sk, &kid, &mut rng);
+
+        let (c, _k) = S::encaps(&pk, &kid, &mut rng);
+
+        criterion.bench_function(&format!("{} encaps", name), |b| {
+            let mut rng = rand::thread_rng();
+            b.iter(|| S::encaps(black_box(&pk), black_box(&kid), &mut rng))
+        });
+        criterion.bench_function(&format!("{} decaps", name), |b| {
+            b.iter(|| S::decaps(black_box(&pk), black_box(&usk), black_box(&c)))
+        });
+    }
+
+    bench::<WatersFO>(criterion, "waters_fo");
+    bench::<WatersNaccacheFO>(criterion, "waters_naccache_fo");
+}
+
 criterion_group!(
     benches,
     criterion_waters_benchmark,
     criterion_waters_naccache_benchmark,
     criterion_kiltz_vahlis_one_benchmark,
+    criterion_boneh_franklin_benchmark,
+    criterion_boneh_boyen_1_benchmark,
+    criterion_bbg_hibe_benchmark,
+    criterion_fujisaki_okamoto_benchmark,
 );
 criterion_main!(benches);
@@ -0,0 +1,356 @@
+//! PEM-style ASCII armor for the datastructures of every scheme.
+//!
+//! Armored objects can be exchanged over channels that only carry text, such as email.
+//! The body of the armor is the base64 encoding of the [`envelope`] of the object,
+//! followed by a CRC-24 checksum of the envelope as used by OpenPGP (RFC 4880):
+//!
+//! ```text
+//! -----BEGIN IBE KILTZ-VAHLIS CIPHERTEXT-----
+//! SUJFAAEDCAAAAJCZUkvu1KVflVLLwFH0PHSzV91/SzjxowOKzFLvCmKcWzt1LQUj
+//! HAzIWxhtagoYfJwMOy+1a0eU74GphPt4J68h5qS3JGzwAg9BJYy0WZYhcyZKbTcE
+//! lbHHW527ZStHzJmZGRf/XY8qa5bNQuDEM5IiRIIDVlqF3tj6lse9eZh/QapOgeC/
+//! uaQVPX4DZ/VoPFY=
+//! =JU3l
+//! -----END IBE KILTZ-VAHLIS CIPHERTEXT-----
+//! ```
+//!
+//! The label names the scheme and object type, and is checked against the envelope when parsing.
+//!
+//! ```
+//! use ibe::armor;
+//! use ibe::kiltz_vahlis_one::*;
+//!
+//! let mut rng = rand::thread_rng();
+//! let (pk, _) = setup(&mut rng);
+//!
+//! let text = armor::encode(&pk);
+//! assert!(text.starts_with("-----BEGIN IBE KILTZ-VAHLIS PUBLIC KEY-----\n"));
+//! let pk2 = armor::decode::<PublicKey>(&text).unwrap();
+//! assert_eq!(pk2.to_bytes()[..], pk.to_bytes()[..]);
+//! ```
+//!
+//! Requires the `armor` feature.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use base64ct::{Base64, Encoding};
+use core::convert::TryFrom;
+
+use crate::envelope::{self, Enveloped, Object, ObjectType, Scheme};
+use crate::util::Wipe;
+use crate::Error;
+
+const BEGIN: &str = "-----BEGIN IBE ";
+const END: &str = "-----END IBE ";
+const DASHES: &str = "-----";
+
+/// Number of bytes encoded per line, resulting in lines of 64 characters.
+const LINE_BYTES: usize = 48;
+
+const SCHEMES: [(Scheme, &str); 6] = [
+    (Scheme::Waters, "WATERS"),
+    (Scheme::WatersNaccache, "WATERS-NACCACHE"),
+    (Scheme::KiltzVahlisOne, "KILTZ-VAHLIS"),
+    (Scheme::BonehFranklin, "BONEH-FRANKLIN"),
+    (Scheme::BonehBoyen1, "BONEH-BOYEN"),
+    (Scheme::BonehBoyenGoh, "BONEH-BOYEN-GOH"),
+];
+
+const OBJECTS: [(ObjectType, &str); 10] = [
+    (ObjectType::PublicKey, "PUBLIC KEY"),
+    (ObjectType::CompactPublicKey, "COMPACT PUBLIC KEY"),
+    (ObjectType::SecretKey, "SECRET KEY"),
+    (ObjectType::UserSecretKey, "USER SECRET KEY"),
+    (ObjectType::Identity, "IDENTITY"),
+    (ObjectType::Message, "MESSAGE"),
+    (ObjectType::SymmetricKey, "SYMMETRIC KEY"),
+    (ObjectType::CipherText, "CIPHERTEXT"),
+    (ObjectType::BasicCipherText, "BASIC CIPHERTEXT"),
+    (ObjectType::KemCipherText, "KEM CIPHERTEXT"),
+];
+
+fn scheme_label(scheme: Scheme) -> &'static str {
+    SCHEMES.iter().find(|(s, _)| *s == scheme).unwrap().1
+}
+
+fn object_label(object: ObjectType) -> &'static str {
+    OBJECTS.iter().find(|(o, _)| *o == object).unwrap().1
+}
+
+/// Parse a label of the form `<SCHEME> <OBJECT TYPE>`.
+fn parse_label(label: &str) -> Option<(Scheme, ObjectType)> {
+    let (scheme, object) = label.split_at(label.find(' ')?);
+    let scheme = SCHEMES.iter().find(|(_, l)| *l == scheme)?.0;
+    let object = OBJECTS.iter().find(|(_, l)| *l == &object[1..])?.0;
+    Some((scheme, object))
+}
+
+/// CRC-24 checksum as specified in RFC 4880.
+///
+/// Does not branch on the input, as the checksummed envelope may contain a secret.
+fn crc24(data: &[u8]) -> u32 {
+    let mut crc: u32 = 0xB7_04CE;
+    for b in data {
+        crc ^= (*b as u32) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            crc ^= 0x186_4CFB & 0u32.wrapping_sub((crc >> 24) & 1);
+        }
+    }
+    crc & 0xFF_FFFF
+}
+
+/// Armor an object.
+pub fn encode<T: Enveloped>(value: &T) -> String
+where
+    T::Output: AsMut<[u8]>,
+{
+    let mut bytes = envelope::encode(value);
+    let label = [scheme_label(T::SCHEME), " ", object_label(T::OBJECT)].concat();
+
+    // Sized up front, such that armored secrets are not left behind in reallocated memory.
+    let lines = bytes.chunks(LINE_BYTES).len();
+    let capacity = BEGIN.len()
+        + END.len()
+        + 2 * (label.len() + DASHES.len() + 1)
+        + Base64::encoded_len(&bytes)
+        + lines
+        + "=AAAA\n".len();
+    let mut res = String::with_capacity(capacity);
+    res.push_str(BEGIN);
+    res.push_str(&label);
+    res.push_str(DASHES);
+    res.push('\n');
+
+    let mut buf = [0u8; LINE_BYTES / 3 * 4];
+    for chunk in bytes.chunks(LINE_BYTES) {
+        res.push_str(Base64::encode(chunk, &mut buf).expect("line fits in buffer"));
+        res.push('\n');
+    }
+    buf.wipe();
+
+    let crc = crc24(&bytes).to_be_bytes();
+    let mut crc_buf = [0u8; 4];
+    res.push('=');
+    res.push_str(Base64::encode(&crc[1..], &mut crc_buf).expect("checksum fits in buffer"));
+    res.push('\n');
+
+    res.push_str(END);
+    res.push_str(&label);
+    res.push_str(DASHES);
+    res.push('\n');
+
+    bytes.wipe();
+    res
+}
+
+/// Parse the armor, returning the scheme and object type of the label and the envelope.
+fn unarmor(text: &str) -> Result<(Scheme, ObjectType, Vec<u8>), Error> {
+    let mut lines = text.trim().lines().map(str::trim_end);
+
+    let label = lines
+        .next()
+        .and_then(|l| l.strip_prefix(BEGIN))
+        .and_then(|l| l.strip_suffix(DASHES))
+        .ok_or(Error::InvalidArmor)?;
+    let (scheme, object) = parse_label(label).ok_or(Error::InvalidArmor)?;
+
+    let end = lines
+        .next_back()
+        .and_then(|l| l.strip_prefix(END))
+        .and_then(|l| l.strip_suffix(DASHES));
+    if end != Some(label) {
+        return Err(Error::InvalidArmor);
+    }
+
+    let checksum = lines
+        .next_back()
+        .and_then(|l| l.strip_prefix('='))
+        .ok_or(Error::InvalidArmor)?;
+    let mut crc = [0u8; 4];
+    Base64::decode(checksum, &mut crc[1..]).map_err(|_| Error::InvalidArmor)?;
+
+    let mut body = String::with_capacity(text.len());
+    for line in lines {
+        body.push_str(line);
+    }
+
+    let mut bytes = alloc::vec![0u8; body.len() / 4 * 3];
+    let len = Base64::decode(&body, &mut bytes).map(|b| b.len());
+    // The body of an armored secret contains the secret as well.
+    let mut body = body.into_bytes();
+    body.wipe();
+
+    let len = match len {
+        Ok(len) => len,
+        Err(_) => {
+            bytes.wipe();
+            return Err(Error::InvalidArmor);
+        }
+    };
+    bytes.truncate(len);
+
+    if crc24(&bytes) != u32::from_be_bytes(crc) {
+        bytes.wipe();
+        return Err(Error::ChecksumMismatch);
+    }
+
+    Ok((scheme, object, bytes))
+}
+
+/// Parse an armored object of a known type.
+///
+/// Rejects armor labelled with another object type, or an object of another scheme.
+pub fn decode<T: Enveloped>(text: &str) -> Result<T, Error>
+where
+    T::Output: AsMut<[u8]> + for<'a> TryFrom<&'a [u8]>,
+{
+    let (scheme, object, mut bytes) = unarmor(text)?;
+    if (scheme, object) != (T::SCHEME, T::OBJECT) {
+        bytes.wipe();
+        return Err(Error::UnexpectedObject { scheme, object });
+    }
+
+    let res = envelope::decode::<T>(&bytes);
+    bytes.wipe();
+    res
+}
+
+/// Parse an armored object, dispatching on the label.
+///
+/// Rejects armor of which the label does not match the enveloped object.
+pub fn decode_any(text: &str) -> Result<Object, Error> {
+    let (scheme, object, mut bytes) = unarmor(text)?;
+    let res = envelope::decode_any(&bytes);
+    bytes.wipe();
+
+    let res = res?;
+    if (res.scheme(), res.object_type()) != (scheme, object) {
+        return Err(Error::InvalidArmor);
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kiltz_vahlis_one::*;
+    use crate::util::XofRng;
+
+    const SEED: [u8; 32] = [7u8; 32];
+    const ID: &str = "email:w.geraedts@sarif.nl";
+
+    #[test]
+    fn checksum() {
+        assert_eq!(crc24(b""), 0xB7_04CE);
+        assert_eq!(crc24(b"123456789"), 0x21_CF02);
+    }
+
+    #[test]
+    fn frozen_armor() {
+        let mut rng = XofRng::new(b"ibe-envelope-test-ciphertext", &SEED);
+        let kid = Identity::derive_str(ID);
+        let (pk, _) = setup_from_seed(&SEED);
+        let (c, _) = encrypt(&pk, &kid, &mut rng);
+
+        assert_eq!(
+            encode(&c),
+            "-----BEGIN IBE KILTZ-VAHLIS CIPHERTEXT-----\n\
+             SUJFAAEDCAAAAJCZUkvu1KVflVLLwFH0PHSzV91/SzjxowOKzFLvCmKcWzt1LQUj\n\
+             HAzIWxhtagoYfJwMOy+1a0eU74GphPt4J68h5qS3JGzwAg9BJYy0WZYhcyZKbTcE\n\
+             lbHHW527ZStHzJmZGRf/XY8qa5bNQuDEM5IiRIIDVlqF3tj6lse9eZh/QapOgeC/\n\
+             uaQVPX4DZ/VoPFY=\n\
+             =JU3l\n\
+             -----END IBE KILTZ-VAHLIS CIPHERTEXT-----\n"
+        );
+    }
+
+    #[test]
+    fn labels() {
+        for (scheme, label) in SCHEMES.iter() {
+            for (object, object_label) in OBJECTS.iter() {
+                let label = [*label, " ", *object_label].concat();
+                assert_eq!(parse_label(&label), Some((*scheme, *object)));
+            }
+        }
+        assert_eq!(parse_label("KILTZ-VAHLIS"), None);
+        assert_eq!(parse_label("KILTZ-VAHLIS PUBLIC  KEY"), None);
+    }
+
+    #[test]
+    fn roundtrip() {
+        let mut rng = XofRng::new(b"ibe-armor-test", &SEED);
+        let kid = Identity::derive_str(ID);
+        let (pk, sk) = setup_from_seed(&SEED);
+        let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+        let (c, k) = encrypt(&pk, &kid, &mut rng);
+
+        let text = encode(&c);
+        assert!(text.starts_with("-----BEGIN IBE KILTZ-VAHLIS CIPHERTEXT-----\n"));
+        assert!(text.ends_with("\n-----END IBE KILTZ-VAHLIS CIPHERTEXT-----\n"));
+        assert!(text.lines().all(|l| l.len() <= 64));
+        assert_eq!(text.len(), text.capacity());
+        assert_eq!(decode::<CipherText>(&text), Ok(c));
+
+        // Line endings and surrounding whitespace are ignored.
+        let crlf = std::format!("\n  {}\n", text.replace('\n', "\r\n"));
+        assert_eq!(decode::<CipherText>(&crlf), Ok(c));
+
+        let usk2 = match decode_any(&encode(&usk)) {
+            Ok(Object::KiltzVahlisOneUserSecretKey(usk2)) => usk2,
+            _ => panic!("expected a Kiltz-Vahlis IBE1 user secret key"),
+        };
+        assert_eq!(decrypt(&usk2, &c), k);
+
+        let pk2 = decode::<PublicKey>(&encode(&pk)).unwrap();
+        assert_eq!(pk2.to_bytes()[..], pk.to_bytes()[..]);
+    }
+
+    #[test]
+    fn rejects_invalid() {
+        let (pk, _) = setup_from_seed(&SEED);
+        let text = encode(&pk);
+
+        assert_eq!(
+            decode::<CompactPublicKey>(&text).err(),
+            Some(Error::UnexpectedObject {
+                scheme: Scheme::KiltzVahlisOne,
+                object: ObjectType::PublicKey,
+            })
+        );
+        assert_eq!(
+            decode::<crate::waters::PublicKey>(&text).err(),
+            Some(Error::UnexpectedObject {
+                scheme: Scheme::KiltzVahlisOne,
+                object: ObjectType::PublicKey,
+            })
+        );
+
+        // Flip a bit in the first character of the body.
+        let start = text.find('\n').unwrap() + 1;
+        let mut corrupted = text.clone().into_bytes();
+        corrupted[start] ^= 0x01;
+        let corrupted = String::from_utf8(corrupted).unwrap();
+        assert_eq!(
+            decode::<PublicKey>(&corrupted).err(),
+            Some(Error::ChecksumMismatch)
+        );
+
+        let relabeled = text.replace("PUBLIC KEY", "COMPACT PUBLIC KEY");
+        assert!(matches!(decode_any(&relabeled), Err(Error::InvalidArmor)));
+
+        let truncated = text.replace("-----END IBE KILTZ-VAHLIS PUBLIC KEY-----\n", "");
+        assert_eq!(
+            decode::<PublicKey>(&truncated).err(),
+            Some(Error::InvalidArmor)
+        );
+
+        let unchecked = text
+            .lines()
+            .filter(|l| !l.starts_with('='))
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(matches!(decode_any(&unchecked), Err(Error::InvalidArmor)));
+        assert!(matches!(decode_any("garbage"), Err(Error::InvalidArmor)));
+    }
+}
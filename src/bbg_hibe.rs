@@ -0,0 +1,1235 @@
+//! Hierarchical Identity Based Encryption Boneh-Boyen-Goh scheme on the [BLS12-381 pairing-friendly elliptic curve](https://github.com/zkcrypto/bls12_381).
+//!  * From: "[Hierarchical Identity Based Encryption with Constant Size Ciphertext](https://eprint.iacr.org/2005/015)"
+//!  * Published in: EUROCRYPT, 2005
+//!
+//! Identities are paths of up to [`MAX_DEPTH`] components, such as `["acme", "finance", "alice"]`.
+//! The maximum depth of a particular deployment is chosen during [`setup`].
+//! A user secret key for an identity can [`delegate`] keys for all identities below it,
+//! whereas ciphertexts are of constant size regardless of the depth.
+//!
+//! Uses [SHA3-512](https://crates.io/crates/tiny-keccak) for hashing identity components to scalars.
+//!
+//! The structure of the byte serialisation of the various datastructures is not guaranteed
+//! to remain constant between releases of this library.
+//! All operations in this library are implemented to run in constant time.
+
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use rand::{CryptoRng, RngCore};
+use subtle::{Choice, ConstantTimeEq, CtOption};
+
+#[cfg(feature = "alloc")]
+use crate::table::{G2Table, SCALAR_WINDOWS};
+use crate::util::*;
+use crate::validation::*;
+use crate::{Compress, DerandomizedIBE, Derive, Error, IBE};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use irmaseal_curve::{G1Affine, G1Projective, G2Affine, G2Projective, Gt, Scalar};
+
+/// Maximum supported depth of the identity hierarchy.
+pub const MAX_DEPTH: usize = 8;
+
+const IDENTITYSIZE: usize = 1 + MAX_DEPTH * 32;
+const PUBLICKEYSIZE: usize = 1 + (2 + MAX_DEPTH) * 48 + (2 + MAX_DEPTH) * 96 + 288;
+const USERSECRETKEYSIZE: usize = IDENTITYSIZE + (2 + MAX_DEPTH) * 96;
+
+const SEED_DST: &[u8] = b"ibe-bbg-hibe-setup-seed";
+
+/// Public key parameters generated by the PKG used to encrypt messages and delegate keys.
+///
+/// Contains the parameters both on G1, for encryption, and on G2, for delegation.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PublicKey {
+    max_depth: usize,
+    g: G1Affine,
+    g3: G1Affine,
+    h: [G1Affine; MAX_DEPTH],
+    ghat: G2Affine,
+    g3hat: G2Affine,
+    hhat: [G2Affine; MAX_DEPTH],
+    z: Gt,
+}
+
+/// Secret key parameter generated by the PKG used to extract user secret keys.
+#[derive(Clone)]
+pub struct SecretKey {
+    msk: G2Affine,
+}
+
+zeroize_on_drop!(SecretKey: msk);
+impl_secret!(SecretKey: msk);
+impl_try_from_secret!(SecretKey, 96);
+
+/// Points on G2 that form the user secret key, along with the identity it belongs to.
+///
+/// Holds a delegation component for every level below the identity.
+#[derive(Clone)]
+pub struct UserSecretKey {
+    id: Identity,
+    d0: G2Affine,
+    d1: G2Affine,
+    b: [G2Affine; MAX_DEPTH],
+}
+
+zeroize_on_drop!(UserSecretKey: d0, d1, b);
+impl_secret!(UserSecretKey: id, d0, d1, b);
+impl_try_from_secret!(UserSecretKey, USERSECRETKEYSIZE);
+
+/// Hierarchical identity, consisting of one field parameter per level.
+///
+/// Each component is effectively a hash of the respective part of the identity, mapped to the curve field.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Identity {
+    depth: usize,
+    ids: [Scalar; MAX_DEPTH],
+}
+
+/// A point on the paired curve that can be encrypted and decrypted.
+///
+/// Use [`derive_key`](Self::derive_key) to derive symmetric keys, such as for AES.
+#[derive(Clone)]
+pub struct Message(Gt);
+
+zeroize_on_drop!(Message: 0);
+impl_secret!(Message: 0);
+
+/// Encrypted message. Can only be decrypted with an user secret key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CipherText {
+    a: Gt,
+    b: G1Affine,
+    c: G1Affine,
+}
+
+/// Public key supporting identities of the full [`MAX_DEPTH`] levels, as used by the [`IBE`] implementation.
+///
+/// As every identity fits within such a public key, extraction and encryption through the trait cannot fail.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FullDepthPublicKey(PublicKey);
+
+/// The Boneh-Boyen-Goh hierarchical identity-based encryption scheme.
+///
+/// Implements [`IBE`] using [`setup`] with [`MAX_DEPTH`] levels.
+#[derive(Clone, Copy, Debug)]
+pub struct BonehBoyenGoh;
+
+/// Generate a keypair used by the Private Key Generator (PKG),
+/// supporting identities of at most `max_depth` levels.
+///
+/// # Panics
+///
+/// If `max_depth` is zero or exceeds [`MAX_DEPTH`].
+pub fn setup<R: RngCore + CryptoRng>(max_depth: usize, rng: &mut R) -> (PublicKey, SecretKey) {
+    assert!(
+        max_depth > 0 && max_depth <= MAX_DEPTH,
+        "unsupported hierarchy depth"
+    );
+
+    let g: G1Affine = rand_g1(rng).into();
+    let ghat: G2Affine = rand_g2(rng).into();
+
+    let mut alpha = rand_scalar(rng);
+    let g2: G2Affine = rand_g2(rng).into();
+    let msk: G2Affine = (g2 * alpha).into();
+    alpha.wipe();
+
+    let gamma = rand_scalar(rng);
+    let g3 = (g * gamma).into();
+    let g3hat = (ghat * gamma).into();
+
+    let mut h = [G1Affine::identity(); MAX_DEPTH];
+    let mut hhat = [G2Affine::identity(); MAX_DEPTH];
+    for (hi, hhati) in h.iter_mut().zip(hhat.iter_mut()).take(max_depth) {
+        let delta = rand_scalar(rng);
+        *hi = (g * delta).into();
+        *hhati = (ghat * delta).into();
+    }
+
+    let z = irmaseal_curve::pairing(&g, &msk);
+
+    let pk = PublicKey {
+        max_depth,
+        g,
+        g3,
+        h,
+        ghat,
+        g3hat,
+        hhat,
+        z,
+    };
+
+    (pk, SecretKey { msk })
+}
+
+/// Deterministically derive a keypair for the PKG supporting identities up to `max_depth` levels from a seed.
+///
+/// The same seed always results in the same keypair, hence the seed must be kept as secret as the master secret key.
+///
+/// # Panics
+///
+/// If `max_depth` is zero or exceeds [`MAX_DEPTH`].
+pub fn setup_from_seed(max_depth: usize, seed: &[u8; 32]) -> (PublicKey, SecretKey) {
+    setup(max_depth, &mut XofRng::new(SEED_DST, seed))
+}
+
+/// Common operation used in encryption to entangle the identity with the public key parameters on G1.
+fn entangle_g1(pk: &PublicKey, v: &Identity) -> G1Projective {
+    let mut res = G1Projective::from(pk.g3);
+    for (hi, vi) in pk.h.iter().zip(v.ids.iter()).take(v.depth) {
+        res += hi * vi;
+    }
+    res
+}
+
+/// Common operation used in extraction and delegation to entangle the identity with the public key parameters on G2.
+fn entangle_g2(pk: &PublicKey, v: &Identity) -> G2Projective {
+    let mut res = G2Projective::from(pk.g3hat);
+    for (hhati, vi) in pk.hhat.iter().zip(v.ids.iter()).take(v.depth) {
+        res += hhati * vi;
+    }
+    res
+}
+
+/// Extract an user secret key for a given identity.
+///
+/// Returns `None` if the identity is deeper than the maximum depth of the public key.
+pub fn extract_usk<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    sk: &SecretKey,
+    v: &Identity,
+    rng: &mut R,
+) -> Option<UserSecretKey> {
+    if v.depth > pk.max_depth {
+        return None;
+    }
+
+    Some(extract_usk_unchecked(pk, sk, v, rng))
+}
+
+/// Extract an user secret key for an identity that is known to fit within the public key.
+fn extract_usk_unchecked<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    sk: &SecretKey,
+    v: &Identity,
+    rng: &mut R,
+) -> UserSecretKey {
+    let mut r = rand_scalar(rng);
+
+    let d0 = (entangle_g2(pk, v) * r + sk.msk).into();
+    let d1 = (pk.ghat * r).into();
+
+    let mut b = [G2Affine::identity(); MAX_DEPTH];
+    for (bj, hhatj) in b
+        .iter_mut()
+        .zip(pk.hhat.iter())
+        .take(pk.max_depth)
+        .skip(v.depth)
+    {
+        *bj = (hhatj * r).into();
+    }
+    r.wipe();
+
+    UserSecretKey { id: *v, d0, d1, b }
+}
+
+/// Extract user secret keys for many identities at once.
+///
+/// Precomputes fixed-base tables for `ghat` and the parameters `hhat` of every level of the hierarchy,
+/// which are shared by all identities. All keys are converted to affine coordinates using a single
+/// inversion per component, and are extracted in parallel when the `rayon` feature is enabled.
+/// Given the same randomness, results in the same keys as calling [`extract_usk`] for every identity in order.
+///
+/// Returns `None` if any of the identities is deeper than the maximum depth of the public key.
+///
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+pub fn extract_usk_batch<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    sk: &SecretKey,
+    ids: &[Identity],
+    rng: &mut R,
+) -> Option<Vec<UserSecretKey>> {
+    if ids.iter().any(|v| v.depth > pk.max_depth) {
+        return None;
+    }
+
+    let ghat = G2Table::fixed_base(&pk.ghat, SCALAR_WINDOWS);
+    let hhat: Vec<G2Table> = pk
+        .hhat
+        .iter()
+        .take(pk.max_depth)
+        .map(|hhatj| G2Table::fixed_base(hhatj, SCALAR_WINDOWS))
+        .collect();
+    let mut rs = rand_scalars(ids.len(), rng);
+
+    let mut d0 = batch_map(ids, |i, v| {
+        let mut e = G2Projective::from(pk.g3hat);
+        for (hhatj, vj) in hhat.iter().zip(v.ids.iter()).take(v.depth) {
+            e += hhatj.mul(vj);
+        }
+        e * rs[i] + sk.msk
+    });
+    let mut d1 = batch_map(&rs, |_, r| ghat.mul(r));
+    let mut bs = batch_map(ids, |i, v| {
+        let mut b = [G2Projective::identity(); MAX_DEPTH];
+        for (bj, hhatj) in b.iter_mut().zip(hhat.iter()).skip(v.depth) {
+            *bj = hhatj.mul(&rs[i]);
+        }
+        b
+    });
+    rs.wipe();
+
+    let mut b: Vec<G2Projective> = bs.iter().flatten().copied().collect();
+    bs.wipe();
+    let mut d0 = batch_normalize_g2(&mut d0);
+    let mut d1 = batch_normalize_g2(&mut d1);
+    let mut b = batch_normalize_g2(&mut b);
+    let res = ids
+        .iter()
+        .zip(d0.iter().zip(d1.iter()))
+        .zip(b.chunks(MAX_DEPTH))
+        .map(|((v, (d0, d1)), bs)| {
+            let mut b = [G2Affine::identity(); MAX_DEPTH];
+            b.copy_from_slice(bs);
+            UserSecretKey {
+                id: *v,
+                d0: *d0,
+                d1: *d1,
+                b,
+            }
+        })
+        .collect();
+    d0.wipe();
+    d1.wipe();
+    b.wipe();
+
+    Some(res)
+}
+
+/// Delegate an user secret key for an identity below the identity of the given user secret key.
+///
+/// The resulting key is distributed identically to a key extracted by the PKG.
+/// Passing the identity of the user secret key itself re-randomizes the key.
+///
+/// Returns `None` if the identity is not below the identity of the user secret key,
+/// or if it is deeper than the maximum depth of the public key.
+pub fn delegate<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    usk: &UserSecretKey,
+    v: &Identity,
+    rng: &mut R,
+) -> Option<UserSecretKey> {
+    if v.depth > pk.max_depth || !usk.id.is_prefix_of(v) {
+        return None;
+    }
+
+    let mut t = rand_scalar(rng);
+
+    let mut d0 = entangle_g2(pk, v) * t + usk.d0;
+    for j in usk.id.depth..v.depth {
+        d0 += usk.b[j] * v.ids[j];
+    }
+    let d1 = (pk.ghat * t + usk.d1).into();
+
+    let mut b = [G2Affine::identity(); MAX_DEPTH];
+    for ((bj, hhatj), uskbj) in b
+        .iter_mut()
+        .zip(pk.hhat.iter())
+        .zip(usk.b.iter())
+        .take(pk.max_depth)
+        .skip(v.depth)
+    {
+        *bj = (hhatj * t + uskbj).into();
+    }
+    t.wipe();
+
+    let res = UserSecretKey {
+        id: *v,
+        d0: d0.into(),
+        d1,
+        b,
+    };
+    d0.wipe();
+
+    Some(res)
+}
+
+/// Encrypt a message for an identity that is known to fit within the public key.
+fn encrypt_with_randomness(pk: &PublicKey, v: &Identity, m: &Message, s: &Scalar) -> CipherText {
+    let a = pk.z * s + m.0;
+    let b = (pk.g * s).into();
+    let c = (entangle_g1(pk, v) * s).into();
+
+    CipherText { a, b, c }
+}
+
+fn encrypt_unchecked<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    v: &Identity,
+    m: &Message,
+    rng: &mut R,
+) -> CipherText {
+    let mut s = rand_scalar(rng);
+    let c = encrypt_with_randomness(pk, v, m, &s);
+    s.wipe();
+
+    c
+}
+
+/// Encrypt a message using the PKG public key and an identity.
+///
+/// Returns `None` if the identity is deeper than the maximum depth of the public key.
+pub fn encrypt<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    v: &Identity,
+    m: &Message,
+    rng: &mut R,
+) -> Option<CipherText> {
+    if v.depth > pk.max_depth {
+        return None;
+    }
+
+    Some(encrypt_unchecked(pk, v, m, rng))
+}
+
+/// Decrypt ciphertext to a message using a user secret key.
+///
+/// Only yields the original message if the user secret key belongs to the exact identity used to encrypt.
+pub fn decrypt(usk: &UserSecretKey, c: &CipherText) -> Message {
+    let num = irmaseal_curve::pairing(&c.c, &usk.d1);
+    let dem = irmaseal_curve::pairing(&c.b, &usk.d0);
+
+    Message(c.a + num - dem)
+}
+
+impl ConstantTimeEq for Identity {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.depth.ct_eq(&other.depth) & self.ids[..].ct_eq(&other.ids[..])
+    }
+}
+
+impl Identity {
+    /// Hash a byte slice to a top-level identity.
+    /// Uses sha3-512 internally.
+    pub fn derive(b: &[u8]) -> Identity {
+        let mut ids = [Scalar::zero(); MAX_DEPTH];
+        ids[0] = Scalar::from_bytes_wide(&sha3_512(b));
+
+        Identity { depth: 1, ids }
+    }
+
+    /// Hash a string slice to a top-level identity.
+    /// Directly converts characters to UTF-8 byte representation.
+    pub fn derive_str(s: &str) -> Identity {
+        Self::derive(s.as_bytes())
+    }
+
+    /// Hash a path of byte slices to a hierarchical identity, starting at the top level.
+    ///
+    /// Returns `None` if the path is empty or longer than [`MAX_DEPTH`].
+    pub fn derive_path<T: AsRef<[u8]>>(path: &[T]) -> Option<Identity> {
+        let (first, rest) = path.split_first()?;
+
+        rest.iter()
+            .try_fold(Self::derive(first.as_ref()), |v, b| v.child(b.as_ref()))
+    }
+
+    /// Derive the identity one level below this identity.
+    ///
+    /// Returns `None` if this identity is already at [`MAX_DEPTH`].
+    pub fn child(&self, b: &[u8]) -> Option<Identity> {
+        if self.depth == MAX_DEPTH {
+            return None;
+        }
+
+        let mut res = *self;
+        res.ids[self.depth] = Scalar::from_bytes_wide(&sha3_512(b));
+        res.depth += 1;
+
+        Some(res)
+    }
+
+    /// The number of levels of this identity.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    fn is_prefix_of(&self, other: &Identity) -> bool {
+        self.depth <= other.depth && self.ids[..self.depth] == other.ids[..self.depth]
+    }
+
+    pub fn to_bytes(&self) -> [u8; IDENTITYSIZE] {
+        let mut res = [0u8; IDENTITYSIZE];
+        let (depth, ids) = mut_array_refs![&mut res, 1, MAX_DEPTH * 32];
+        depth[0] = self.depth as u8;
+        for (i, vi) in self.ids.iter().enumerate() {
+            *array_mut_ref![ids, i * 32, 32] = vi.to_bytes();
+        }
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; IDENTITYSIZE]) -> CtOption<Self> {
+        let (id, is_some) = Self::from_bytes_inner(bytes);
+        CtOption::new(id, is_some)
+    }
+
+    fn from_bytes_inner(bytes: &[u8; IDENTITYSIZE]) -> (Self, Choice) {
+        let (depth, ids) = array_refs![bytes, 1, MAX_DEPTH * 32];
+        let depth = depth[0] as usize;
+
+        let mut res = [Scalar::zero(); MAX_DEPTH];
+        let mut is_some = Choice::from((depth > 0 && depth <= MAX_DEPTH) as u8);
+        for (i, ri) in res.iter_mut().enumerate() {
+            is_some &= Scalar::from_bytes(array_ref![ids, i * 32, 32])
+                .map(|s| {
+                    *ri = s;
+                })
+                .is_some();
+
+            // Unused levels must be zero for the encoding to be canonical.
+            if i >= depth {
+                is_some &= ri.ct_eq(&Scalar::zero());
+            }
+        }
+
+        (Identity { depth, ids: res }, is_some)
+    }
+}
+
+impl Message {
+    /// Generate a random point on the paired curve.
+    pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        Self(rand_gt(rng))
+    }
+
+    pub fn to_bytes(&self) -> [u8; 288] {
+        self.0.to_compressed()
+    }
+
+    pub fn from_bytes(bytes: &[u8; 288]) -> CtOption<Self> {
+        Gt::from_compressed(bytes).map(Message)
+    }
+
+    /// Derive symmetric key material bound to `c` and `info`, see [key derivation](crate#key-derivation).
+    pub fn derive_key(&self, c: &CipherText, info: &[u8], out: &mut [u8]) {
+        kdf(&self.to_bytes(), &c.to_bytes(), info, out)
+    }
+}
+
+impl PublicKey {
+    /// Validate a public key obtained from an untrusted source.
+    ///
+    /// Checks that all points of the levels up to the maximum depth are in the prime order subgroup
+    /// and that none of them is the identity. Additionally checks that the points on G2 have the same
+    /// discrete logarithms as their counterparts on G1, using pairings.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        check_g1(&self.g, "g", None)?;
+        check_g1(&self.g3, "g3", None)?;
+        check_g2(&self.ghat, "ghat", None)?;
+        check_g2(&self.g3hat, "g3hat", None)?;
+
+        let consistent = |p: &G1Affine, q: &G2Affine| {
+            irmaseal_curve::pairing(p, &self.ghat) == irmaseal_curve::pairing(&self.g, q)
+        };
+
+        if !consistent(&self.g3, &self.g3hat) {
+            return Err(ValidationError::new(
+                "g3hat",
+                None,
+                ValidationErrorKind::Inconsistent,
+            ));
+        }
+
+        for (i, (hi, hhati)) in self
+            .h
+            .iter()
+            .zip(self.hhat.iter())
+            .take(self.max_depth)
+            .enumerate()
+        {
+            check_g1(hi, "h", Some(i))?;
+            check_g2(hhati, "hhat", Some(i))?;
+            if !consistent(hi, hhati) {
+                return Err(ValidationError::new(
+                    "hhat",
+                    Some(i),
+                    ValidationErrorKind::Inconsistent,
+                ));
+            }
+        }
+
+        check_gt(&self.z, "z")
+    }
+
+    /// The maximum depth of identities supported by this public key.
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    pub fn to_bytes(&self) -> [u8; PUBLICKEYSIZE] {
+        let mut res = [0u8; PUBLICKEYSIZE];
+        let (max_depth, g, g3, h, ghat, g3hat, hhat, z) = mut_array_refs![
+            &mut res,
+            1,
+            48,
+            48,
+            MAX_DEPTH * 48,
+            96,
+            96,
+            MAX_DEPTH * 96,
+            288
+        ];
+        max_depth[0] = self.max_depth as u8;
+        *g = self.g.to_compressed();
+        *g3 = self.g3.to_compressed();
+        for (i, hi) in self.h.iter().enumerate() {
+            *array_mut_ref![h, i * 48, 48] = hi.to_compressed();
+        }
+        *ghat = self.ghat.to_compressed();
+        *g3hat = self.g3hat.to_compressed();
+        for (i, hhati) in self.hhat.iter().enumerate() {
+            *array_mut_ref![hhat, i * 96, 96] = hhati.to_compressed();
+        }
+        *z = self.z.to_compressed();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; PUBLICKEYSIZE]) -> CtOption<Self> {
+        let (max_depth, g, g3, h, ghat, g3hat, hhat, z) = array_refs![
+            bytes,
+            1,
+            48,
+            48,
+            MAX_DEPTH * 48,
+            96,
+            96,
+            MAX_DEPTH * 96,
+            288
+        ];
+        let max_depth = max_depth[0] as usize;
+        let mut is_some = Choice::from((max_depth > 0 && max_depth <= MAX_DEPTH) as u8);
+
+        let mut hres = [G1Affine::identity(); MAX_DEPTH];
+        for (i, ri) in hres.iter_mut().enumerate() {
+            is_some &= G1Affine::from_compressed(array_ref![h, i * 48, 48])
+                .map(|s| {
+                    *ri = s;
+                })
+                .is_some();
+
+            // Unused levels must be the identity for the encoding to be canonical.
+            if i >= max_depth {
+                is_some &= ri.is_identity();
+            }
+        }
+
+        let mut hhatres = [G2Affine::identity(); MAX_DEPTH];
+        for (i, ri) in hhatres.iter_mut().enumerate() {
+            is_some &= G2Affine::from_compressed(array_ref![hhat, i * 96, 96])
+                .map(|s| {
+                    *ri = s;
+                })
+                .is_some();
+
+            if i >= max_depth {
+                is_some &= ri.is_identity();
+            }
+        }
+
+        let g = G1Affine::from_compressed(g);
+        let g3 = G1Affine::from_compressed(g3);
+        let ghat = G2Affine::from_compressed(ghat);
+        let g3hat = G2Affine::from_compressed(g3hat);
+        let z = Gt::from_compressed(z);
+
+        g.and_then(|g| {
+            g3.and_then(|g3| {
+                ghat.and_then(|ghat| {
+                    g3hat.and_then(|g3hat| {
+                        z.and_then(|z| {
+                            CtOption::new(
+                                PublicKey {
+                                    max_depth,
+                                    g,
+                                    g3,
+                                    h: hres,
+                                    ghat,
+                                    g3hat,
+                                    hhat: hhatres,
+                                    z,
+                                },
+                                is_some,
+                            )
+                        })
+                    })
+                })
+            })
+        })
+    }
+}
+
+impl SecretKey {
+    pub fn to_bytes(&self) -> [u8; 96] {
+        self.msk.to_compressed()
+    }
+
+    pub fn from_bytes(bytes: &[u8; 96]) -> CtOption<Self> {
+        G2Affine::from_compressed(bytes).map(|msk| SecretKey { msk })
+    }
+}
+
+impl UserSecretKey {
+    /// The identity this user secret key belongs to.
+    pub fn identity(&self) -> &Identity {
+        &self.id
+    }
+
+    pub fn to_bytes(&self) -> [u8; USERSECRETKEYSIZE] {
+        let mut res = [0u8; USERSECRETKEYSIZE];
+        let (id, d0, d1, b) = mut_array_refs![&mut res, IDENTITYSIZE, 96, 96, MAX_DEPTH * 96];
+        *id = self.id.to_bytes();
+        *d0 = self.d0.to_compressed();
+        *d1 = self.d1.to_compressed();
+        for (i, bi) in self.b.iter().enumerate() {
+            *array_mut_ref![b, i * 96, 96] = bi.to_compressed();
+        }
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; USERSECRETKEYSIZE]) -> CtOption<Self> {
+        let (id, d0, d1, b) = array_refs![bytes, IDENTITYSIZE, 96, 96, MAX_DEPTH * 96];
+
+        let (id, mut is_some) = Identity::from_bytes_inner(id);
+        let d0 = G2Affine::from_compressed(d0);
+        let d1 = G2Affine::from_compressed(d1);
+
+        let mut bres = [G2Affine::identity(); MAX_DEPTH];
+        for (i, ri) in bres.iter_mut().enumerate() {
+            is_some &= G2Affine::from_compressed(array_ref![b, i * 96, 96])
+                .map(|s| {
+                    *ri = s;
+                })
+                .is_some();
+
+            // Levels at or above the identity itself carry no delegation component.
+            if i < id.depth {
+                is_some &= ri.is_identity();
+            }
+        }
+
+        d0.and_then(|d0| {
+            d1.and_then(|d1| {
+                CtOption::new(
+                    UserSecretKey {
+                        id,
+                        d0,
+                        d1,
+                        b: bres,
+                    },
+                    is_some,
+                )
+            })
+        })
+    }
+}
+
+impl CipherText {
+    pub fn to_bytes(&self) -> [u8; 384] {
+        let mut res = [0u8; 384];
+        let (a, b, c) = mut_array_refs![&mut res, 288, 48, 48];
+        *a = self.a.to_compressed();
+        *b = self.b.to_compressed();
+        *c = self.c.to_compressed();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; 384]) -> CtOption<Self> {
+        let (a, b, c) = array_refs![bytes, 288, 48, 48];
+
+        let a = Gt::from_compressed(a);
+        let b = G1Affine::from_compressed(b);
+        let c = G1Affine::from_compressed(c);
+
+        a.and_then(|a| b.and_then(|b| c.map(|c| CipherText { a, b, c })))
+    }
+}
+
+impl Derive for Identity {
+    fn derive(b: &[u8]) -> Identity {
+        Identity::derive(b)
+    }
+}
+
+impl FullDepthPublicKey {
+    /// Wrap a public key, returning `None` if it supports fewer than [`MAX_DEPTH`] levels.
+    pub fn new(pk: PublicKey) -> Option<Self> {
+        if pk.max_depth == MAX_DEPTH {
+            Some(FullDepthPublicKey(pk))
+        } else {
+            None
+        }
+    }
+
+    /// The wrapped public key.
+    pub fn public_key(&self) -> &PublicKey {
+        &self.0
+    }
+}
+
+/// Public keys are wrapped in a [`FullDepthPublicKey`], such that identities of any depth are supported.
+/// Use the functions of this module directly for hierarchies of fewer levels.
+impl IBE for BonehBoyenGoh {
+    type PublicKey = FullDepthPublicKey;
+    type SecretKey = SecretKey;
+    type UserSecretKey = UserSecretKey;
+    type Identity = Identity;
+    type Message = Message;
+    type CipherText = CipherText;
+
+    fn setup<R: RngCore + CryptoRng>(rng: &mut R) -> (FullDepthPublicKey, SecretKey) {
+        let (pk, sk) = setup(MAX_DEPTH, rng);
+        (FullDepthPublicKey(pk), sk)
+    }
+
+    fn extract_usk<R: RngCore + CryptoRng>(
+        pk: &FullDepthPublicKey,
+        sk: &SecretKey,
+        v: &Identity,
+        rng: &mut R,
+    ) -> UserSecretKey {
+        extract_usk_unchecked(&pk.0, sk, v, rng)
+    }
+
+    fn encrypt<R: RngCore + CryptoRng>(
+        pk: &FullDepthPublicKey,
+        v: &Identity,
+        m: &Message,
+        rng: &mut R,
+    ) -> CipherText {
+        encrypt_unchecked(&pk.0, v, m, rng)
+    }
+
+    fn decrypt(usk: &UserSecretKey, c: &CipherText) -> Message {
+        decrypt(usk, c)
+    }
+}
+
+impl DerandomizedIBE for BonehBoyenGoh {
+    fn generate_message<R: RngCore + CryptoRng>(rng: &mut R) -> Message {
+        Message::generate(rng)
+    }
+
+    fn encrypt_derandomized(
+        pk: &FullDepthPublicKey,
+        v: &Identity,
+        m: &Message,
+        coins: &[u8; 64],
+    ) -> CipherText {
+        encrypt_with_randomness(&pk.0, v, m, &Scalar::from_bytes_wide(coins))
+    }
+}
+
+impl_serde!(PublicKey, PUBLICKEYSIZE);
+impl_serde!(SecretKey, 96);
+impl_serde!(UserSecretKey, USERSECRETKEYSIZE);
+impl_serde!(Identity, IDENTITYSIZE);
+impl_serde!(Message, 288);
+impl_serde!(CipherText, 384);
+
+impl Compress for PublicKey {
+    type Output = [u8; PUBLICKEYSIZE];
+
+    fn to_bytes(&self) -> [u8; PUBLICKEYSIZE] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; PUBLICKEYSIZE]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for SecretKey {
+    type Output = [u8; 96];
+
+    fn to_bytes(&self) -> [u8; 96] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; 96]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for UserSecretKey {
+    type Output = [u8; USERSECRETKEYSIZE];
+
+    fn to_bytes(&self) -> [u8; USERSECRETKEYSIZE] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; USERSECRETKEYSIZE]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for Identity {
+    type Output = [u8; IDENTITYSIZE];
+
+    fn to_bytes(&self) -> [u8; IDENTITYSIZE] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; IDENTITYSIZE]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for Message {
+    type Output = [u8; 288];
+
+    fn to_bytes(&self) -> [u8; 288] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; 288]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for CipherText {
+    type Output = [u8; 384];
+
+    fn to_bytes(&self) -> [u8; 384] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; 384]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl TryFrom<&[u8]> for PublicKey {
+    type Error = Error;
+
+    /// Like [`PublicKey::from_bytes`], rejects an unsupported maximum depth
+    /// and unused levels that are not the identity as invalid encodings.
+    fn try_from(bytes: &[u8]) -> Result<Self, Error> {
+        let mut d = Decoder::new(bytes, PUBLICKEYSIZE)?;
+
+        let max_depth = d.bytes::<1>()[0] as usize;
+        if max_depth == 0 || max_depth > MAX_DEPTH {
+            return Err(Error::InvalidEncoding {
+                component: "max_depth",
+                index: None,
+            });
+        }
+
+        let g = d.g1("g", None)?;
+        let g3 = d.g1("g3", None)?;
+        let mut h = [G1Affine::identity(); MAX_DEPTH];
+        for (i, hi) in h.iter_mut().enumerate() {
+            *hi = d.g1("h", Some(i))?;
+            if i >= max_depth && !bool::from(hi.is_identity()) {
+                return Err(Error::InvalidEncoding {
+                    component: "h",
+                    index: Some(i),
+                });
+            }
+        }
+
+        let ghat = d.g2("ghat", None)?;
+        let g3hat = d.g2("g3hat", None)?;
+        let mut hhat = [G2Affine::identity(); MAX_DEPTH];
+        for (i, hhati) in hhat.iter_mut().enumerate() {
+            *hhati = d.g2("hhat", Some(i))?;
+            if i >= max_depth && !bool::from(hhati.is_identity()) {
+                return Err(Error::InvalidEncoding {
+                    component: "hhat",
+                    index: Some(i),
+                });
+            }
+        }
+
+        Ok(PublicKey {
+            max_depth,
+            g,
+            g3,
+            h,
+            ghat,
+            g3hat,
+            hhat,
+            z: d.gt("z")?,
+        })
+    }
+}
+
+impl TryFrom<&[u8]> for CipherText {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Error> {
+        let mut d = Decoder::new(bytes, 384)?;
+        Ok(CipherText {
+            a: d.gt("a")?,
+            b: d.g1("b", None)?,
+            c: d.g1("c", None)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATH: [&str; 3] = ["acme", "finance", "w.geraedts@sarif.nl"];
+
+    #[allow(dead_code)]
+    struct DefaultSubResults {
+        kid: Identity,
+        m: Message,
+        pk: PublicKey,
+        sk: SecretKey,
+        usk: UserSecretKey,
+        c: CipherText,
+    }
+
+    fn perform_default() -> DefaultSubResults {
+        let mut rng = rand::thread_rng();
+
+        let kid = Identity::derive_path(&PATH).unwrap();
+
+        let m = Message::generate(&mut rng);
+
+        let (pk, sk) = setup(4, &mut rng);
+        let usk = extract_usk(&pk, &sk, &kid, &mut rng).unwrap();
+
+        let c = encrypt(&pk, &kid, &m, &mut rng).unwrap();
+
+        DefaultSubResults {
+            kid,
+            m,
+            pk,
+            sk,
+            usk,
+            c,
+        }
+    }
+
+    #[test]
+    fn eq_encrypt_decrypt() {
+        let results = perform_default();
+        let m2 = decrypt(&results.usk, &results.c);
+
+        assert_eq!(results.m, m2);
+    }
+
+    #[test]
+    fn eq_derive_key() {
+        let results = perform_default();
+        let (mut key, mut key2) = ([0u8; 32], [0u8; 32]);
+        results.m.derive_key(&results.c, b"test", &mut key);
+        decrypt(&results.usk, &results.c).derive_key(&results.c, b"test", &mut key2);
+        assert_eq!(key, key2);
+    }
+
+    #[test]
+    fn eq_delegate_decrypt() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+
+        let tenant = Identity::derive_str(PATH[0]);
+        let department = tenant.child(PATH[1].as_bytes()).unwrap();
+
+        let usk_tenant = extract_usk(&results.pk, &results.sk, &tenant, &mut rng).unwrap();
+        let usk_department = delegate(&results.pk, &usk_tenant, &department, &mut rng).unwrap();
+        let usk_user = delegate(&results.pk, &usk_department, &results.kid, &mut rng).unwrap();
+        let usk_direct = delegate(&results.pk, &usk_tenant, &results.kid, &mut rng).unwrap();
+
+        assert_eq!(usk_user.identity(), &results.kid);
+        assert_eq!(results.m, decrypt(&usk_user, &results.c));
+        assert_eq!(results.m, decrypt(&usk_direct, &results.c));
+
+        // A key higher up the hierarchy cannot decrypt directly.
+        assert_ne!(results.m, decrypt(&usk_department, &results.c));
+    }
+
+    #[test]
+    fn validate_public_key() {
+        let (pk, _) = setup(3, &mut rand::thread_rng());
+        assert_eq!(pk.validate(), Ok(()));
+
+        let mut inconsistent = pk;
+        inconsistent.hhat[1] = inconsistent.hhat[2];
+        assert_eq!(
+            inconsistent.validate(),
+            Err(ValidationError::new(
+                "hhat",
+                Some(1),
+                ValidationErrorKind::Inconsistent
+            ))
+        );
+    }
+
+    #[test]
+    fn delegate_rejects_non_descendants() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+
+        let sibling = Identity::derive_path(&["acme", "legal"]).unwrap();
+        let parent = Identity::derive_path(&PATH[..2]).unwrap();
+        let deep = Identity::derive_path(&["acme", "finance", "a", "b", "c"]).unwrap();
+
+        assert!(delegate(&results.pk, &results.usk, &sibling, &mut rng).is_none());
+        assert!(delegate(&results.pk, &results.usk, &parent, &mut rng).is_none());
+        assert!(delegate(&results.pk, &results.usk, &deep, &mut rng).is_none());
+    }
+
+    #[test]
+    fn rejects_identities_beyond_max_depth() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+
+        let deep = Identity::derive_path(&["acme", "finance", "a", "b", "c"]).unwrap();
+
+        assert!(extract_usk(&results.pk, &results.sk, &deep, &mut rng).is_none());
+        assert!(encrypt(&results.pk, &deep, &results.m, &mut rng).is_none());
+        #[cfg(feature = "alloc")]
+        assert!(
+            extract_usk_batch(&results.pk, &results.sk, &[results.kid, deep], &mut rng).is_none()
+        );
+    }
+
+    #[test]
+    fn trait_requires_full_depth_public_key() {
+        let mut rng = rand::thread_rng();
+
+        let (pk, _) = setup(4, &mut rng);
+        assert!(FullDepthPublicKey::new(pk).is_none());
+
+        let (pk, sk) = <BonehBoyenGoh as IBE>::setup(&mut rng);
+        assert_eq!(FullDepthPublicKey::new(*pk.public_key()), Some(pk));
+
+        let kid = Identity::derive_path(&["x"; MAX_DEPTH]).unwrap();
+        let m = Message::generate(&mut rng);
+        let usk = <BonehBoyenGoh as IBE>::extract_usk(&pk, &sk, &kid, &mut rng);
+        let c = <BonehBoyenGoh as IBE>::encrypt(&pk, &kid, &m, &mut rng);
+        assert_eq!(<BonehBoyenGoh as IBE>::decrypt(&usk, &c), m);
+    }
+
+    #[test]
+    fn identity_depth() {
+        let kid = Identity::derive_path(&PATH).unwrap();
+        assert_eq!(kid.depth(), PATH.len());
+
+        let empty: [&str; 0] = [];
+        assert!(Identity::derive_path(&empty).is_none());
+        assert!(Identity::derive_path(&["x"; MAX_DEPTH + 1]).is_none());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn batch_matches_sequential() {
+        let (pk, sk) = setup_from_seed(4, &[1u8; 32]);
+        let ids = [
+            Identity::derive_str(PATH[0]),
+            Identity::derive_path(&PATH).unwrap(),
+            Identity::derive_path(&["acme", "finance", "a", "b"]).unwrap(),
+        ];
+
+        let mut rng = XofRng::new(b"ibe-bbg-hibe-test", &[0u8; 32]);
+        let mut rng2 = XofRng::new(b"ibe-bbg-hibe-test", &[0u8; 32]);
+
+        let usks = extract_usk_batch(&pk, &sk, &ids, &mut rng).unwrap();
+        assert_eq!(usks.len(), ids.len());
+        for (v, usk) in ids.iter().zip(usks.iter()) {
+            assert_eq!(usk, &extract_usk(&pk, &sk, v, &mut rng2).unwrap());
+        }
+    }
+
+    #[test]
+    fn debug_is_redacted() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+
+        let fingerprint: std::string::String = results
+            .usk
+            .fingerprint()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        assert_eq!(
+            format!("{:?}", results.usk),
+            format!("UserSecretKey {{ fingerprint: {}, .. }}", fingerprint)
+        );
+
+        let usk2 = extract_usk(&results.pk, &results.sk, &results.kid, &mut rng).unwrap();
+        assert!(results.usk == results.usk.clone());
+        assert!(results.usk != usk2);
+    }
+
+    #[test]
+    fn setup_from_seed_is_deterministic() {
+        let (pk, sk) = setup_from_seed(MAX_DEPTH, &[1u8; 32]);
+        let (pk2, sk2) = setup_from_seed(MAX_DEPTH, &[1u8; 32]);
+        let (pk3, sk3) = setup_from_seed(MAX_DEPTH, &[2u8; 32]);
+
+        assert!(pk == pk2);
+        assert!(sk == sk2);
+        assert!(pk != pk3);
+        assert!(sk != sk3);
+    }
+
+    #[test]
+    fn eq_serialize_deserialize() {
+        let result = perform_default();
+
+        assert_eq!(result.m, Message::from_bytes(&result.m.to_bytes()).unwrap());
+        assert_eq!(
+            result.kid,
+            Identity::from_bytes(&result.kid.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            result.pk,
+            PublicKey::from_bytes(&result.pk.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            result.sk,
+            SecretKey::from_bytes(&result.sk.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            result.usk,
+            UserSecretKey::from_bytes(&result.usk.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            result.c,
+            CipherText::from_bytes(&result.c.to_bytes()).unwrap()
+        );
+    }
+
+    #[test]
+    fn try_from_rejects_invalid_depth() {
+        let (pk, _) = setup(3, &mut rand::thread_rng());
+        let bytes = pk.to_bytes();
+        assert_eq!(PublicKey::try_from(&bytes[..]).unwrap(), pk);
+
+        let mut corrupted = bytes;
+        corrupted[0] = MAX_DEPTH as u8 + 1;
+        assert_eq!(
+            PublicKey::try_from(&corrupted[..]).err(),
+            Some(Error::InvalidEncoding {
+                component: "max_depth",
+                index: None,
+            })
+        );
+
+        let mut corrupted = bytes;
+        corrupted[0] = 2;
+        assert_eq!(
+            PublicKey::try_from(&corrupted[..]).err(),
+            Some(Error::InvalidEncoding {
+                component: "h",
+                index: Some(2),
+            })
+        );
+    }
+}
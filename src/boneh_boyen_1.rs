@@ -0,0 +1,837 @@
+//! Identity Based Encryption Boneh-Boyen BB1 scheme on the [BLS12-381 pairing-friendly elliptic curve](https://github.com/zkcrypto/bls12_381).
+//!  * From: "[Efficient Selective-ID Secure Identity-Based Encryption Without Random Oracles](https://link.springer.com/chapter/10.1007/978-3-540-24676-3_14)"
+//!  * Published in: EUROCRYPT, 2004
+//!
+//! Selective-ID secure in the standard model, with short constant-size public parameters.
+//! Offers both a PKE variant encrypting a [`Message`] and a KEM variant generating a [`SymmetricKey`].
+//!
+//! Uses [SHA3-512](https://crates.io/crates/tiny-keccak) for hashing identities to scalars.
+//!
+//! The structure of the byte serialisation of the various datastructures is not guaranteed
+//! to remain constant between releases of this library.
+//! All operations in this library are implemented to run in constant time.
+
+use arrayref::{array_refs, mut_array_refs};
+use rand::{CryptoRng, RngCore};
+use subtle::CtOption;
+
+#[cfg(feature = "alloc")]
+use crate::table::{G2Table, SCALAR_WINDOWS};
+use crate::util::*;
+use crate::validation::*;
+use crate::{Compress, DerandomizedIBE, Derive, Error, IBE, IBKEM};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use irmaseal_curve::{G1Affine, G1Projective, G2Affine, Gt, Scalar};
+
+const PUBLICKEYSIZE: usize = 3 * 48 + 288;
+const SECRETKEYSIZE: usize = 4 * 96;
+
+const SEED_DST: &[u8] = b"ibe-boneh-boyen-1-setup-seed";
+
+/// Public key parameters generated by the PKG used to encrypt messages.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PublicKey {
+    g: G1Affine,
+    g1: G1Affine,
+    h: G1Affine,
+    v: Gt,
+}
+
+/// Secret key parameters generated by the PKG used to extract user secret keys.
+///
+/// Contains the counterparts on G2 of the public key parameters.
+#[derive(Clone)]
+pub struct SecretKey {
+    g: G2Affine,
+    g1: G2Affine,
+    h: G2Affine,
+    g2alpha: G2Affine,
+}
+
+zeroize_on_drop!(SecretKey: g, g1, h, g2alpha);
+impl_secret!(SecretKey: g, g1, h, g2alpha);
+impl_try_from_secret!(SecretKey, SECRETKEYSIZE);
+
+/// Points on G2 that form the user secret key.
+#[derive(Clone)]
+pub struct UserSecretKey {
+    d0: G2Affine,
+    d1: G2Affine,
+}
+
+zeroize_on_drop!(UserSecretKey: d0, d1);
+impl_secret!(UserSecretKey: d0, d1);
+impl_try_from_secret!(UserSecretKey, 192);
+
+/// Field parameter for an identity.
+///
+/// Effectively a hash of an identity, mapped to the curve field.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Identity(Scalar);
+
+/// A point on the paired curve that can be encrypted and decrypted.
+///
+/// Use [`derive_key`](Self::derive_key) to derive symmetric keys, such as for AES.
+#[derive(Clone)]
+pub struct Message(Gt);
+
+zeroize_on_drop!(Message: 0);
+impl_secret!(Message: 0);
+
+/// Encrypted message. Can only be decrypted with an user secret key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CipherText {
+    a: Gt,
+    b: G1Affine,
+    c: G1Affine,
+}
+
+/// A point on the paired curve that is encapsulated.
+///
+/// Use [`derive_key`](Self::derive_key) to derive symmetric keys, such as for AES.
+#[derive(Clone)]
+pub struct SymmetricKey(Gt);
+
+zeroize_on_drop!(SymmetricKey: 0);
+impl_secret!(SymmetricKey: 0);
+
+/// Encapsulated symmetric key. Can only be decapsulated with an user secret key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KemCipherText {
+    b: G1Affine,
+    c: G1Affine,
+}
+
+/// The Boneh-Boyen BB1 identity-based encryption scheme.
+///
+/// Implements [`IBE`] using [`encrypt`] and [`decrypt`],
+/// and [`IBKEM`] using [`encaps`] and [`decaps`].
+#[derive(Clone, Copy, Debug)]
+pub struct BonehBoyen1;
+
+/// Generate a keypair used by the Private Key Generator (PKG).
+pub fn setup<R: RngCore + CryptoRng>(rng: &mut R) -> (PublicKey, SecretKey) {
+    let g: G1Affine = rand_g1(rng).into();
+    let ghat: G2Affine = rand_g2(rng).into();
+    let g2: G2Affine = rand_g2(rng).into();
+
+    let mut alpha = rand_scalar(rng);
+    let mut delta = rand_scalar(rng);
+
+    let g1: G1Affine = (g * alpha).into();
+    let h = (g * delta).into();
+    let v = irmaseal_curve::pairing(&g1, &g2);
+
+    let pk = PublicKey { g, g1, h, v };
+    let sk = SecretKey {
+        g: ghat,
+        g1: (ghat * alpha).into(),
+        h: (ghat * delta).into(),
+        g2alpha: (g2 * alpha).into(),
+    };
+    alpha.wipe();
+    delta.wipe();
+
+    (pk, sk)
+}
+
+/// Deterministically derive a keypair for the PKG from a seed.
+///
+/// The same seed always results in the same keypair, hence the seed must be kept as secret as the master secret key.
+pub fn setup_from_seed(seed: &[u8; 32]) -> (PublicKey, SecretKey) {
+    setup(&mut XofRng::new(SEED_DST, seed))
+}
+
+/// Extract an user secret key for a given identity.
+pub fn extract_usk<R: RngCore + CryptoRng>(
+    _pk: &PublicKey,
+    sk: &SecretKey,
+    v: &Identity,
+    rng: &mut R,
+) -> UserSecretKey {
+    let mut r = rand_scalar(rng);
+
+    let d0 = (sk.g2alpha + (sk.g1 * v.0 + sk.h) * r).into();
+    let d1 = (sk.g * r).into();
+    r.wipe();
+
+    UserSecretKey { d0, d1 }
+}
+
+/// Extract user secret keys for many identities at once.
+///
+/// Precomputes fixed-base tables for the points `g`, `g1` and `h` of the secret key, such that every
+/// key takes three table lookups instead of three scalar multiplications, as `(g1 * v + h) * r`
+/// equals `g1 * (v * r) + h * r`. All keys are converted to affine coordinates using one inversion
+/// per component, and are extracted in parallel when the `rayon` feature is enabled.
+/// Given the same randomness, results in the same keys as calling [`extract_usk`] for every identity in order.
+///
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+pub fn extract_usk_batch<R: RngCore + CryptoRng>(
+    _pk: &PublicKey,
+    sk: &SecretKey,
+    ids: &[Identity],
+    rng: &mut R,
+) -> Vec<UserSecretKey> {
+    let mut g = G2Table::fixed_base(&sk.g, SCALAR_WINDOWS);
+    let mut g1 = G2Table::fixed_base(&sk.g1, SCALAR_WINDOWS);
+    let mut h = G2Table::fixed_base(&sk.h, SCALAR_WINDOWS);
+    let mut rs = rand_scalars(ids.len(), rng);
+
+    let mut d0 = batch_map(ids, |i, v| {
+        let mut vr = v.0 * rs[i];
+        let d0 = sk.g2alpha + g1.mul(&vr) + h.mul(&rs[i]);
+        vr.wipe();
+        d0
+    });
+    let mut d1 = batch_map(&rs, |_, r| g.mul(r));
+    rs.wipe();
+    g.wipe();
+    g1.wipe();
+    h.wipe();
+
+    let mut d0 = batch_normalize_g2(&mut d0);
+    let mut d1 = batch_normalize_g2(&mut d1);
+    let res = d0
+        .iter()
+        .zip(d1.iter())
+        .map(|(d0, d1)| UserSecretKey { d0: *d0, d1: *d1 })
+        .collect();
+    d0.wipe();
+    d1.wipe();
+
+    res
+}
+
+/// Common operation used in encryption and encapsulation
+/// to compute the ciphertext points for an identity.
+fn encaps_with_randomness(pk: &PublicKey, v: &Identity, s: &Scalar) -> (KemCipherText, Gt) {
+    let b = (pk.g * s).into();
+    let c = ((pk.g1 * v.0 + G1Projective::from(pk.h)) * s).into();
+    let k = pk.v * s;
+
+    (KemCipherText { b, c }, k)
+}
+
+/// Recover the encapsulated point on the paired curve.
+fn decaps_point(usk: &UserSecretKey, b: &G1Affine, c: &G1Affine) -> Gt {
+    irmaseal_curve::pairing(b, &usk.d0) - irmaseal_curve::pairing(c, &usk.d1)
+}
+
+fn encrypt_with_randomness(pk: &PublicKey, v: &Identity, m: &Message, s: &Scalar) -> CipherText {
+    let (KemCipherText { b, c }, k) = encaps_with_randomness(pk, v, s);
+    let a = k + m.0;
+
+    CipherText { a, b, c }
+}
+
+/// Encrypt a message using the PKG public key and an identity.
+pub fn encrypt<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    v: &Identity,
+    m: &Message,
+    rng: &mut R,
+) -> CipherText {
+    let mut s = rand_scalar(rng);
+    let c = encrypt_with_randomness(pk, v, m, &s);
+    s.wipe();
+
+    c
+}
+
+/// Decrypt ciphertext to a message using a user secret key.
+pub fn decrypt(usk: &UserSecretKey, c: &CipherText) -> Message {
+    Message(c.a - decaps_point(usk, &c.b, &c.c))
+}
+
+/// Generate a symmetric key and corresponding ciphertext for that key.
+pub fn encaps<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    v: &Identity,
+    rng: &mut R,
+) -> (KemCipherText, SymmetricKey) {
+    let mut s = rand_scalar(rng);
+    let (c, k) = encaps_with_randomness(pk, v, &s);
+    s.wipe();
+
+    (c, SymmetricKey(k))
+}
+
+/// Decapsulate a ciphertext to a symmetric key using a user secret key.
+pub fn decaps(usk: &UserSecretKey, c: &KemCipherText) -> SymmetricKey {
+    SymmetricKey(decaps_point(usk, &c.b, &c.c))
+}
+
+impl Identity {
+    /// Hash a byte slice to a scalar, which acts as a user public key.
+    /// Uses sha3-512 internally.
+    pub fn derive(b: &[u8]) -> Identity {
+        Identity(Scalar::from_bytes_wide(&sha3_512(b)))
+    }
+
+    /// Hash a string slice to a scalar.
+    /// Directly converts characters to UTF-8 byte representation.
+    pub fn derive_str(s: &str) -> Identity {
+        Self::derive(s.as_bytes())
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.to_bytes()
+    }
+
+    pub fn from_bytes(bytes: &[u8; 32]) -> CtOption<Self> {
+        Scalar::from_bytes(bytes).map(Identity)
+    }
+}
+
+impl Message {
+    /// Generate a random point on the paired curve.
+    pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        Self(rand_gt(rng))
+    }
+
+    pub fn to_bytes(&self) -> [u8; 288] {
+        self.0.to_compressed()
+    }
+
+    pub fn from_bytes(bytes: &[u8; 288]) -> CtOption<Self> {
+        Gt::from_compressed(bytes).map(Message)
+    }
+
+    /// Derive symmetric key material bound to `c` and `info`, see [key derivation](crate#key-derivation).
+    pub fn derive_key(&self, c: &CipherText, info: &[u8], out: &mut [u8]) {
+        kdf(&self.to_bytes(), &c.to_bytes(), info, out)
+    }
+}
+
+impl SymmetricKey {
+    pub fn to_bytes(&self) -> [u8; 288] {
+        self.0.to_compressed()
+    }
+
+    pub fn from_bytes(bytes: &[u8; 288]) -> CtOption<Self> {
+        Gt::from_compressed(bytes).map(SymmetricKey)
+    }
+
+    /// Derive symmetric key material bound to `c` and `info`, see [key derivation](crate#key-derivation).
+    pub fn derive_key(&self, c: &KemCipherText, info: &[u8], out: &mut [u8]) {
+        kdf(&self.to_bytes(), &c.to_bytes(), info, out)
+    }
+}
+
+impl PublicKey {
+    /// Validate a public key obtained from an untrusted source.
+    ///
+    /// Checks that all points are in the prime order subgroup and that none of them is the identity,
+    /// including the pairing `v` in the target group.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        check_g1(&self.g, "g", None)?;
+        check_g1(&self.g1, "g1", None)?;
+        check_g1(&self.h, "h", None)?;
+        check_gt(&self.v, "v")
+    }
+
+    pub fn to_bytes(&self) -> [u8; PUBLICKEYSIZE] {
+        let mut res = [0u8; PUBLICKEYSIZE];
+        let (g, g1, h, v) = mut_array_refs![&mut res, 48, 48, 48, 288];
+        *g = self.g.to_compressed();
+        *g1 = self.g1.to_compressed();
+        *h = self.h.to_compressed();
+        *v = self.v.to_compressed();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; PUBLICKEYSIZE]) -> CtOption<Self> {
+        let (g, g1, h, v) = array_refs![bytes, 48, 48, 48, 288];
+
+        let g = G1Affine::from_compressed(g);
+        let g1 = G1Affine::from_compressed(g1);
+        let h = G1Affine::from_compressed(h);
+        let v = Gt::from_compressed(v);
+
+        g.and_then(|g| g1.and_then(|g1| h.and_then(|h| v.map(|v| PublicKey { g, g1, h, v }))))
+    }
+}
+
+impl SecretKey {
+    pub fn to_bytes(&self) -> [u8; SECRETKEYSIZE] {
+        let mut res = [0u8; SECRETKEYSIZE];
+        let (g, g1, h, g2alpha) = mut_array_refs![&mut res, 96, 96, 96, 96];
+        *g = self.g.to_compressed();
+        *g1 = self.g1.to_compressed();
+        *h = self.h.to_compressed();
+        *g2alpha = self.g2alpha.to_compressed();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; SECRETKEYSIZE]) -> CtOption<Self> {
+        let (g, g1, h, g2alpha) = array_refs![bytes, 96, 96, 96, 96];
+
+        let g = G2Affine::from_compressed(g);
+        let g1 = G2Affine::from_compressed(g1);
+        let h = G2Affine::from_compressed(h);
+        let g2alpha = G2Affine::from_compressed(g2alpha);
+
+        g.and_then(|g| {
+            g1.and_then(|g1| h.and_then(|h| g2alpha.map(|g2alpha| SecretKey { g, g1, h, g2alpha })))
+        })
+    }
+}
+
+impl UserSecretKey {
+    pub fn to_bytes(&self) -> [u8; 192] {
+        let mut res = [0u8; 192];
+        let (d0, d1) = mut_array_refs![&mut res, 96, 96];
+        *d0 = self.d0.to_compressed();
+        *d1 = self.d1.to_compressed();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; 192]) -> CtOption<Self> {
+        let (d0, d1) = array_refs![bytes, 96, 96];
+
+        let d0 = G2Affine::from_compressed(d0);
+        let d1 = G2Affine::from_compressed(d1);
+
+        d0.and_then(|d0| d1.map(|d1| UserSecretKey { d0, d1 }))
+    }
+}
+
+impl CipherText {
+    pub fn to_bytes(&self) -> [u8; 384] {
+        let mut res = [0u8; 384];
+        let (a, b, c) = mut_array_refs![&mut res, 288, 48, 48];
+        *a = self.a.to_compressed();
+        *b = self.b.to_compressed();
+        *c = self.c.to_compressed();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; 384]) -> CtOption<Self> {
+        let (a, b, c) = array_refs![bytes, 288, 48, 48];
+
+        let a = Gt::from_compressed(a);
+        let b = G1Affine::from_compressed(b);
+        let c = G1Affine::from_compressed(c);
+
+        a.and_then(|a| b.and_then(|b| c.map(|c| CipherText { a, b, c })))
+    }
+}
+
+impl KemCipherText {
+    pub fn to_bytes(&self) -> [u8; 96] {
+        let mut res = [0u8; 96];
+        let (b, c) = mut_array_refs![&mut res, 48, 48];
+        *b = self.b.to_compressed();
+        *c = self.c.to_compressed();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; 96]) -> CtOption<Self> {
+        let (b, c) = array_refs![bytes, 48, 48];
+
+        let b = G1Affine::from_compressed(b);
+        let c = G1Affine::from_compressed(c);
+
+        b.and_then(|b| c.map(|c| KemCipherText { b, c }))
+    }
+}
+
+impl Derive for Identity {
+    fn derive(b: &[u8]) -> Identity {
+        Identity::derive(b)
+    }
+}
+
+impl IBE for BonehBoyen1 {
+    type PublicKey = PublicKey;
+    type SecretKey = SecretKey;
+    type UserSecretKey = UserSecretKey;
+    type Identity = Identity;
+    type Message = Message;
+    type CipherText = CipherText;
+
+    fn setup<R: RngCore + CryptoRng>(rng: &mut R) -> (PublicKey, SecretKey) {
+        setup(rng)
+    }
+
+    fn extract_usk<R: RngCore + CryptoRng>(
+        pk: &PublicKey,
+        sk: &SecretKey,
+        v: &Identity,
+        rng: &mut R,
+    ) -> UserSecretKey {
+        extract_usk(pk, sk, v, rng)
+    }
+
+    fn encrypt<R: RngCore + CryptoRng>(
+        pk: &PublicKey,
+        v: &Identity,
+        m: &Message,
+        rng: &mut R,
+    ) -> CipherText {
+        encrypt(pk, v, m, rng)
+    }
+
+    fn decrypt(usk: &UserSecretKey, c: &CipherText) -> Message {
+        decrypt(usk, c)
+    }
+}
+
+impl DerandomizedIBE for BonehBoyen1 {
+    fn generate_message<R: RngCore + CryptoRng>(rng: &mut R) -> Message {
+        Message::generate(rng)
+    }
+
+    fn encrypt_derandomized(
+        pk: &PublicKey,
+        v: &Identity,
+        m: &Message,
+        coins: &[u8; 64],
+    ) -> CipherText {
+        encrypt_with_randomness(pk, v, m, &Scalar::from_bytes_wide(coins))
+    }
+}
+
+impl IBKEM for BonehBoyen1 {
+    type PublicKey = PublicKey;
+    type SecretKey = SecretKey;
+    type UserSecretKey = UserSecretKey;
+    type Identity = Identity;
+    type CipherText = KemCipherText;
+    type SharedSecret = SymmetricKey;
+
+    fn setup<R: RngCore + CryptoRng>(rng: &mut R) -> (PublicKey, SecretKey) {
+        setup(rng)
+    }
+
+    fn extract_usk<R: RngCore + CryptoRng>(
+        pk: &PublicKey,
+        sk: &SecretKey,
+        v: &Identity,
+        rng: &mut R,
+    ) -> UserSecretKey {
+        extract_usk(pk, sk, v, rng)
+    }
+
+    fn encaps<R: RngCore + CryptoRng>(
+        pk: &PublicKey,
+        v: &Identity,
+        rng: &mut R,
+    ) -> (KemCipherText, SymmetricKey) {
+        encaps(pk, v, rng)
+    }
+
+    fn decaps(_pk: &PublicKey, usk: &UserSecretKey, c: &KemCipherText) -> SymmetricKey {
+        decaps(usk, c)
+    }
+}
+
+impl_serde!(PublicKey, PUBLICKEYSIZE);
+impl_serde!(SecretKey, SECRETKEYSIZE);
+impl_serde!(UserSecretKey, 192);
+impl_serde!(Identity, 32);
+impl_serde!(Message, 288);
+impl_serde!(CipherText, 384);
+impl_serde!(SymmetricKey, 288);
+impl_serde!(KemCipherText, 96);
+
+impl Compress for PublicKey {
+    type Output = [u8; PUBLICKEYSIZE];
+
+    fn to_bytes(&self) -> [u8; PUBLICKEYSIZE] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; PUBLICKEYSIZE]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for SecretKey {
+    type Output = [u8; SECRETKEYSIZE];
+
+    fn to_bytes(&self) -> [u8; SECRETKEYSIZE] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; SECRETKEYSIZE]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for UserSecretKey {
+    type Output = [u8; 192];
+
+    fn to_bytes(&self) -> [u8; 192] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; 192]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for Identity {
+    type Output = [u8; 32];
+
+    fn to_bytes(&self) -> [u8; 32] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; 32]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for Message {
+    type Output = [u8; 288];
+
+    fn to_bytes(&self) -> [u8; 288] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; 288]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for CipherText {
+    type Output = [u8; 384];
+
+    fn to_bytes(&self) -> [u8; 384] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; 384]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for SymmetricKey {
+    type Output = [u8; 288];
+
+    fn to_bytes(&self) -> [u8; 288] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; 288]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for KemCipherText {
+    type Output = [u8; 96];
+
+    fn to_bytes(&self) -> [u8; 96] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; 96]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl TryFrom<&[u8]> for PublicKey {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Error> {
+        let mut d = Decoder::new(bytes, PUBLICKEYSIZE)?;
+        Ok(PublicKey {
+            g: d.g1("g", None)?,
+            g1: d.g1("g1", None)?,
+            h: d.g1("h", None)?,
+            v: d.gt("v")?,
+        })
+    }
+}
+
+impl TryFrom<&[u8]> for CipherText {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Error> {
+        let mut d = Decoder::new(bytes, 384)?;
+        Ok(CipherText {
+            a: d.gt("a")?,
+            b: d.g1("b", None)?,
+            c: d.g1("c", None)?,
+        })
+    }
+}
+
+impl TryFrom<&[u8]> for KemCipherText {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Error> {
+        let mut d = Decoder::new(bytes, 96)?;
+        Ok(KemCipherText {
+            b: d.g1("b", None)?,
+            c: d.g1("c", None)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "email:w.geraedts@sarif.nl";
+
+    #[allow(dead_code)]
+    struct DefaultSubResults {
+        kid: Identity,
+        m: Message,
+        pk: PublicKey,
+        sk: SecretKey,
+        usk: UserSecretKey,
+        c: CipherText,
+        kc: KemCipherText,
+        k: SymmetricKey,
+    }
+
+    fn perform_default() -> DefaultSubResults {
+        let mut rng = rand::thread_rng();
+
+        let id = ID.as_bytes();
+        let kid = Identity::derive(id);
+
+        let m = Message::generate(&mut rng);
+
+        let (pk, sk) = setup(&mut rng);
+        let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+
+        let c = encrypt(&pk, &kid, &m, &mut rng);
+        let (kc, k) = encaps(&pk, &kid, &mut rng);
+
+        DefaultSubResults {
+            kid,
+            m,
+            pk,
+            sk,
+            usk,
+            c,
+            kc,
+            k,
+        }
+    }
+
+    #[test]
+    fn eq_encrypt_decrypt() {
+        let results = perform_default();
+        let m2 = decrypt(&results.usk, &results.c);
+
+        assert_eq!(results.m, m2);
+    }
+
+    #[test]
+    fn eq_derive_key() {
+        let results = perform_default();
+        let (mut key, mut key2) = ([0u8; 32], [0u8; 32]);
+        results.m.derive_key(&results.c, b"test", &mut key);
+        decrypt(&results.usk, &results.c).derive_key(&results.c, b"test", &mut key2);
+        assert_eq!(key, key2);
+    }
+
+    #[test]
+    fn eq_derive_key_kem() {
+        let results = perform_default();
+        let (mut key, mut key2) = ([0u8; 32], [0u8; 32]);
+        results.k.derive_key(&results.kc, b"test", &mut key);
+        decaps(&results.usk, &results.kc).derive_key(&results.kc, b"test", &mut key2);
+        assert_eq!(key, key2);
+    }
+
+    #[test]
+    fn eq_encaps_decaps() {
+        let results = perform_default();
+        let k2 = decaps(&results.usk, &results.kc);
+
+        assert_eq!(results.k, k2);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn batch_matches_sequential() {
+        let (pk, sk) = setup_from_seed(&[1u8; 32]);
+        let ids = [
+            Identity::derive_str(ID),
+            Identity::derive_str("email:l.botros@cs.ru.nl"),
+        ];
+
+        let mut rng = XofRng::new(b"ibe-boneh-boyen-1-test", &[0u8; 32]);
+        let mut rng2 = XofRng::new(b"ibe-boneh-boyen-1-test", &[0u8; 32]);
+
+        let usks = extract_usk_batch(&pk, &sk, &ids, &mut rng);
+        assert_eq!(usks.len(), ids.len());
+        for (v, usk) in ids.iter().zip(usks.iter()) {
+            assert_eq!(usk, &extract_usk(&pk, &sk, v, &mut rng2));
+        }
+    }
+
+    #[test]
+    fn debug_is_redacted() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+
+        let fingerprint: std::string::String = results
+            .usk
+            .fingerprint()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        assert_eq!(
+            format!("{:?}", results.usk),
+            format!("UserSecretKey {{ fingerprint: {}, .. }}", fingerprint)
+        );
+
+        let usk2 = extract_usk(&results.pk, &results.sk, &results.kid, &mut rng);
+        assert!(results.usk == results.usk.clone());
+        assert!(results.usk != usk2);
+    }
+
+    #[test]
+    fn setup_from_seed_is_deterministic() {
+        let (pk, sk) = setup_from_seed(&[1u8; 32]);
+        let (pk2, sk2) = setup_from_seed(&[1u8; 32]);
+        let (pk3, sk3) = setup_from_seed(&[2u8; 32]);
+
+        assert!(pk == pk2);
+        assert!(sk == sk2);
+        assert!(pk != pk3);
+        assert!(sk != sk3);
+    }
+
+    #[test]
+    fn eq_serialize_deserialize() {
+        let result = perform_default();
+
+        assert_eq!(result.m, Message::from_bytes(&result.m.to_bytes()).unwrap());
+        assert_eq!(
+            result.k,
+            SymmetricKey::from_bytes(&result.k.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            result.pk,
+            PublicKey::from_bytes(&result.pk.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            result.sk,
+            SecretKey::from_bytes(&result.sk.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            result.usk,
+            UserSecretKey::from_bytes(&result.usk.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            result.c,
+            CipherText::from_bytes(&result.c.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            result.kc,
+            KemCipherText::from_bytes(&result.kc.to_bytes()).unwrap()
+        );
+    }
+}
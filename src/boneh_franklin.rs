@@ -0,0 +1,677 @@
+//! Identity Based Encryption Boneh-Franklin scheme on the [BLS12-381 pairing-friendly elliptic curve](https://github.com/zkcrypto/bls12_381).
+//!  * From: "[Identity-Based Encryption from the Weil Pairing](https://link.springer.com/chapter/10.1007/3-540-44647-8_13)"
+//!  * Published in: CRYPTO, 2001
+//!
+//! Implements both the CPA-secure BasicIdent and the CCA2-secure FullIdent variant.
+//! Security relies on the random oracle model.
+//! In contrast to the other schemes in this library, the public key consists of a single point.
+//!
+//! Identities are mapped to G2 using [hash-to-curve](https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-hash-to-curve-12)
+//! with [SHAKE256](https://crates.io/crates/sha3). Uses [SHA3-256](https://crates.io/crates/tiny-keccak)
+//! and [SHA3-512](https://crates.io/crates/tiny-keccak) for the other random oracles.
+//!
+//! The structure of the byte serialisation of the various datastructures is not guaranteed
+//! to remain constant between releases of this library.
+//! All operations in this library are implemented to run in constant time.
+
+use arrayref::{array_refs, mut_array_refs};
+use rand::{CryptoRng, RngCore};
+use subtle::{ConstantTimeEq, CtOption};
+
+use crate::util::*;
+use crate::validation::*;
+use crate::{Compress, DerandomizedIBE, Derive, Error, IBE};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use irmaseal_curve::{G1Affine, G2Affine, Gt, Scalar};
+
+const IDENTITY_DST: &[u8] = b"ibe-boneh-franklin-BLS12381G2_XOF:SHAKE-256_SSWU_RO_";
+const MASK_DST: &[u8] = b"ibe-boneh-franklin-mask";
+const RANDOMNESS_DST: &[u8] = b"ibe-boneh-franklin-randomness";
+const MESSAGE_MASK_DST: &[u8] = b"ibe-boneh-franklin-message-mask";
+const SEED_DST: &[u8] = b"ibe-boneh-franklin-setup-seed";
+
+const MESSAGE_SIZE: usize = 32;
+
+/// Public key parameter generated by the PKG used to encrypt messages.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PublicKey {
+    ppub: G1Affine,
+}
+
+/// Secret key parameter generated by the PKG used to extract user secret keys.
+#[derive(Clone)]
+pub struct SecretKey {
+    s: Scalar,
+}
+
+zeroize_on_drop!(SecretKey: s);
+impl_secret!(SecretKey: s);
+impl_try_from_secret!(SecretKey, 32);
+
+/// Point on G2 that forms the user secret key.
+#[derive(Clone)]
+pub struct UserSecretKey {
+    d: G2Affine,
+}
+
+zeroize_on_drop!(UserSecretKey: d);
+impl_secret!(UserSecretKey: d);
+impl_try_from_secret!(UserSecretKey, 96);
+
+/// Identity hashed to a point on G2.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Identity(G2Affine);
+
+/// A fixed-size message that can be encrypted and decrypted.
+///
+/// Can be used directly as a 256-bit symmetric key when generated randomly.
+#[derive(Clone)]
+pub struct Message([u8; MESSAGE_SIZE]);
+
+zeroize_on_drop!(Message: 0);
+impl_secret!(Message: 0);
+
+/// Message encrypted using the CPA-secure BasicIdent variant.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BasicCipherText {
+    u: G1Affine,
+    v: [u8; MESSAGE_SIZE],
+}
+
+/// Message encrypted using the CCA2-secure FullIdent variant.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CipherText {
+    u: G1Affine,
+    v: [u8; MESSAGE_SIZE],
+    w: [u8; MESSAGE_SIZE],
+}
+
+/// The Boneh-Franklin identity-based encryption scheme.
+///
+/// Implements [`IBE`] and [`DerandomizedIBE`] using the BasicIdent variant,
+/// such that it can be used with the [Fujisaki-Okamoto transform](crate::fujisaki_okamoto).
+/// For direct usage, prefer the FullIdent variant in [`encrypt`] and [`decrypt`].
+#[derive(Clone, Copy, Debug)]
+pub struct BonehFranklin;
+
+/// Generate a keypair used by the Private Key Generator (PKG).
+pub fn setup<R: RngCore + CryptoRng>(rng: &mut R) -> (PublicKey, SecretKey) {
+    let s = rand_scalar(rng);
+    let ppub = (G1Affine::generator() * s).into();
+
+    (PublicKey { ppub }, SecretKey { s })
+}
+
+/// Deterministically derive a keypair for the PKG from a seed.
+///
+/// The same seed always results in the same keypair, hence the seed must be kept as secret as the master secret key.
+pub fn setup_from_seed(seed: &[u8; 32]) -> (PublicKey, SecretKey) {
+    setup(&mut XofRng::new(SEED_DST, seed))
+}
+
+/// Extract an user secret key for a given identity.
+///
+/// This scheme does not require randomness for extraction, hence equal identities
+/// always result in equal user secret keys.
+pub fn extract_usk(_pk: &PublicKey, sk: &SecretKey, v: &Identity) -> UserSecretKey {
+    let d = (v.0 * sk.s).into();
+
+    UserSecretKey { d }
+}
+
+/// Extract user secret keys for many identities at once.
+///
+/// As extraction is deterministic, only the conversion to affine coordinates is shared,
+/// using a single inversion for the entire batch.
+/// Extracts the keys in parallel when the `rayon` feature is enabled.
+/// The random number generator is not used, but is taken such that the signature matches
+/// that of the other schemes.
+///
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+pub fn extract_usk_batch<R: RngCore + CryptoRng>(
+    _pk: &PublicKey,
+    sk: &SecretKey,
+    ids: &[Identity],
+    _rng: &mut R,
+) -> Vec<UserSecretKey> {
+    let mut d = batch_map(ids, |_, v| v.0 * sk.s);
+
+    let mut d = batch_normalize_g2(&mut d);
+    let res = d.iter().map(|d| UserSecretKey { d: *d }).collect();
+    d.wipe();
+
+    res
+}
+
+/// Hash an element of the target group to a mask for a message.
+fn mask(g: &Gt) -> [u8; MESSAGE_SIZE] {
+    sha3_256_multi(&[MASK_DST, &g.to_compressed()])
+}
+
+fn xor(a: &[u8; MESSAGE_SIZE], b: &[u8; MESSAGE_SIZE]) -> [u8; MESSAGE_SIZE] {
+    let mut res = [0u8; MESSAGE_SIZE];
+    for ((ri, ai), bi) in res.iter_mut().zip(a.iter()).zip(b.iter()) {
+        *ri = ai ^ bi;
+    }
+    res
+}
+
+/// Encrypt a message using the BasicIdent variant with the given randomness.
+fn encrypt_basic_with_randomness(
+    pk: &PublicKey,
+    v: &Identity,
+    m: &Message,
+    r: &Scalar,
+) -> BasicCipherText {
+    let u = (G1Affine::generator() * r).into();
+    let g = irmaseal_curve::pairing(&pk.ppub, &v.0) * r;
+    let v = xor(&m.0, &mask(&g));
+
+    BasicCipherText { u, v }
+}
+
+/// Encrypt a message using the PKG public key and an identity, using the CPA-secure BasicIdent variant.
+pub fn encrypt_basic<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    v: &Identity,
+    m: &Message,
+    rng: &mut R,
+) -> BasicCipherText {
+    let mut r = rand_scalar(rng);
+    let c = encrypt_basic_with_randomness(pk, v, m, &r);
+    r.wipe();
+
+    c
+}
+
+/// Decrypt ciphertext to a message using a user secret key, using the CPA-secure BasicIdent variant.
+pub fn decrypt_basic(usk: &UserSecretKey, c: &BasicCipherText) -> Message {
+    let g = irmaseal_curve::pairing(&c.u, &usk.d);
+    Message(xor(&c.v, &mask(&g)))
+}
+
+/// Derive the encryption randomness from sigma and the message.
+fn derive_randomness(sigma: &[u8; MESSAGE_SIZE], m: &Message) -> Scalar {
+    Scalar::from_bytes_wide(&sha3_512_multi(&[RANDOMNESS_DST, sigma, &m.0]))
+}
+
+/// Hash sigma to a mask for a message.
+fn message_mask(sigma: &[u8; MESSAGE_SIZE]) -> [u8; MESSAGE_SIZE] {
+    sha3_256_multi(&[MESSAGE_MASK_DST, sigma])
+}
+
+/// Encrypt a message using the PKG public key and an identity, using the CCA2-secure FullIdent variant.
+pub fn encrypt<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    v: &Identity,
+    m: &Message,
+    rng: &mut R,
+) -> CipherText {
+    let mut sigma = [0u8; MESSAGE_SIZE];
+    rng.fill_bytes(&mut sigma);
+
+    let mut r = derive_randomness(&sigma, m);
+    let BasicCipherText { u, v } = encrypt_basic_with_randomness(pk, v, &Message(sigma), &r);
+    let w = xor(&m.0, &message_mask(&sigma));
+    r.wipe();
+    sigma.wipe();
+
+    CipherText { u, v, w }
+}
+
+/// Decrypt ciphertext to a message using a user secret key, using the CCA2-secure FullIdent variant.
+///
+/// Results in none if the ciphertext was not validly generated for the identity of the user secret key.
+pub fn decrypt(usk: &UserSecretKey, c: &CipherText) -> CtOption<Message> {
+    let sigma = decrypt_basic(usk, &BasicCipherText { u: c.u, v: c.v });
+    let m = Message(xor(&c.w, &message_mask(&sigma.0)));
+
+    let mut r = derive_randomness(&sigma.0, &m);
+    let u: G1Affine = (G1Affine::generator() * r).into();
+    r.wipe();
+
+    CtOption::new(m, u.ct_eq(&c.u))
+}
+
+impl Identity {
+    /// Hash a byte slice to a point on G2, which acts as a user public key.
+    /// Uses hash-to-curve with SHAKE256 internally.
+    pub fn derive(b: &[u8]) -> Identity {
+        Identity(hash_to_g2(b, IDENTITY_DST).into())
+    }
+
+    /// Hash a string slice to a point on G2.
+    /// Directly converts characters to UTF-8 byte representation.
+    pub fn derive_str(s: &str) -> Identity {
+        Self::derive(s.as_bytes())
+    }
+
+    pub fn to_bytes(&self) -> [u8; 96] {
+        self.0.to_compressed()
+    }
+
+    pub fn from_bytes(bytes: &[u8; 96]) -> CtOption<Self> {
+        G2Affine::from_compressed(bytes).map(Identity)
+    }
+}
+
+impl Message {
+    /// Generate a uniformly random message.
+    pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        let mut m = [0u8; MESSAGE_SIZE];
+        rng.fill_bytes(&mut m);
+        Message(m)
+    }
+
+    pub fn to_bytes(&self) -> [u8; MESSAGE_SIZE] {
+        self.0
+    }
+
+    pub fn from_bytes(bytes: &[u8; MESSAGE_SIZE]) -> CtOption<Self> {
+        CtOption::new(Message(*bytes), 1u8.into())
+    }
+}
+
+impl PublicKey {
+    /// Validate a public key obtained from an untrusted source.
+    ///
+    /// Checks that `ppub` is in the prime order subgroup and is not the identity,
+    /// as the identity would result in ciphertexts that can be decrypted without any key.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        check_g1(&self.ppub, "ppub", None)
+    }
+
+    pub fn to_bytes(&self) -> [u8; 48] {
+        self.ppub.to_compressed()
+    }
+
+    pub fn from_bytes(bytes: &[u8; 48]) -> CtOption<Self> {
+        G1Affine::from_compressed(bytes).map(|ppub| PublicKey { ppub })
+    }
+}
+
+impl SecretKey {
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.s.to_bytes()
+    }
+
+    pub fn from_bytes(bytes: &[u8; 32]) -> CtOption<Self> {
+        Scalar::from_bytes(bytes).map(|s| SecretKey { s })
+    }
+}
+
+impl UserSecretKey {
+    pub fn to_bytes(&self) -> [u8; 96] {
+        self.d.to_compressed()
+    }
+
+    pub fn from_bytes(bytes: &[u8; 96]) -> CtOption<Self> {
+        G2Affine::from_compressed(bytes).map(|d| UserSecretKey { d })
+    }
+}
+
+impl BasicCipherText {
+    pub fn to_bytes(&self) -> [u8; 80] {
+        let mut res = [0u8; 80];
+        let (u, v) = mut_array_refs![&mut res, 48, MESSAGE_SIZE];
+        *u = self.u.to_compressed();
+        *v = self.v;
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; 80]) -> CtOption<Self> {
+        let (u, v) = array_refs![bytes, 48, MESSAGE_SIZE];
+
+        G1Affine::from_compressed(u).map(|u| BasicCipherText { u, v: *v })
+    }
+}
+
+impl CipherText {
+    pub fn to_bytes(&self) -> [u8; 112] {
+        let mut res = [0u8; 112];
+        let (u, v, w) = mut_array_refs![&mut res, 48, MESSAGE_SIZE, MESSAGE_SIZE];
+        *u = self.u.to_compressed();
+        *v = self.v;
+        *w = self.w;
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; 112]) -> CtOption<Self> {
+        let (u, v, w) = array_refs![bytes, 48, MESSAGE_SIZE, MESSAGE_SIZE];
+
+        G1Affine::from_compressed(u).map(|u| CipherText { u, v: *v, w: *w })
+    }
+}
+
+impl Derive for Identity {
+    fn derive(b: &[u8]) -> Identity {
+        Identity::derive(b)
+    }
+}
+
+impl IBE for BonehFranklin {
+    type PublicKey = PublicKey;
+    type SecretKey = SecretKey;
+    type UserSecretKey = UserSecretKey;
+    type Identity = Identity;
+    type Message = Message;
+    type CipherText = BasicCipherText;
+
+    fn setup<R: RngCore + CryptoRng>(rng: &mut R) -> (PublicKey, SecretKey) {
+        setup(rng)
+    }
+
+    fn extract_usk<R: RngCore + CryptoRng>(
+        pk: &PublicKey,
+        sk: &SecretKey,
+        v: &Identity,
+        _rng: &mut R,
+    ) -> UserSecretKey {
+        extract_usk(pk, sk, v)
+    }
+
+    fn encrypt<R: RngCore + CryptoRng>(
+        pk: &PublicKey,
+        v: &Identity,
+        m: &Message,
+        rng: &mut R,
+    ) -> BasicCipherText {
+        encrypt_basic(pk, v, m, rng)
+    }
+
+    fn decrypt(usk: &UserSecretKey, c: &BasicCipherText) -> Message {
+        decrypt_basic(usk, c)
+    }
+}
+
+impl DerandomizedIBE for BonehFranklin {
+    fn generate_message<R: RngCore + CryptoRng>(rng: &mut R) -> Message {
+        Message::generate(rng)
+    }
+
+    fn encrypt_derandomized(
+        pk: &PublicKey,
+        v: &Identity,
+        m: &Message,
+        coins: &[u8; 64],
+    ) -> BasicCipherText {
+        encrypt_basic_with_randomness(pk, v, m, &Scalar::from_bytes_wide(coins))
+    }
+}
+
+impl_serde!(PublicKey, 48);
+impl_serde!(SecretKey, 32);
+impl_serde!(UserSecretKey, 96);
+impl_serde!(Identity, 96);
+impl_serde!(Message, MESSAGE_SIZE);
+impl_serde!(BasicCipherText, 80);
+impl_serde!(CipherText, 112);
+
+impl Compress for PublicKey {
+    type Output = [u8; 48];
+
+    fn to_bytes(&self) -> [u8; 48] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; 48]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for SecretKey {
+    type Output = [u8; 32];
+
+    fn to_bytes(&self) -> [u8; 32] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; 32]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for UserSecretKey {
+    type Output = [u8; 96];
+
+    fn to_bytes(&self) -> [u8; 96] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; 96]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for Identity {
+    type Output = [u8; 96];
+
+    fn to_bytes(&self) -> [u8; 96] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; 96]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for Message {
+    type Output = [u8; MESSAGE_SIZE];
+
+    fn to_bytes(&self) -> [u8; MESSAGE_SIZE] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; MESSAGE_SIZE]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for BasicCipherText {
+    type Output = [u8; 80];
+
+    fn to_bytes(&self) -> [u8; 80] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; 80]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for CipherText {
+    type Output = [u8; 112];
+
+    fn to_bytes(&self) -> [u8; 112] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; 112]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl TryFrom<&[u8]> for PublicKey {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Error> {
+        let mut d = Decoder::new(bytes, 48)?;
+        Ok(PublicKey {
+            ppub: d.g1("ppub", None)?,
+        })
+    }
+}
+
+impl TryFrom<&[u8]> for BasicCipherText {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Error> {
+        let mut d = Decoder::new(bytes, 80)?;
+        Ok(BasicCipherText {
+            u: d.g1("u", None)?,
+            v: *d.bytes(),
+        })
+    }
+}
+
+impl TryFrom<&[u8]> for CipherText {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Error> {
+        let mut d = Decoder::new(bytes, 112)?;
+        Ok(CipherText {
+            u: d.g1("u", None)?,
+            v: *d.bytes(),
+            w: *d.bytes(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "email:w.geraedts@sarif.nl";
+    const OTHER_ID: &str = "email:l.botros@cs.ru.nl";
+
+    #[allow(dead_code)]
+    struct DefaultSubResults {
+        kid: Identity,
+        m: Message,
+        pk: PublicKey,
+        sk: SecretKey,
+        usk: UserSecretKey,
+        c: CipherText,
+        cbasic: BasicCipherText,
+    }
+
+    fn perform_default() -> DefaultSubResults {
+        let mut rng = rand::thread_rng();
+
+        let id = ID.as_bytes();
+        let kid = Identity::derive(id);
+
+        let m = Message::generate(&mut rng);
+
+        let (pk, sk) = setup(&mut rng);
+        let usk = extract_usk(&pk, &sk, &kid);
+
+        let c = encrypt(&pk, &kid, &m, &mut rng);
+        let cbasic = encrypt_basic(&pk, &kid, &m, &mut rng);
+
+        DefaultSubResults {
+            kid,
+            m,
+            pk,
+            sk,
+            usk,
+            c,
+            cbasic,
+        }
+    }
+
+    #[test]
+    fn eq_encrypt_decrypt() {
+        let results = perform_default();
+
+        assert_eq!(results.m, decrypt(&results.usk, &results.c).unwrap());
+        assert_eq!(results.m, decrypt_basic(&results.usk, &results.cbasic));
+    }
+
+    #[test]
+    fn reject_tampered() {
+        let mut results = perform_default();
+
+        results.c.w[0] ^= 1;
+        assert!(bool::from(decrypt(&results.usk, &results.c).is_none()));
+
+        results.c.w[0] ^= 1;
+        results.c.v[0] ^= 1;
+        assert!(bool::from(decrypt(&results.usk, &results.c).is_none()));
+    }
+
+    #[test]
+    fn reject_other_identity() {
+        let results = perform_default();
+
+        let other_kid = Identity::derive_str(OTHER_ID);
+        let other_usk = extract_usk(&results.pk, &results.sk, &other_kid);
+
+        assert!(bool::from(decrypt(&other_usk, &results.c).is_none()));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn batch_matches_sequential() {
+        let (pk, sk) = setup_from_seed(&[1u8; 32]);
+        let ids = [Identity::derive_str(ID), Identity::derive_str(OTHER_ID)];
+
+        let usks = extract_usk_batch(&pk, &sk, &ids, &mut rand::thread_rng());
+        assert_eq!(usks.len(), ids.len());
+        for (v, usk) in ids.iter().zip(usks.iter()) {
+            assert_eq!(usk, &extract_usk(&pk, &sk, v));
+        }
+    }
+
+    #[test]
+    fn debug_is_redacted() {
+        let results = perform_default();
+
+        let fingerprint: std::string::String = results
+            .usk
+            .fingerprint()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        assert_eq!(
+            format!("{:?}", results.usk),
+            format!("UserSecretKey {{ fingerprint: {}, .. }}", fingerprint)
+        );
+
+        let usk2 = extract_usk(&results.pk, &results.sk, &Identity::derive_str(OTHER_ID));
+        assert!(results.usk == results.usk.clone());
+        assert!(results.usk != usk2);
+    }
+
+    #[test]
+    fn setup_from_seed_is_deterministic() {
+        let (pk, sk) = setup_from_seed(&[1u8; 32]);
+        let (pk2, sk2) = setup_from_seed(&[1u8; 32]);
+        let (pk3, sk3) = setup_from_seed(&[2u8; 32]);
+
+        assert!(pk == pk2);
+        assert!(sk == sk2);
+        assert!(pk != pk3);
+        assert!(sk != sk3);
+    }
+
+    #[test]
+    fn eq_serialize_deserialize() {
+        let result = perform_default();
+
+        assert_eq!(result.m, Message::from_bytes(&result.m.to_bytes()).unwrap());
+        assert_eq!(
+            result.pk,
+            PublicKey::from_bytes(&result.pk.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            result.sk,
+            SecretKey::from_bytes(&result.sk.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            result.usk,
+            UserSecretKey::from_bytes(&result.usk.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            result.c,
+            CipherText::from_bytes(&result.c.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            result.cbasic,
+            BasicCipherText::from_bytes(&result.cbasic.to_bytes()).unwrap()
+        );
+    }
+}
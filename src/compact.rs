@@ -0,0 +1,109 @@
+//! Compact public keys, of which the bulk of the parameters is derived from a public seed.
+//!
+//! The public keys of the [`waters`](crate::waters), [`waters_naccache`](crate::waters_naccache)
+//! and [`kiltz_vahlis_one`](crate::kiltz_vahlis_one) schemes are dominated by a large number
+//! of random points. Using `setup_compact` in those modules, these points are instead derived
+//! by hash-to-curve from a 32-byte seed, such that only the seed and the remaining points need to be stored.
+//! The resulting keys are only a few hundred bytes in size, and must be expanded before use.
+//!
+//! Expansion hashes every parameter to the curve, which is relatively expensive.
+//! With the `alloc` feature, an `ExpansionCache` can be used to keep recently expanded public keys around.
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// Compact public keys that can be expanded to a full public key.
+pub trait Expand {
+    /// The full public key.
+    type PublicKey;
+
+    /// Derive the full public key, recomputing the parameters derived from the seed.
+    fn expand(&self) -> Self::PublicKey;
+}
+
+/// Cache of recently expanded public keys.
+///
+/// Holds at most `capacity` public keys, evicting the least recently used key when full.
+#[cfg(feature = "alloc")]
+pub struct ExpansionCache<C: Expand> {
+    capacity: usize,
+    entries: Vec<(C, C::PublicKey)>,
+}
+
+#[cfg(feature = "alloc")]
+impl<C: Expand + Clone + PartialEq> ExpansionCache<C> {
+    /// Create an empty cache holding at most `capacity` public keys.
+    ///
+    /// # Panics
+    ///
+    /// If `capacity` is zero.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "cache capacity must be positive");
+
+        ExpansionCache {
+            capacity,
+            entries: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Get the expanded public key, expanding it only if it is not cached yet.
+    pub fn get(&mut self, compact: &C) -> &C::PublicKey {
+        match self.entries.iter().position(|(c, _)| c == compact) {
+            Some(i) => {
+                let entry = self.entries.remove(i);
+                self.entries.push(entry);
+            }
+            None => {
+                if self.entries.len() == self.capacity {
+                    self.entries.remove(0);
+                }
+                self.entries.push((compact.clone(), compact.expand()));
+            }
+        }
+
+        &self.entries[self.entries.len() - 1].1
+    }
+
+    /// The number of cached public keys.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no public keys are cached.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Remove all cached public keys.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::waters_naccache::{setup_compact, CompactPublicKey};
+
+    #[test]
+    fn cache_evicts_least_recently_used() {
+        let mut rng = rand::thread_rng();
+
+        let (a, _) = setup_compact(&mut rng);
+        let (b, _) = setup_compact(&mut rng);
+        let (c, _) = setup_compact(&mut rng);
+
+        let mut cache = ExpansionCache::<CompactPublicKey>::new(2);
+        assert!(cache.is_empty());
+
+        assert!(*cache.get(&a) == a.expand());
+        cache.get(&b);
+        cache.get(&a);
+        cache.get(&c);
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.entries.iter().any(|(k, _)| *k == a));
+        assert!(cache.entries.iter().all(|(k, _)| *k != b));
+        assert!(*cache.get(&c) == c.expand());
+    }
+}
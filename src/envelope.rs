@@ -0,0 +1,739 @@
+//! Stable, self-describing wire format for the datastructures of every scheme.
+//!
+//! The byte representation returned by `to_bytes` may change between releases of this library.
+//! For long-term storage, objects should instead be wrapped in an envelope, which consists of a header
+//! followed by the byte representation of the object as payload:
+//!
+//! | Offset | Size | Content                                     |
+//! |--------|------|---------------------------------------------|
+//! | 0      | 4    | Magic bytes [`MAGIC`]                       |
+//! | 4      | 1    | Format version, currently [`VERSION`]       |
+//! | 5      | 1    | Scheme identifier, see [`Scheme`]           |
+//! | 6      | 1    | Object type identifier, see [`ObjectType`]  |
+//! | 7      | 4    | Payload length in bytes, big-endian         |
+//! | 11     |      | Payload                                     |
+//!
+//! Whenever the byte representation of any object changes, the format version is increased,
+//! such that envelopes written by older releases are rejected instead of misinterpreted.
+//! The test vectors of this module guard the current version against accidental changes.
+//!
+//! Use [`decode`] when the expected type is known, and [`decode_any`] to dispatch on the header.
+//!
+//! ```
+//! use ibe::envelope::{self, Object};
+//! use ibe::kiltz_vahlis_one::*;
+//!
+//! let mut rng = rand::thread_rng();
+//! let kid = Identity::derive_str("email:w.geraedts@sarif.nl");
+//! let (pk, _) = setup(&mut rng);
+//! let (c, _) = encrypt(&pk, &kid, &mut rng);
+//!
+//! let mut buf = [0u8; envelope::HEADER_SIZE + 144];
+//! envelope::encode_into(&c, &mut buf).unwrap();
+//!
+//! assert_eq!(envelope::decode::<CipherText>(&buf), Ok(c));
+//! assert!(matches!(envelope::decode_any(&buf), Ok(Object::KiltzVahlisOneCipherText(_))));
+//! ```
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use crate::util::Wipe;
+use crate::{
+    bbg_hibe, boneh_boyen_1, boneh_franklin, kiltz_vahlis_one, waters, waters_naccache, Compress,
+    Error,
+};
+
+/// Magic bytes with which every envelope starts.
+pub const MAGIC: [u8; 4] = *b"IBE\0";
+
+/// Version of the envelope format written by this release.
+pub const VERSION: u8 = 1;
+
+/// Size of the envelope header in bytes.
+pub const HEADER_SIZE: usize = 11;
+
+/// Identifier of the scheme an object belongs to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Scheme {
+    Waters = 1,
+    WatersNaccache = 2,
+    KiltzVahlisOne = 3,
+    BonehFranklin = 4,
+    BonehBoyen1 = 5,
+    BonehBoyenGoh = 6,
+}
+
+impl Scheme {
+    /// The identifier of this scheme in the envelope header.
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    /// Look up a scheme by its identifier in the envelope header.
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(Scheme::Waters),
+            2 => Some(Scheme::WatersNaccache),
+            3 => Some(Scheme::KiltzVahlisOne),
+            4 => Some(Scheme::BonehFranklin),
+            5 => Some(Scheme::BonehBoyen1),
+            6 => Some(Scheme::BonehBoyenGoh),
+            _ => None,
+        }
+    }
+}
+
+/// Identifier of the type of an object.
+///
+/// Not every scheme has objects of every type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ObjectType {
+    PublicKey = 1,
+    CompactPublicKey = 2,
+    SecretKey = 3,
+    UserSecretKey = 4,
+    Identity = 5,
+    Message = 6,
+    SymmetricKey = 7,
+    CipherText = 8,
+    BasicCipherText = 9,
+    KemCipherText = 10,
+}
+
+impl ObjectType {
+    /// The identifier of this object type in the envelope header.
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    /// Look up an object type by its identifier in the envelope header.
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(ObjectType::PublicKey),
+            2 => Some(ObjectType::CompactPublicKey),
+            3 => Some(ObjectType::SecretKey),
+            4 => Some(ObjectType::UserSecretKey),
+            5 => Some(ObjectType::Identity),
+            6 => Some(ObjectType::Message),
+            7 => Some(ObjectType::SymmetricKey),
+            8 => Some(ObjectType::CipherText),
+            9 => Some(ObjectType::BasicCipherText),
+            10 => Some(ObjectType::KemCipherText),
+            _ => None,
+        }
+    }
+}
+
+/// Parsed envelope header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Header {
+    scheme: Scheme,
+    object: ObjectType,
+    payload_len: usize,
+}
+
+impl Header {
+    /// Parse the header of an envelope.
+    ///
+    /// Checks the magic bytes and version, and that the payload length matches the length of the input.
+    pub fn parse(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(Error::InvalidLength {
+                expected: HEADER_SIZE,
+                actual: bytes.len(),
+            });
+        }
+
+        let (magic, version, scheme, object, payload_len) =
+            arrayref::array_refs![arrayref::array_ref![bytes, 0, HEADER_SIZE], 4, 1, 1, 1, 4];
+        if *magic != MAGIC {
+            return Err(Error::InvalidHeader);
+        }
+        if version[0] != VERSION {
+            return Err(Error::UnsupportedVersion(version[0]));
+        }
+
+        let scheme = Scheme::from_id(scheme[0]).ok_or(Error::InvalidHeader)?;
+        let object = ObjectType::from_id(object[0]).ok_or(Error::InvalidHeader)?;
+        let payload_len = u32::from_be_bytes(*payload_len) as usize;
+        if bytes.len() - HEADER_SIZE != payload_len {
+            return Err(Error::InvalidLength {
+                expected: HEADER_SIZE + payload_len,
+                actual: bytes.len(),
+            });
+        }
+
+        Ok(Header {
+            scheme,
+            object,
+            payload_len,
+        })
+    }
+
+    /// The scheme the enveloped object belongs to.
+    pub fn scheme(&self) -> Scheme {
+        self.scheme
+    }
+
+    /// The type of the enveloped object.
+    pub fn object(&self) -> ObjectType {
+        self.object
+    }
+
+    /// The length of the payload in bytes.
+    pub fn payload_len(&self) -> usize {
+        self.payload_len
+    }
+
+    fn to_bytes(self) -> [u8; HEADER_SIZE] {
+        let mut res = [0u8; HEADER_SIZE];
+        let (magic, version, scheme, object, payload_len) =
+            arrayref::mut_array_refs![&mut res, 4, 1, 1, 1, 4];
+        *magic = MAGIC;
+        version[0] = VERSION;
+        scheme[0] = self.scheme.id();
+        object[0] = self.object.id();
+        *payload_len = (self.payload_len as u32).to_be_bytes();
+        res
+    }
+}
+
+/// Types that can be wrapped in an envelope.
+///
+/// The byte representation of these types is a byte array, which forms the payload.
+pub trait Enveloped: Compress {
+    /// The scheme this type belongs to.
+    const SCHEME: Scheme;
+
+    /// The object type identifier of this type.
+    const OBJECT: ObjectType;
+}
+
+fn header<T: Enveloped>() -> Header {
+    Header {
+        scheme: T::SCHEME,
+        object: T::OBJECT,
+        payload_len: core::mem::size_of::<T::Output>(),
+    }
+}
+
+/// The size of the envelope of a type, in bytes.
+pub fn encoded_len<T: Enveloped>() -> usize {
+    HEADER_SIZE + header::<T>().payload_len
+}
+
+/// Wrap an object in an envelope, writing it to `out`, which must be exactly [`encoded_len`] bytes long.
+pub fn encode_into<T: Enveloped>(value: &T, out: &mut [u8]) -> Result<(), Error>
+where
+    T::Output: AsMut<[u8]>,
+{
+    if out.len() != encoded_len::<T>() {
+        return Err(Error::InvalidLength {
+            expected: encoded_len::<T>(),
+            actual: out.len(),
+        });
+    }
+
+    let (head, payload) = out.split_at_mut(HEADER_SIZE);
+    head.copy_from_slice(&header::<T>().to_bytes());
+
+    let mut bytes = value.to_bytes();
+    payload.copy_from_slice(bytes.as_mut());
+    bytes.as_mut().wipe();
+
+    Ok(())
+}
+
+/// Wrap an object in an envelope.
+#[cfg(feature = "alloc")]
+pub fn encode<T: Enveloped>(value: &T) -> Vec<u8>
+where
+    T::Output: AsMut<[u8]>,
+{
+    let mut res = alloc::vec![0u8; encoded_len::<T>()];
+    encode_into(value, &mut res).expect("buffer has the encoded length");
+    res
+}
+
+/// Unwrap an object of a known type from an envelope.
+///
+/// Rejects envelopes containing another object type, or an object of another scheme.
+/// If the payload is invalid, the component is not reported; use `TryFrom<&[u8]>` on the payload for details.
+pub fn decode<T: Enveloped>(bytes: &[u8]) -> Result<T, Error>
+where
+    T::Output: AsMut<[u8]> + for<'a> TryFrom<&'a [u8]>,
+{
+    let found = Header::parse(bytes)?;
+    let expected = header::<T>();
+    if (found.scheme, found.object) != (expected.scheme, expected.object) {
+        return Err(Error::UnexpectedObject {
+            scheme: found.scheme,
+            object: found.object,
+        });
+    }
+
+    let mut payload =
+        T::Output::try_from(&bytes[HEADER_SIZE..]).map_err(|_| Error::InvalidLength {
+            expected: encoded_len::<T>(),
+            actual: bytes.len(),
+        })?;
+    let res = Option::from(T::from_bytes(&payload));
+    payload.as_mut().wipe();
+
+    res.ok_or(Error::InvalidEncoding {
+        component: "payload",
+        index: None,
+    })
+}
+
+macro_rules! enveloped {
+    ($($variant:ident($scheme:ident, $object:ident, $t:ty)),+ $(,)?) => {
+        $(
+            impl Enveloped for $t {
+                const SCHEME: Scheme = Scheme::$scheme;
+                const OBJECT: ObjectType = ObjectType::$object;
+            }
+        )+
+
+        /// Object of any scheme and type, as unwrapped by [`decode_any`].
+        #[allow(clippy::large_enum_variant)]
+        #[derive(Clone)]
+        pub enum Object {
+            $($variant($t),)+
+        }
+
+        impl Object {
+            /// The scheme this object belongs to.
+            pub fn scheme(&self) -> Scheme {
+                match self {
+                    $(Object::$variant(_) => Scheme::$scheme,)+
+                }
+            }
+
+            /// The type of this object.
+            pub fn object_type(&self) -> ObjectType {
+                match self {
+                    $(Object::$variant(_) => ObjectType::$object,)+
+                }
+            }
+        }
+
+        /// Unwrap an object from an envelope, dispatching on the scheme and object type in the header.
+        ///
+        /// Rejects combinations of scheme and object type that do not exist.
+        pub fn decode_any(bytes: &[u8]) -> Result<Object, Error> {
+            let header = Header::parse(bytes)?;
+            match (header.scheme, header.object) {
+                $((Scheme::$scheme, ObjectType::$object) => decode::<$t>(bytes).map(Object::$variant),)+
+                (scheme, object) => Err(Error::UnexpectedObject { scheme, object }),
+            }
+        }
+    };
+}
+
+enveloped! {
+    WatersPublicKey(Waters, PublicKey, waters::PublicKey),
+    WatersCompactPublicKey(Waters, CompactPublicKey, waters::CompactPublicKey),
+    WatersSecretKey(Waters, SecretKey, waters::SecretKey),
+    WatersUserSecretKey(Waters, UserSecretKey, waters::UserSecretKey),
+    WatersIdentity(Waters, Identity, waters::Identity),
+    WatersMessage(Waters, Message, waters::Message),
+    WatersCipherText(Waters, CipherText, waters::CipherText),
+
+    WatersNaccachePublicKey(WatersNaccache, PublicKey, waters_naccache::PublicKey),
+    WatersNaccacheCompactPublicKey(WatersNaccache, CompactPublicKey, waters_naccache::CompactPublicKey),
+    WatersNaccacheSecretKey(WatersNaccache, SecretKey, waters_naccache::SecretKey),
+    WatersNaccacheUserSecretKey(WatersNaccache, UserSecretKey, waters_naccache::UserSecretKey),
+    WatersNaccacheIdentity(WatersNaccache, Identity, waters_naccache::Identity),
+    WatersNaccacheMessage(WatersNaccache, Message, waters_naccache::Message),
+    WatersNaccacheCipherText(WatersNaccache, CipherText, waters_naccache::CipherText),
+
+    KiltzVahlisOnePublicKey(KiltzVahlisOne, PublicKey, kiltz_vahlis_one::PublicKey),
+    KiltzVahlisOneCompactPublicKey(KiltzVahlisOne, CompactPublicKey, kiltz_vahlis_one::CompactPublicKey),
+    KiltzVahlisOneSecretKey(KiltzVahlisOne, SecretKey, kiltz_vahlis_one::SecretKey),
+    KiltzVahlisOneUserSecretKey(KiltzVahlisOne, UserSecretKey, kiltz_vahlis_one::UserSecretKey),
+    KiltzVahlisOneIdentity(KiltzVahlisOne, Identity, kiltz_vahlis_one::Identity),
+    KiltzVahlisOneSymmetricKey(KiltzVahlisOne, SymmetricKey, kiltz_vahlis_one::SymmetricKey),
+    KiltzVahlisOneCipherText(KiltzVahlisOne, CipherText, kiltz_vahlis_one::CipherText),
+
+    BonehFranklinPublicKey(BonehFranklin, PublicKey, boneh_franklin::PublicKey),
+    BonehFranklinSecretKey(BonehFranklin, SecretKey, boneh_franklin::SecretKey),
+    BonehFranklinUserSecretKey(BonehFranklin, UserSecretKey, boneh_franklin::UserSecretKey),
+    BonehFranklinIdentity(BonehFranklin, Identity, boneh_franklin::Identity),
+    BonehFranklinMessage(BonehFranklin, Message, boneh_franklin::Message),
+    BonehFranklinBasicCipherText(BonehFranklin, BasicCipherText, boneh_franklin::BasicCipherText),
+    BonehFranklinCipherText(BonehFranklin, CipherText, boneh_franklin::CipherText),
+
+    BonehBoyen1PublicKey(BonehBoyen1, PublicKey, boneh_boyen_1::PublicKey),
+    BonehBoyen1SecretKey(BonehBoyen1, SecretKey, boneh_boyen_1::SecretKey),
+    BonehBoyen1UserSecretKey(BonehBoyen1, UserSecretKey, boneh_boyen_1::UserSecretKey),
+    BonehBoyen1Identity(BonehBoyen1, Identity, boneh_boyen_1::Identity),
+    BonehBoyen1Message(BonehBoyen1, Message, boneh_boyen_1::Message),
+    BonehBoyen1SymmetricKey(BonehBoyen1, SymmetricKey, boneh_boyen_1::SymmetricKey),
+    BonehBoyen1CipherText(BonehBoyen1, CipherText, boneh_boyen_1::CipherText),
+    BonehBoyen1KemCipherText(BonehBoyen1, KemCipherText, boneh_boyen_1::KemCipherText),
+
+    BonehBoyenGohPublicKey(BonehBoyenGoh, PublicKey, bbg_hibe::PublicKey),
+    BonehBoyenGohSecretKey(BonehBoyenGoh, SecretKey, bbg_hibe::SecretKey),
+    BonehBoyenGohUserSecretKey(BonehBoyenGoh, UserSecretKey, bbg_hibe::UserSecretKey),
+    BonehBoyenGohIdentity(BonehBoyenGoh, Identity, bbg_hibe::Identity),
+    BonehBoyenGohMessage(BonehBoyenGoh, Message, bbg_hibe::Message),
+    BonehBoyenGohCipherText(BonehBoyenGoh, CipherText, bbg_hibe::CipherText),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{sha3_256, XofRng};
+    use std::string::String;
+    use std::vec::Vec;
+
+    const SEED: [u8; 32] = [7u8; 32];
+    const ID: &str = "email:w.geraedts@sarif.nl";
+
+    fn envelope<T: Enveloped>(value: &T) -> Vec<u8>
+    where
+        T::Output: AsMut<[u8]>,
+    {
+        let mut res = std::vec![0u8; encoded_len::<T>()];
+        encode_into(value, &mut res).unwrap();
+        res
+    }
+
+    fn digest<T: Enveloped>(value: &T) -> String
+    where
+        T::Output: AsMut<[u8]>,
+    {
+        sha3_256(&envelope(value))
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// Deterministically generated objects of which the envelopes are frozen.
+    fn vectors() -> Vec<(&'static str, String)> {
+        let mut rng = XofRng::new(b"ibe-envelope-test-vectors", &SEED);
+        let mut res = Vec::new();
+
+        {
+            use crate::waters::*;
+            let kid = Identity::derive_str(ID);
+            let (pk, sk) = setup_from_seed(&SEED);
+            let (cpk, _) = setup_compact(&mut rng);
+            let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+            let m = Message::generate(&mut rng);
+            let c = encrypt(&pk, &kid, &m, &mut rng);
+            res.push(("waters pk", digest(&pk)));
+            res.push(("waters cpk", digest(&cpk)));
+            res.push(("waters sk", digest(&sk)));
+            res.push(("waters usk", digest(&usk)));
+            res.push(("waters id", digest(&kid)));
+            res.push(("waters m", digest(&m)));
+            res.push(("waters c", digest(&c)));
+        }
+        {
+            use crate::waters_naccache::*;
+            let kid = Identity::derive_str(ID);
+            let (pk, sk) = setup_from_seed(&SEED);
+            let (cpk, _) = setup_compact(&mut rng);
+            let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+            let m = Message::generate(&mut rng);
+            let c = encrypt(&pk, &kid, &m, &mut rng);
+            res.push(("waters_naccache pk", digest(&pk)));
+            res.push(("waters_naccache cpk", digest(&cpk)));
+            res.push(("waters_naccache sk", digest(&sk)));
+            res.push(("waters_naccache usk", digest(&usk)));
+            res.push(("waters_naccache id", digest(&kid)));
+            res.push(("waters_naccache m", digest(&m)));
+            res.push(("waters_naccache c", digest(&c)));
+        }
+        {
+            use crate::kiltz_vahlis_one::*;
+            let kid = Identity::derive_str(ID);
+            let (pk, sk) = setup_from_seed(&SEED);
+            let (cpk, _) = setup_compact(&mut rng);
+            let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+            let (c, k) = encrypt(&pk, &kid, &mut rng);
+            res.push(("kiltz_vahlis_one pk", digest(&pk)));
+            res.push(("kiltz_vahlis_one cpk", digest(&cpk)));
+            res.push(("kiltz_vahlis_one sk", digest(&sk)));
+            res.push(("kiltz_vahlis_one usk", digest(&usk)));
+            res.push(("kiltz_vahlis_one id", digest(&kid)));
+            res.push(("kiltz_vahlis_one k", digest(&k)));
+            res.push(("kiltz_vahlis_one c", digest(&c)));
+        }
+        {
+            use crate::boneh_franklin::*;
+            let kid = Identity::derive_str(ID);
+            let (pk, sk) = setup_from_seed(&SEED);
+            let m = Message::generate(&mut rng);
+            res.push(("boneh_franklin pk", digest(&pk)));
+            res.push(("boneh_franklin usk", digest(&extract_usk(&pk, &sk, &kid))));
+            res.push((
+                "boneh_franklin c",
+                digest(&encrypt(&pk, &kid, &m, &mut rng)),
+            ));
+        }
+        {
+            use crate::boneh_boyen_1::*;
+            let kid = Identity::derive_str(ID);
+            let (pk, sk) = setup_from_seed(&SEED);
+            let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+            let (c, _) = encaps(&pk, &kid, &mut rng);
+            res.push(("boneh_boyen_1 pk", digest(&pk)));
+            res.push(("boneh_boyen_1 usk", digest(&usk)));
+            res.push(("boneh_boyen_1 kem c", digest(&c)));
+        }
+        {
+            use crate::bbg_hibe::*;
+            let kid = Identity::derive_path(&["acme", "finance"]).unwrap();
+            let (pk, sk) = setup_from_seed(MAX_DEPTH, &SEED);
+            let usk = extract_usk(&pk, &sk, &kid, &mut rng).unwrap();
+            let m = Message::generate(&mut rng);
+            res.push(("bbg_hibe pk", digest(&pk)));
+            res.push(("bbg_hibe usk", digest(&usk)));
+            res.push((
+                "bbg_hibe c",
+                digest(&encrypt(&pk, &kid, &m, &mut rng).unwrap()),
+            ));
+        }
+
+        res
+    }
+
+    /// SHA3-256 digests of the envelopes of [`vectors`], frozen for format version 1.
+    const VECTORS: &[(&str, &str)] = &[
+        (
+            "waters pk",
+            "6d24df3ff34e4d44e22e7d7f48d1b053c6a986a1c16526db424353c912ea1555",
+        ),
+        (
+            "waters cpk",
+            "c85711cc8d49abfc86cd09554f46c69f4b68c7ddca53e3cddfb1086d85394023",
+        ),
+        (
+            "waters sk",
+            "ddd74d604406a1c1bd59d3e7e4b0247a8221134077bf78567054e4b7184edb4b",
+        ),
+        (
+            "waters usk",
+            "72dbb591a2e27c971bde874aea67ef6df97c7963b7f0575598c2230d297652c0",
+        ),
+        (
+            "waters id",
+            "4d7dfc75d2001a7336ba4b93e7984520ff4ed5ef8e295d23125babf6374270c1",
+        ),
+        (
+            "waters m",
+            "e4ff9f58d705d713d3f8a8d2a653f1fba634616e5a3f450a1a2b1735774a289b",
+        ),
+        (
+            "waters c",
+            "aad02b7ba092c22ad7c5f7a928364f5287e22bca0073ac9ab3e4ab5773208dc8",
+        ),
+        (
+            "waters_naccache pk",
+            "1b45d27b4c827d2bf5b329c518c1ff9f186a90e05406a38abfc89746c8c01ef3",
+        ),
+        (
+            "waters_naccache cpk",
+            "b6a15a743ae511e97a21adf13f437aa3aeea60ece328a875abf6496767de17ee",
+        ),
+        (
+            "waters_naccache sk",
+            "b4537ce5c9bbced3c01cdc997017bac14ec98246f6399ee10ab1cdf8e27776c4",
+        ),
+        (
+            "waters_naccache usk",
+            "b3bf168d132fcbe48c5f7b1fd4e8f1830c4fc4e3ffd30c4ca1aefd209276e249",
+        ),
+        (
+            "waters_naccache id",
+            "187c5f4c1bac49a798e115c22cc33d1567e5ef6fc76779fcb48257f8600a5eaf",
+        ),
+        (
+            "waters_naccache m",
+            "7fddaf0470708dd75dd90649c7b9eb78e700e4822d917dde6df8b40126aa4580",
+        ),
+        (
+            "waters_naccache c",
+            "af49031257c00a41e23803ea3bf25bc1baa6f7d1be8a2dff7ca296b74eddbb5e",
+        ),
+        (
+            "kiltz_vahlis_one pk",
+            "9250ecee085408f2de9f82f08cd064b322b9f48d4dc368f3e6007cc2340b5cf5",
+        ),
+        (
+            "kiltz_vahlis_one cpk",
+            "a3f400a0ee4c80031f61e4123c1b3502d646f6dfc0eb6b47f4a00cd4a8cbf268",
+        ),
+        (
+            "kiltz_vahlis_one sk",
+            "86a0b96619ef28fac8f0b871869e68190aa4f388ef5f6d0ff5e1be84df2beedf",
+        ),
+        (
+            "kiltz_vahlis_one usk",
+            "e645e1d4689c31f85b099755005fa3debd5fbdd39ab4b6734a97c32fa46821f3",
+        ),
+        (
+            "kiltz_vahlis_one id",
+            "93d53ad1e9cfb07a57a9db88c1d6b536693ad14034125a4729af2dcabdbee54e",
+        ),
+        (
+            "kiltz_vahlis_one k",
+            "88cc3bdcb370390e12f59543b7c3851ec97d691c29b265897a64f46187b73c91",
+        ),
+        (
+            "kiltz_vahlis_one c",
+            "894b09c8de818ba5c87465785b0c85ed8133f380538e1cfecfe21599419a1614",
+        ),
+        (
+            "boneh_franklin pk",
+            "daddc8b1a3c95b02713bab3bbc5c2743b51f89989be1789712637f9257449bdd",
+        ),
+        (
+            "boneh_franklin usk",
+            "854bc914c9959dafae718a4d888f50b509e9e49498840fd62c451780bf17af10",
+        ),
+        (
+            "boneh_franklin c",
+            "2d3256c2ca4d5aa73187c0350099d882d241ec74492d3f98571ac8ab0ab592fa",
+        ),
+        (
+            "boneh_boyen_1 pk",
+            "a4bf128091fed2be09d8ac0a30eb9e54e45f037cc7767ae4fbcc3488e26c13be",
+        ),
+        (
+            "boneh_boyen_1 usk",
+            "2b75b113ae24ed950fac0f3274d9c4362ef03840da638bb9d7d0186d92ac4f68",
+        ),
+        (
+            "boneh_boyen_1 kem c",
+            "877ba3d18da5efd769748a554039c54b572e111687b1786087c5fdb0e6761dbb",
+        ),
+        (
+            "bbg_hibe pk",
+            "e1969bd8d3c519fae3a164d97f4fff4ed327390d154e386c97e8b838e0e8110a",
+        ),
+        (
+            "bbg_hibe usk",
+            "1965fa61d91fb12c97bae4143811f0594f8515db4c087836d6f9ab13ca110003",
+        ),
+        (
+            "bbg_hibe c",
+            "7b15ce3179ea3418d21a712b4d42767114b36226f9b0417447415d8cb6c3dd95",
+        ),
+    ];
+
+    /// Envelope of a Kiltz-Vahlis IBE1 ciphertext, frozen for format version 1.
+    const KILTZ_VAHLIS_ONE_CIPHERTEXT: &str = "494245000103080000009099524beed4a55f9552cbc051f43c74b357dd7f4b38f1a3038acc52ef0a629c5b3b752d05231c0cc85b186d6a0a187c9c0c3b2fb56b4794ef81a984fb7827af21e6a4b7246cf0020f41258cb459962173264a6d370495b1c75b9dbb652b47cc99991917ff5d8f2a6b96cd42e0c4339222448203565a85ded8fa96c7bd79987f41aa4e81e0bfb9a4153d7e0367f5683c56";
+
+    fn from_hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn frozen_vectors() {
+        let vectors = vectors();
+        assert_eq!(vectors.len(), VECTORS.len());
+        for ((name, digest), (frozen_name, frozen)) in vectors.iter().zip(VECTORS) {
+            assert_eq!(name, frozen_name);
+            assert_eq!(digest, frozen, "envelope of {} changed", name);
+        }
+    }
+
+    #[test]
+    fn frozen_envelope() {
+        use crate::kiltz_vahlis_one::*;
+
+        let mut rng = XofRng::new(b"ibe-envelope-test-ciphertext", &SEED);
+        let kid = Identity::derive_str(ID);
+        let (pk, sk) = setup_from_seed(&SEED);
+        let (c, k) = encrypt(&pk, &kid, &mut rng);
+
+        let bytes = from_hex(KILTZ_VAHLIS_ONE_CIPHERTEXT);
+        assert_eq!(envelope(&c), bytes);
+
+        let header = Header::parse(&bytes).unwrap();
+        assert_eq!(header.scheme(), Scheme::KiltzVahlisOne);
+        assert_eq!(header.object(), ObjectType::CipherText);
+        assert_eq!(header.payload_len(), 144);
+
+        let c2 = match decode_any(&bytes) {
+            Ok(Object::KiltzVahlisOneCipherText(c2)) => c2,
+            _ => panic!("expected a Kiltz-Vahlis IBE1 ciphertext"),
+        };
+        let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+        assert_eq!(decrypt(&usk, &c2), k);
+    }
+
+    #[test]
+    fn rejects_mismatches() {
+        use crate::kiltz_vahlis_one::CipherText;
+
+        let bytes = from_hex(KILTZ_VAHLIS_ONE_CIPHERTEXT);
+        let unexpected = Error::UnexpectedObject {
+            scheme: Scheme::KiltzVahlisOne,
+            object: ObjectType::CipherText,
+        };
+        assert_eq!(decode::<waters::CipherText>(&bytes).err(), Some(unexpected));
+        assert_eq!(
+            decode::<kiltz_vahlis_one::SymmetricKey>(&bytes).err(),
+            Some(unexpected)
+        );
+
+        // Kiltz-Vahlis IBE1 has no messages.
+        let mut corrupted = bytes.clone();
+        corrupted[6] = ObjectType::Message.id();
+        assert!(matches!(
+            decode_any(&corrupted),
+            Err(Error::UnexpectedObject {
+                scheme: Scheme::KiltzVahlisOne,
+                object: ObjectType::Message,
+            })
+        ));
+
+        let mut corrupted = bytes.clone();
+        corrupted[0] ^= 1;
+        assert_eq!(
+            decode::<CipherText>(&corrupted).err(),
+            Some(Error::InvalidHeader)
+        );
+
+        let mut corrupted = bytes.clone();
+        corrupted[4] = 2;
+        assert_eq!(
+            decode::<CipherText>(&corrupted).err(),
+            Some(Error::UnsupportedVersion(2))
+        );
+
+        let mut corrupted = bytes.clone();
+        corrupted[5] = 0;
+        assert!(matches!(decode_any(&corrupted), Err(Error::InvalidHeader)));
+
+        assert_eq!(
+            decode::<CipherText>(&bytes[..bytes.len() - 1]).err(),
+            Some(Error::InvalidLength {
+                expected: bytes.len(),
+                actual: bytes.len() - 1,
+            })
+        );
+
+        let mut corrupted = bytes;
+        corrupted[HEADER_SIZE..HEADER_SIZE + 96].copy_from_slice(&[0xff; 96]);
+        assert_eq!(
+            decode::<CipherText>(&corrupted).err(),
+            Some(Error::InvalidEncoding {
+                component: "payload",
+                index: None,
+            })
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use crate::envelope::{ObjectType, Scheme};
+use crate::validation::ValidationError;
+
+/// Reasons why decoding a datastructure of this crate failed.
+///
+/// Returned by the `TryFrom<&[u8]>` implementations of the key and ciphertext types.
+/// Unlike the `from_bytes` methods, these accept slices of any length and report which component is invalid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The input does not have the length of the encoding.
+    InvalidLength { expected: usize, actual: usize },
+    /// A component of the input is not a valid encoding.
+    InvalidEncoding {
+        component: &'static str,
+        index: Option<usize>,
+    },
+    /// A component of the input encodes an element outside the prime order subgroup.
+    NotInSubgroup {
+        component: &'static str,
+        index: Option<usize>,
+    },
+    /// The input is not a valid encoding of a secret value.
+    ///
+    /// Secret values are decoded in constant time, hence the invalid component is not reported.
+    InvalidSecret,
+    /// A public key failed validation.
+    Validation(ValidationError),
+    /// The input does not start with an envelope header, or names an unknown scheme or object type.
+    InvalidHeader,
+    /// The envelope was written using an unsupported version of the format.
+    UnsupportedVersion(u8),
+    /// The envelope contains another object than expected,
+    /// or a combination of scheme and object type that does not exist.
+    UnexpectedObject { scheme: Scheme, object: ObjectType },
+    /// The input is not valid ASCII armor, or its label does not match the armored object.
+    InvalidArmor,
+    /// The checksum of the ASCII armor does not match its contents.
+    ChecksumMismatch,
+}
+
+fn write_component(
+    f: &mut core::fmt::Formatter<'_>,
+    component: &str,
+    index: &Option<usize>,
+) -> core::fmt::Result {
+    match index {
+        Some(i) => write!(f, "{}[{}]", component, i),
+        None => f.write_str(component),
+    }
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::InvalidLength { expected, actual } => {
+                write!(
+                    f,
+                    "invalid length: expected {} bytes, got {}",
+                    expected, actual
+                )
+            }
+            Error::InvalidEncoding { component, index } => {
+                f.write_str("invalid encoding of ")?;
+                write_component(f, component, index)
+            }
+            Error::NotInSubgroup { component, index } => {
+                write_component(f, component, index)?;
+                f.write_str(" is not in the prime order subgroup")
+            }
+            Error::InvalidSecret => f.write_str("invalid encoding of secret value"),
+            Error::Validation(e) => core::fmt::Display::fmt(e, f),
+            Error::InvalidHeader => f.write_str("invalid envelope header"),
+            Error::UnsupportedVersion(v) => write!(f, "unsupported envelope version {}", v),
+            Error::UnexpectedObject { scheme, object } => {
+                write!(f, "unexpected object {:?} of scheme {:?}", object, scheme)
+            }
+            Error::InvalidArmor => f.write_str("invalid ASCII armor"),
+            Error::ChecksumMismatch => f.write_str("ASCII armor checksum mismatch"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Validation(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ValidationError> for Error {
+    fn from(e: ValidationError) -> Self {
+        Error::Validation(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validation::ValidationErrorKind;
+
+    #[test]
+    fn display() {
+        let err = Error::InvalidEncoding {
+            component: "h",
+            index: Some(5),
+        };
+        assert_eq!(format!("{}", err), "invalid encoding of h[5]");
+
+        let err = Error::InvalidLength {
+            expected: 48,
+            actual: 47,
+        };
+        assert_eq!(
+            format!("{}", err),
+            "invalid length: expected 48 bytes, got 47"
+        );
+
+        let err = Error::from(ValidationError::new(
+            "z",
+            None,
+            ValidationErrorKind::Identity,
+        ));
+        assert_eq!(
+            format!("{}", err),
+            "invalid public key component z: identity element"
+        );
+    }
+}
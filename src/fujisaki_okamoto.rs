@@ -0,0 +1,239 @@
+//! Fujisaki-Okamoto transform turning a CPA-secure IBE scheme into a CCA2-secure IBKEM.
+//!  * From: "[Secure Integration of Asymmetric and Symmetric Encryption Schemes](https://link.springer.com/chapter/10.1007/3-540-48405-1_34)"
+//!  * Published in: CRYPTO, 1999
+//!  * Implicit rejection as in: "[A Modular Analysis of the Fujisaki-Okamoto Transformation](https://eprint.iacr.org/2017/604)"
+//!
+//! Can be applied to any scheme implementing [`DerandomizedIBE`],
+//! such as [`Waters`](crate::waters::Waters), [`WatersNaccache`](crate::waters_naccache::WatersNaccache)
+//! and [`BonehFranklin`](crate::boneh_franklin::BonehFranklin).
+//!
+//! Encapsulation encrypts a random message, deriving the encryption randomness from that message.
+//! Decapsulation decrypts the message, re-encrypts it and compares the result with the ciphertext.
+//! A mismatching ciphertext does not result in an error, but in a pseudorandom shared secret
+//! derived from the user secret key and the ciphertext.
+//!
+//! Uses [SHA3-512](https://crates.io/crates/tiny-keccak) for deriving randomness and
+//! [SHA3-256](https://crates.io/crates/tiny-keccak) for deriving shared secrets.
+//! All operations in this library are implemented to run in constant time.
+
+use core::marker::PhantomData;
+use rand::{CryptoRng, RngCore};
+use subtle::{ConditionallySelectable, ConstantTimeEq};
+
+use crate::util::*;
+use crate::{boneh_franklin, waters, waters_naccache};
+use crate::{Compress, DerandomizedIBE, IBKEM};
+
+const COINS_DST: &[u8] = b"ibe-fujisaki-okamoto-coins";
+const KEY_DST: &[u8] = b"ibe-fujisaki-okamoto-key";
+const REJECT_DST: &[u8] = b"ibe-fujisaki-okamoto-reject";
+
+/// The Fujisaki-Okamoto transform applied to the scheme `S`.
+#[derive(Clone, Copy, Debug)]
+pub struct FujisakiOkamoto<S>(PhantomData<S>);
+
+/// CCA2-secure IBKEM based on the Boneh-Franklin BasicIdent scheme.
+pub type BonehFranklinFO = FujisakiOkamoto<boneh_franklin::BonehFranklin>;
+
+/// CCA2-secure IBKEM based on the Waters scheme.
+pub type WatersFO = FujisakiOkamoto<waters::Waters>;
+
+/// CCA2-secure IBKEM based on the Waters-Naccache scheme.
+pub type WatersNaccacheFO = FujisakiOkamoto<waters_naccache::WatersNaccache>;
+
+/// User secret key of the underlying scheme, together with the identity it was extracted for.
+///
+/// The identity is required to re-encrypt during decapsulation.
+pub struct UserSecretKey<S: DerandomizedIBE> {
+    usk: S::UserSecretKey,
+    id: S::Identity,
+}
+
+#[cfg(feature = "zeroize")]
+impl<S: DerandomizedIBE> zeroize::Zeroize for UserSecretKey<S>
+where
+    S::UserSecretKey: zeroize::Zeroize,
+{
+    fn zeroize(&mut self) {
+        self.usk.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<S: DerandomizedIBE> zeroize::ZeroizeOnDrop for UserSecretKey<S> where
+    S::UserSecretKey: zeroize::ZeroizeOnDrop
+{
+}
+
+/// A shared secret derived from an encapsulated message.
+///
+/// Suitable to be used directly as a symmetric key.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SharedSecret([u8; 32]);
+
+zeroize_on_drop!(SharedSecret: 0);
+
+impl SharedSecret {
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0
+    }
+
+    pub fn from_bytes(bytes: &[u8; 32]) -> Self {
+        SharedSecret(*bytes)
+    }
+}
+
+fn derive_coins<S: DerandomizedIBE>(m: &S::Message) -> [u8; 64]
+where
+    S::Message: Compress,
+{
+    sha3_512_multi(&[COINS_DST, m.to_bytes().as_ref()])
+}
+
+impl<S> IBKEM for FujisakiOkamoto<S>
+where
+    S: DerandomizedIBE,
+    S::Identity: Clone,
+    S::UserSecretKey: Compress,
+    S::Message: Compress,
+    S::CipherText: Compress,
+{
+    type PublicKey = S::PublicKey;
+    type SecretKey = S::SecretKey;
+    type UserSecretKey = UserSecretKey<S>;
+    type Identity = S::Identity;
+    type CipherText = S::CipherText;
+    type SharedSecret = SharedSecret;
+
+    fn setup<R: RngCore + CryptoRng>(rng: &mut R) -> (S::PublicKey, S::SecretKey) {
+        S::setup(rng)
+    }
+
+    fn extract_usk<R: RngCore + CryptoRng>(
+        pk: &S::PublicKey,
+        sk: &S::SecretKey,
+        id: &S::Identity,
+        rng: &mut R,
+    ) -> UserSecretKey<S> {
+        UserSecretKey {
+            usk: S::extract_usk(pk, sk, id, rng),
+            id: id.clone(),
+        }
+    }
+
+    fn encaps<R: RngCore + CryptoRng>(
+        pk: &S::PublicKey,
+        id: &S::Identity,
+        rng: &mut R,
+    ) -> (S::CipherText, SharedSecret) {
+        let m = S::generate_message(rng);
+        let mut coins = derive_coins::<S>(&m);
+
+        let c = S::encrypt_derandomized(pk, id, &m, &coins);
+        coins.wipe();
+        let k = sha3_256_multi(&[KEY_DST, m.to_bytes().as_ref(), c.to_bytes().as_ref()]);
+
+        (c, SharedSecret(k))
+    }
+
+    fn decaps(pk: &S::PublicKey, usk: &UserSecretKey<S>, c: &S::CipherText) -> SharedSecret {
+        let m = S::decrypt(&usk.usk, c);
+        let mut coins = derive_coins::<S>(&m);
+
+        let c2 = S::encrypt_derandomized(pk, &usk.id, &m, &coins);
+        coins.wipe();
+
+        let cbytes = c.to_bytes();
+        let valid = cbytes.as_ref().ct_eq(c2.to_bytes().as_ref());
+
+        let mut k = sha3_256_multi(&[KEY_DST, m.to_bytes().as_ref(), cbytes.as_ref()]);
+        let mut kreject =
+            sha3_256_multi(&[REJECT_DST, usk.usk.to_bytes().as_ref(), cbytes.as_ref()]);
+
+        let mut res = [0u8; 32];
+        for ((r, ki), kri) in res.iter_mut().zip(k.iter()).zip(kreject.iter()) {
+            *r = u8::conditional_select(kri, ki, valid);
+        }
+        k.wipe();
+        kreject.wipe();
+
+        SharedSecret(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Derive, IBE};
+
+    const ID: &str = "email:w.geraedts@sarif.nl";
+    const OTHER_ID: &str = "email:l.botros@cs.ru.nl";
+
+    fn eq_encaps_decaps<S: IBKEM>()
+    where
+        S::SharedSecret: core::fmt::Debug + PartialEq,
+    {
+        let mut rng = rand::thread_rng();
+
+        let kid = S::Identity::derive_str(ID);
+
+        let (pk, sk) = S::setup(&mut rng);
+        let usk = S::extract_usk(&pk, &sk, &kid, &mut rng);
+
+        let (c, k) = S::encaps(&pk, &kid, &mut rng);
+        let k2 = S::decaps(&pk, &usk, &c);
+
+        assert_eq!(k, k2);
+    }
+
+    #[test]
+    fn eq_encaps_decaps_waters() {
+        eq_encaps_decaps::<WatersFO>();
+    }
+
+    #[test]
+    fn eq_encaps_decaps_waters_naccache() {
+        eq_encaps_decaps::<WatersNaccacheFO>();
+    }
+
+    #[test]
+    fn eq_encaps_decaps_boneh_franklin() {
+        eq_encaps_decaps::<BonehFranklinFO>();
+    }
+
+    #[test]
+    fn ne_decaps_other_identity() {
+        let mut rng = rand::thread_rng();
+
+        let kid = waters::Identity::derive_str(ID);
+        let other_kid = waters::Identity::derive_str(OTHER_ID);
+
+        let (pk, sk) = WatersFO::setup(&mut rng);
+        let usk = WatersFO::extract_usk(&pk, &sk, &other_kid, &mut rng);
+
+        let (c, k) = WatersFO::encaps(&pk, &kid, &mut rng);
+
+        assert_ne!(k, WatersFO::decaps(&pk, &usk, &c));
+    }
+
+    #[test]
+    fn implicit_rejection() {
+        let mut rng = rand::thread_rng();
+
+        let kid = waters::Identity::derive_str(ID);
+
+        let (pk, sk) = WatersFO::setup(&mut rng);
+        let usk = WatersFO::extract_usk(&pk, &sk, &kid, &mut rng);
+
+        // A ciphertext not generated with derived coins must be rejected,
+        // consistently resulting in the same pseudorandom shared secret.
+        let m = waters::Message::generate(&mut rng);
+        let c = waters::Waters::encrypt(&pk, &kid, &m, &mut rng);
+
+        let k = WatersFO::decaps(&pk, &usk, &c);
+        let kaccept = sha3_256_multi(&[KEY_DST, &m.to_bytes(), &c.to_bytes()]);
+
+        assert_ne!(k, SharedSecret(kaccept));
+        assert_eq!(k, WatersFO::decaps(&pk, &usk, &c));
+    }
+}
@@ -0,0 +1,193 @@
+//! Hybrid encryption of arbitrary byte messages using the Kiltz-Vahlis IBE1 scheme and AES-256-GCM.
+//!
+//! A fresh symmetric key is encapsulated for an identity using [`kiltz_vahlis_one`](crate::kiltz_vahlis_one).
+//! An AES-256-GCM key is derived from that symmetric key and the IBE ciphertext
+//! using [`SymmetricKey::derive_key`], with which the plaintext is sealed.
+//!
+//! The resulting byte string is laid out as follows:
+//!
+//! | Field                   | Size (bytes)     |
+//! |-------------------------|------------------|
+//! | Magic bytes `IBEH`      | 4                |
+//! | Format version          | 1                |
+//! | IBE ciphertext          | 144              |
+//! | AES-GCM nonce           | 12               |
+//! | AES-GCM ciphertext      | plaintext length |
+//! | AES-GCM tag             | 16               |
+//!
+//! The magic bytes, format version and IBE ciphertext are authenticated as associated data.
+//!
+//! Requires the `hybrid` feature.
+
+use aes_gcm::aead::{AeadInPlace, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce, Tag};
+use alloc::vec::Vec;
+use arrayref::array_ref;
+use rand::{CryptoRng, RngCore};
+
+use crate::kiltz_vahlis_one::{
+    decrypt, encrypt, CipherText, Identity, PublicKey, SymmetricKey, UserSecretKey,
+};
+use crate::util::Wipe;
+
+const MAGIC: &[u8; 4] = b"IBEH";
+const VERSION: u8 = 1;
+
+const HEADER_SIZE: usize = MAGIC.len() + 1;
+const CIPHERTEXT_SIZE: usize = 144;
+const NONCE_SIZE: usize = 12;
+const TAG_SIZE: usize = 16;
+
+const AAD_SIZE: usize = HEADER_SIZE + CIPHERTEXT_SIZE;
+const PREFIX_SIZE: usize = AAD_SIZE + NONCE_SIZE;
+
+/// Number of bytes a sealed message is larger than its plaintext.
+pub const OVERHEAD: usize = PREFIX_SIZE + TAG_SIZE;
+
+const KDF_INFO: &[u8] = b"ibe-hybrid-aes256gcm";
+
+/// Reasons why a sealed message could not be opened.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The message is shorter than the minimal overhead.
+    Truncated,
+    /// The magic bytes or format version are not recognized.
+    InvalidHeader,
+    /// The IBE ciphertext does not consist of valid curve points.
+    InvalidCipherText,
+    /// The AES-GCM tag does not match, either because the message was tampered with
+    /// or because it was not sealed for the identity of the user secret key.
+    Authentication,
+}
+
+fn derive_cipher(k: &SymmetricKey, c: &CipherText) -> Aes256Gcm {
+    let mut key = [0u8; 32];
+    k.derive_key(c, KDF_INFO, &mut key);
+
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    key.wipe();
+
+    cipher
+}
+
+/// Seal a plaintext for an identity using the PKG public key.
+pub fn seal<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    v: &Identity,
+    plaintext: &[u8],
+    rng: &mut R,
+) -> Vec<u8> {
+    let (c, k) = encrypt(pk, v, rng);
+    let cipher = derive_cipher(&k, &c);
+    let cbytes = c.to_bytes();
+
+    let mut nonce = [0u8; NONCE_SIZE];
+    rng.fill_bytes(&mut nonce);
+
+    let mut res = Vec::with_capacity(OVERHEAD + plaintext.len());
+    res.extend_from_slice(MAGIC);
+    res.push(VERSION);
+    res.extend_from_slice(&cbytes);
+    res.extend_from_slice(&nonce);
+    res.extend_from_slice(plaintext);
+
+    let (prefix, body) = res.split_at_mut(PREFIX_SIZE);
+    let tag = cipher
+        .encrypt_in_place_detached(Nonce::from_slice(&nonce), &prefix[..AAD_SIZE], body)
+        .expect("plaintext exceeds the AES-GCM length limit");
+
+    res.extend_from_slice(&tag);
+    res
+}
+
+/// Open a sealed message using a user secret key.
+pub fn open(usk: &UserSecretKey, sealed: &[u8]) -> Result<Vec<u8>, Error> {
+    if sealed.len() < OVERHEAD {
+        return Err(Error::Truncated);
+    }
+
+    if &sealed[..MAGIC.len()] != MAGIC || sealed[MAGIC.len()] != VERSION {
+        return Err(Error::InvalidHeader);
+    }
+
+    let cbytes = array_ref![sealed, HEADER_SIZE, CIPHERTEXT_SIZE];
+    let c: Option<CipherText> = CipherText::from_bytes(cbytes).into();
+    let c = c.ok_or(Error::InvalidCipherText)?;
+
+    let k = decrypt(usk, &c);
+    let cipher = derive_cipher(&k, &c);
+
+    let nonce = Nonce::from_slice(&sealed[AAD_SIZE..PREFIX_SIZE]);
+    let (body, tag) = sealed[PREFIX_SIZE..].split_at(sealed.len() - OVERHEAD);
+
+    let mut res = body.to_vec();
+    cipher
+        .decrypt_in_place_detached(nonce, &sealed[..AAD_SIZE], &mut res, Tag::from_slice(tag))
+        .map_err(|_| Error::Authentication)?;
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kiltz_vahlis_one::{extract_usk, setup};
+
+    const ID: &str = "email:w.geraedts@sarif.nl";
+    const OTHER_ID: &str = "email:l.botros@cs.ru.nl";
+    const PLAINTEXT: &[u8] = b"All your base are belong to us.";
+
+    #[test]
+    fn eq_seal_open() {
+        let mut rng = rand::thread_rng();
+
+        let kid = Identity::derive_str(ID);
+        let (pk, sk) = setup(&mut rng);
+        let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+
+        let sealed = seal(&pk, &kid, PLAINTEXT, &mut rng);
+        assert_eq!(sealed.len(), PLAINTEXT.len() + OVERHEAD);
+        assert_eq!(open(&usk, &sealed).unwrap(), PLAINTEXT);
+
+        let sealed = seal(&pk, &kid, &[], &mut rng);
+        assert_eq!(open(&usk, &sealed).unwrap(), &[0u8; 0]);
+    }
+
+    #[test]
+    fn reject_other_identity() {
+        let mut rng = rand::thread_rng();
+
+        let kid = Identity::derive_str(ID);
+        let other_kid = Identity::derive_str(OTHER_ID);
+        let (pk, sk) = setup(&mut rng);
+        let usk = extract_usk(&pk, &sk, &other_kid, &mut rng);
+
+        let sealed = seal(&pk, &kid, PLAINTEXT, &mut rng);
+        assert_eq!(open(&usk, &sealed), Err(Error::Authentication));
+    }
+
+    #[test]
+    fn reject_tampered() {
+        let mut rng = rand::thread_rng();
+
+        let kid = Identity::derive_str(ID);
+        let (pk, sk) = setup(&mut rng);
+        let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+
+        let sealed = seal(&pk, &kid, PLAINTEXT, &mut rng);
+
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert_eq!(open(&usk, &tampered), Err(Error::Authentication));
+
+        let mut tampered = sealed.clone();
+        tampered[PREFIX_SIZE] ^= 1;
+        assert_eq!(open(&usk, &tampered), Err(Error::Authentication));
+
+        let mut tampered = sealed.clone();
+        tampered[MAGIC.len()] = VERSION + 1;
+        assert_eq!(open(&usk, &tampered), Err(Error::InvalidHeader));
+
+        assert_eq!(open(&usk, &sealed[..OVERHEAD - 1]), Err(Error::Truncated));
+    }
+}
@@ -0,0 +1,105 @@
+//! Key encapsulation style interface shared by the schemes in this crate.
+//!
+//! Allows application code to be generic over the scheme used,
+//! such that switching schemes is a matter of changing a single type parameter.
+
+use crate::Derive;
+use rand::{CryptoRng, RngCore};
+
+/// Identity Based Key Encapsulation Mechanism.
+///
+/// Encapsulation yields a fresh shared secret together with a ciphertext,
+/// from which the shared secret can be recovered with the user secret key.
+pub trait IBKEM {
+    /// Public key parameters generated by the PKG used to encapsulate secrets.
+    type PublicKey;
+
+    /// Secret key generated by the PKG used to extract user secret keys.
+    type SecretKey;
+
+    /// Secret key for a single identity, used to decapsulate secrets.
+    type UserSecretKey;
+
+    /// Identity under which secrets are encapsulated.
+    type Identity: Derive;
+
+    /// Encapsulation of a shared secret. Can only be decapsulated with an user secret key.
+    type CipherText;
+
+    /// Secret shared between the encapsulating and decapsulating party.
+    type SharedSecret;
+
+    /// Generate a keypair used by the Private Key Generator (PKG).
+    fn setup<R: RngCore + CryptoRng>(rng: &mut R) -> (Self::PublicKey, Self::SecretKey);
+
+    /// Extract an user secret key for a given identity.
+    fn extract_usk<R: RngCore + CryptoRng>(
+        pk: &Self::PublicKey,
+        sk: &Self::SecretKey,
+        id: &Self::Identity,
+        rng: &mut R,
+    ) -> Self::UserSecretKey;
+
+    /// Generate a shared secret and the corresponding ciphertext for an identity.
+    fn encaps<R: RngCore + CryptoRng>(
+        pk: &Self::PublicKey,
+        id: &Self::Identity,
+        rng: &mut R,
+    ) -> (Self::CipherText, Self::SharedSecret);
+
+    /// Recover the shared secret from a ciphertext using a user secret key.
+    ///
+    /// The public key is required by schemes that re-encrypt during decapsulation,
+    /// such as the [Fujisaki-Okamoto transform](crate::fujisaki_okamoto).
+    fn decaps(
+        pk: &Self::PublicKey,
+        usk: &Self::UserSecretKey,
+        c: &Self::CipherText,
+    ) -> Self::SharedSecret;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{boneh_boyen_1, kiltz_vahlis_one, waters, waters_naccache};
+    use core::fmt::Debug;
+
+    const ID: &str = "email:w.geraedts@sarif.nl";
+
+    fn eq_encaps_decaps<S: IBKEM>()
+    where
+        S::SharedSecret: Debug + PartialEq,
+    {
+        let mut rng = rand::thread_rng();
+
+        let kid = S::Identity::derive_str(ID);
+
+        let (pk, sk) = S::setup(&mut rng);
+        let usk = S::extract_usk(&pk, &sk, &kid, &mut rng);
+
+        let (c, ss) = S::encaps(&pk, &kid, &mut rng);
+        let ss2 = S::decaps(&pk, &usk, &c);
+
+        assert_eq!(ss, ss2);
+    }
+
+    #[test]
+    fn eq_encaps_decaps_waters() {
+        eq_encaps_decaps::<waters::Waters>();
+    }
+
+    #[test]
+    fn eq_encaps_decaps_waters_naccache() {
+        eq_encaps_decaps::<waters_naccache::WatersNaccache>();
+    }
+
+    #[test]
+    fn eq_encaps_decaps_kiltz_vahlis_one() {
+        eq_encaps_decaps::<kiltz_vahlis_one::KiltzVahlisOne>();
+    }
+
+    #[test]
+    fn eq_encaps_decaps_boneh_boyen_1() {
+        eq_encaps_decaps::<boneh_boyen_1::BonehBoyen1>();
+    }
+}
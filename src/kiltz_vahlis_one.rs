@@ -8,11 +8,23 @@
 //! to remain constant between releases of this library.
 //! All operations in this library are implemented to run in constant time.
 
+use crate::compact::Expand;
+#[cfg(feature = "alloc")]
+use crate::table::{G1Table, G2Table, SCALAR_WINDOWS};
 use crate::util::*;
+use crate::validation::*;
+use crate::{Compress, Derive, Error, IBKEM};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
-use irmaseal_curve::{G1Affine, G1Projective, G2Affine, Gt, Scalar};
-use rand::Rng;
-use subtle::{Choice, ConditionallySelectable, CtOption};
+use core::convert::TryFrom;
+use irmaseal_curve::{multi_miller_loop, G1Affine, G1Projective, G2Affine, G2Prepared, Gt, Scalar};
+use rand::{CryptoRng, RngCore};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption};
+
+#[cfg(feature = "alloc")]
+pub mod dkg;
+pub mod threshold;
 
 const K: usize = 256;
 const N: usize = 2 * K;
@@ -21,6 +33,11 @@ const N_BYTE_LEN: usize = N / 8;
 const HASH_PARAMETER_SIZE: usize = N * 48;
 
 const PUBLICKEYSIZE: usize = 96 + 48 + HASH_PARAMETER_SIZE + 48 + 288;
+const COMPACTPUBLICKEYSIZE: usize = 32 + 96 + 48 + 288;
+
+const SEED_DST: &[u8] = b"ibe-kiltz-vahlis-one-setup-seed";
+const HASH_PARAMETERS_DST: &[u8] =
+    b"ibe-kiltz-vahlis-one-hash-parameters-BLS12381G1_XOF:SHAKE-256_SSWU_RO_";
 
 struct HashParameters([G1Affine; N]);
 
@@ -34,25 +51,71 @@ pub struct PublicKey {
     z: Gt,
 }
 
+/// Public key of which the hash parameters are derived from a public seed, see [`setup_compact`].
+///
+/// Must be expanded to a [`PublicKey`] before use.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CompactPublicKey {
+    seed: [u8; 32],
+    g: G2Affine,
+    u: G1Affine,
+    z: Gt,
+}
+
 /// Secret key parameter generated by the PKG used to extract user secret keys.
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone)]
 pub struct SecretKey {
     alpha: G1Affine,
 }
 
+zeroize_on_drop!(SecretKey: alpha);
+impl_secret!(SecretKey: alpha);
+impl_try_from_secret!(SecretKey, 48);
+
 /// Points on the paired curves that form the user secret key.
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone)]
 pub struct UserSecretKey {
     d1: G1Affine,
     d2: G2Affine,
     d3: G1Affine,
 }
 
+zeroize_on_drop!(UserSecretKey: d1, d2, d3);
+impl_secret!(UserSecretKey: d1, d2, d3);
+impl_try_from_secret!(UserSecretKey, 192);
+
+/// User secret key with the precomputed line functions of `d2`, for faster repeated decryption.
+///
+/// When the `zeroize` feature is enabled, only the contained user secret key is wiped when dropped,
+/// as the line functions cannot be wiped. These only depend on `d2`, which does not allow decryption by itself.
+///
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+#[derive(Clone)]
+pub struct PreparedUserSecretKey {
+    usk: UserSecretKey,
+    d2: G2Prepared,
+}
+
 /// Byte representation of an identity.
 ///
 /// Can be hashed to the curve together with some parameters from the Public Key.
 pub struct Identity([u8; N_BYTE_LEN]);
 
+/// Public key with precomputed tables, for faster extraction and encryption using a long-lived public key.
+///
+/// Contains fixed-base tables for `g` and `u`, and the sums of all subsets of every four
+/// hash parameters. Takes about 500 KiB of memory.
+///
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+pub struct PreparedPublicKey {
+    pk: PublicKey,
+    g: G2Table,
+    u: G1Table,
+    h: G1Table,
+}
+
 /// Encrypted message. Can only be decrypted with an user secret key.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct CipherText {
@@ -62,12 +125,21 @@ pub struct CipherText {
 
 /// A point on the paired curve that can be encrypted and decrypted.
 ///
-/// You can use the byte representation to derive an AES key.
-#[derive(Clone, Copy, Debug, PartialEq)]
+/// Use [`derive_key`](Self::derive_key) to derive symmetric keys, such as for AES.
+#[derive(Clone)]
 pub struct SymmetricKey(Gt);
 
+zeroize_on_drop!(SymmetricKey: 0);
+impl_secret!(SymmetricKey: 0);
+
+/// The Kiltz-Vahlis IBE1 identity-based key encapsulation mechanism.
+///
+/// Implements [`IBKEM`] on top of the functions in this module.
+#[derive(Clone, Copy, Debug)]
+pub struct KiltzVahlisOne;
+
 /// Generate a keypair used by the Private Key Generator (PKG).
-pub fn setup<R: Rng>(rng: &mut R) -> (PublicKey, SecretKey) {
+pub fn setup<R: RngCore + CryptoRng>(rng: &mut R) -> (PublicKey, SecretKey) {
     let g: G2Affine = rand_g2(rng).into();
 
     let alpha: G1Affine = rand_g1(rng).into();
@@ -86,6 +158,33 @@ pub fn setup<R: Rng>(rng: &mut R) -> (PublicKey, SecretKey) {
     (pk, sk)
 }
 
+/// Deterministically derive a keypair for the PKG from a seed.
+///
+/// The same seed always results in the same keypair, hence the seed must be kept as secret as the master secret key.
+pub fn setup_from_seed(seed: &[u8; 32]) -> (PublicKey, SecretKey) {
+    setup(&mut XofRng::new(SEED_DST, seed))
+}
+
+/// Generate a keypair used by the Private Key Generator (PKG), with a compact public key.
+///
+/// The hash parameters are derived by hash-to-curve from a random public seed,
+/// such that their discrete logarithms are unknown to anyone, including the PKG.
+pub fn setup_compact<R: RngCore + CryptoRng>(rng: &mut R) -> (CompactPublicKey, SecretKey) {
+    let mut seed = [0u8; 32];
+    rng.fill_bytes(&mut seed);
+
+    let g: G2Affine = rand_g2(rng).into();
+
+    let alpha: G1Affine = rand_g1(rng).into();
+    let u: G1Affine = rand_g1(rng).into();
+    let z = irmaseal_curve::pairing(&alpha, &g);
+
+    let pk = CompactPublicKey { seed, g, u, z };
+    let sk = SecretKey { alpha };
+
+    (pk, sk)
+}
+
 fn hash_to_curve(pk: &PublicKey, v: &Identity) -> G1Projective {
     let mut hcoll: G1Projective = pk.hzero.into();
     for (hi, vi) in pk.h.0.iter().zip(bits(&v.0)) {
@@ -100,44 +199,166 @@ fn hash_g2_to_scalar(x: G2Affine) -> Scalar {
 }
 
 /// Extract an user secret key for a given identity.
-pub fn extract_usk<R: Rng>(
+pub fn extract_usk<R: RngCore + CryptoRng>(
     pk: &PublicKey,
     sk: &SecretKey,
     v: &Identity,
     rng: &mut R,
 ) -> UserSecretKey {
-    let s = rand_scalar(rng);
+    let mut s = rand_scalar(rng);
 
     let d1 = (sk.alpha + (hash_to_curve(pk, v) * s)).into();
     let d2 = (pk.g * (-s)).into();
     let d3 = (pk.u * s).into();
+    s.wipe();
 
     UserSecretKey { d1, d2, d3 }
 }
 
 /// Generate a symmetric key and corresponding CipherText for that key.
-pub fn encrypt<R: Rng>(pk: &PublicKey, v: &Identity, rng: &mut R) -> (CipherText, SymmetricKey) {
-    let r = rand_scalar(rng);
+pub fn encrypt<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    v: &Identity,
+    rng: &mut R,
+) -> (CipherText, SymmetricKey) {
+    let mut r = rand_scalar(rng);
 
     let c1 = (pk.g * r).into();
     let t = hash_g2_to_scalar(c1);
     let c2 = ((hash_to_curve(pk, v) + (pk.u * t)) * r).into();
     let k = pk.z * r;
+    r.wipe();
+
+    (CipherText { c1, c2 }, SymmetricKey(k))
+}
+
+/// Extract an user secret key for a given identity using a prepared public key.
+///
+/// Results in the same key as [`extract_usk`] given the same randomness.
+#[cfg(feature = "alloc")]
+pub fn extract_usk_prepared<R: RngCore + CryptoRng>(
+    ppk: &PreparedPublicKey,
+    sk: &SecretKey,
+    v: &Identity,
+    rng: &mut R,
+) -> UserSecretKey {
+    let mut s = rand_scalar(rng);
+
+    let d1 = (sk.alpha + (ppk.hash_to_curve(v) * s)).into();
+    let d2 = ppk.g.mul(&(-s)).into();
+    let d3 = ppk.u.mul(&s).into();
+    s.wipe();
+
+    UserSecretKey { d1, d2, d3 }
+}
+
+/// Extract user secret keys for many identities at once.
+///
+/// Computes the tables of a [`PreparedPublicKey`] once for the entire batch, after which every key takes
+/// a single variable-base scalar multiplication. All keys are converted to affine coordinates using one
+/// inversion per component, and are extracted in parallel when the `rayon` feature is enabled.
+/// Given the same randomness, results in the same keys as calling [`extract_usk`] for every identity in order.
+///
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+pub fn extract_usk_batch<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    sk: &SecretKey,
+    ids: &[Identity],
+    rng: &mut R,
+) -> Vec<UserSecretKey> {
+    let ppk = PreparedPublicKey::new(pk);
+    let mut ss = rand_scalars(ids.len(), rng);
+
+    let mut d1 = batch_map(ids, |i, v| sk.alpha + ppk.hash_to_curve(v) * ss[i]);
+    let mut d2 = batch_map(&ss, |_, s| ppk.g.mul(&-s));
+    let mut d3 = batch_map(&ss, |_, s| ppk.u.mul(s));
+    ss.wipe();
+
+    let mut d1 = batch_normalize_g1(&mut d1);
+    let mut d2 = batch_normalize_g2(&mut d2);
+    let mut d3 = batch_normalize_g1(&mut d3);
+    let res = d1
+        .iter()
+        .zip(d2.iter())
+        .zip(d3.iter())
+        .map(|((d1, d2), d3)| UserSecretKey {
+            d1: *d1,
+            d2: *d2,
+            d3: *d3,
+        })
+        .collect();
+    d1.wipe();
+    d2.wipe();
+    d3.wipe();
+
+    res
+}
+
+/// Generate a symmetric key and corresponding CipherText for that key using a prepared public key.
+///
+/// Results in the same ciphertext and key as [`encrypt`] given the same randomness.
+#[cfg(feature = "alloc")]
+pub fn encrypt_prepared<R: RngCore + CryptoRng>(
+    ppk: &PreparedPublicKey,
+    v: &Identity,
+    rng: &mut R,
+) -> (CipherText, SymmetricKey) {
+    let mut r = rand_scalar(rng);
+
+    let c1 = ppk.g.mul(&r).into();
+    let t = hash_g2_to_scalar(c1);
+    let c2 = ((ppk.hash_to_curve(v) + ppk.u.mul(&t)) * r).into();
+    let k = ppk.pk.z * r;
+    r.wipe();
 
     (CipherText { c1, c2 }, SymmetricKey(k))
 }
 
 /// Decrypt ciphertext to a SymmetricKey using a user secret key.
 pub fn decrypt(usk: &UserSecretKey, c: &CipherText) -> SymmetricKey {
-    let t = hash_g2_to_scalar(c.c1);
-    let k1 = irmaseal_curve::pairing(&(usk.d1 + (usk.d3 * t)).into(), &c.c1);
-    let k2 = irmaseal_curve::pairing(&c.c2, &usk.d2);
+    decrypt_with(usk, &G2Prepared::from(usk.d2), c)
+}
 
-    let k = k1 + k2;
+/// Decrypt ciphertext to a SymmetricKey using a prepared user secret key.
+///
+/// Results in the same symmetric key as [`decrypt`].
+#[cfg(feature = "alloc")]
+pub fn decrypt_prepared(pusk: &PreparedUserSecretKey, c: &CipherText) -> SymmetricKey {
+    decrypt_with(&pusk.usk, &pusk.d2, c)
+}
+
+/// Compute `e(d1 + d3 * t, c1) + e(c2, d2)` using a single Miller loop and final exponentiation.
+fn decrypt_with(usk: &UserSecretKey, d2: &G2Prepared, c: &CipherText) -> SymmetricKey {
+    let t = hash_g2_to_scalar(c.c1);
+    let mut d: G1Affine = (usk.d1 + (usk.d3 * t)).into();
+    let c1 = G2Prepared::from(c.c1);
+    let k = multi_miller_loop(&[(&d, &c1), (&c.c2, d2)]).final_exponentiation();
+    d.wipe();
 
     SymmetricKey(k)
 }
 
+/// Verify that a user secret key is valid for the given identity.
+///
+/// Checks the key against the public key using pairings, without requiring a ciphertext.
+pub fn verify_usk(pk: &PublicKey, v: &Identity, usk: &UserSecretKey) -> Choice {
+    verify_usk_with(pk, v, usk, &pk.z)
+}
+
+/// Verify a user secret key against the pairing of its master secret key component with `g`.
+fn verify_usk_with(pk: &PublicKey, v: &Identity, usk: &UserSecretKey, target: &Gt) -> Choice {
+    let hcoll: G1Affine = hash_to_curve(pk, v).into();
+
+    // e(d1, g) = e(alpha, g) + e(H(v), g)^s, with d2 = g^-s.
+    let k = irmaseal_curve::pairing(&usk.d1, &pk.g) + irmaseal_curve::pairing(&hcoll, &usk.d2);
+
+    // e(d3, g) = e(u, g)^s, with d2 = g^-s.
+    let u = irmaseal_curve::pairing(&usk.d3, &pk.g) + irmaseal_curve::pairing(&pk.u, &usk.d2);
+
+    k.ct_eq(target) & u.ct_eq(&Gt::identity())
+}
+
 impl Identity {
     /// Hash a byte slice to a set of Identity parameters, which acts as a user public key.
     /// Uses sha3-512 internally.
@@ -150,15 +371,19 @@ impl Identity {
     pub fn derive_str(s: &str) -> Identity {
         Self::derive(s.as_bytes())
     }
+
+    pub fn to_bytes(&self) -> [u8; N_BYTE_LEN] {
+        self.0
+    }
+
+    pub fn from_bytes(bytes: &[u8; N_BYTE_LEN]) -> CtOption<Self> {
+        CtOption::new(Identity(*bytes), 1u8.into())
+    }
 }
 
 impl Clone for Identity {
     fn clone(&self) -> Self {
-        let mut res = [u8::default(); N_BYTE_LEN];
-        for (src, dst) in self.0.iter().zip(res.as_mut().iter_mut()) {
-            *dst = *src;
-        }
-        Identity(res)
+        *self
     }
 }
 
@@ -172,13 +397,28 @@ impl SymmetricKey {
     pub fn from_bytes(bytes: &[u8; 288]) -> CtOption<Self> {
         Gt::from_compressed(bytes).map(Self)
     }
+
+    /// Derive symmetric key material bound to `c` and `info`, see [key derivation](crate#key-derivation).
+    pub fn derive_key(&self, c: &CipherText, info: &[u8], out: &mut [u8]) {
+        kdf(&self.to_bytes(), &c.to_bytes(), info, out)
+    }
 }
 
 impl HashParameters {
+    /// Derive the hash parameters from a public seed using hash-to-curve.
+    fn derive(seed: &[u8; 32]) -> Self {
+        let mut res = [G1Affine::default(); N];
+        for (i, hi) in (0u32..).zip(res.iter_mut()) {
+            *hi = hash_to_g1(&seeded_parameter_input(seed, i), HASH_PARAMETERS_DST).into();
+        }
+        HashParameters(res)
+    }
+
+    #[allow(clippy::wrong_self_convention)]
     pub fn to_bytes(&self) -> [u8; HASH_PARAMETER_SIZE] {
         let mut res = [0u8; HASH_PARAMETER_SIZE];
-        for i in 0..N {
-            *array_mut_ref![&mut res, i * 48, 48] = self.0[i].to_compressed();
+        for (i, hi) in self.0.iter().enumerate() {
+            *array_mut_ref![&mut res, i * 48, 48] = hi.to_compressed();
         }
         res
     }
@@ -186,11 +426,11 @@ impl HashParameters {
     pub fn from_bytes(bytes: &[u8; HASH_PARAMETER_SIZE]) -> CtOption<Self> {
         let mut res = [G1Affine::default(); N];
         let mut is_some = Choice::from(1u8);
-        for i in 0..N {
+        for (i, ri) in res.iter_mut().enumerate() {
             // See comment in PublicKey::from_bytes on cofactor.
             is_some &= G1Affine::from_compressed_unchecked(array_ref![bytes, i * 48, 48])
                 .map(|s| {
-                    res[i] = s;
+                    *ri = s;
                 })
                 .is_some();
         }
@@ -202,7 +442,7 @@ impl ConditionallySelectable for HashParameters {
     fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
         let mut res = [G1Affine::default(); N];
         for (i, (ai, bi)) in a.0.iter().zip(b.0.iter()).enumerate() {
-            res[i] = G1Affine::conditional_select(&ai, &bi, choice);
+            res[i] = G1Affine::conditional_select(ai, bi, choice);
         }
         HashParameters(res)
     }
@@ -216,11 +456,7 @@ impl PartialEq for HashParameters {
 
 impl Clone for HashParameters {
     fn clone(&self) -> Self {
-        let mut res = [G1Affine::default(); N];
-        for (src, dst) in self.0.iter().zip(res.as_mut().iter_mut()) {
-            *dst = *src;
-        }
-        Self(res)
+        *self
     }
 }
 
@@ -233,6 +469,27 @@ impl Default for HashParameters {
 }
 
 impl PublicKey {
+    /// Validate a public key obtained from an untrusted source.
+    ///
+    /// As [`PublicKey::from_bytes`] skips the subgroup checks, this checks that all points are
+    /// in the prime order subgroup, and that `z` is in the order `r` subgroup of the target group.
+    /// None of them may be the identity, except for `hzero`, which is the identity by construction.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        check_g2(&self.g, "g", None)?;
+        if !bool::from(self.hzero.is_torsion_free()) {
+            return Err(ValidationError::new(
+                "hzero",
+                None,
+                ValidationErrorKind::NotInSubgroup,
+            ));
+        }
+        for (i, hi) in self.h.0.iter().enumerate() {
+            check_g1(hi, "h", Some(i))?;
+        }
+        check_g1(&self.u, "u", None)?;
+        check_gt(&self.z, "z")
+    }
+
     pub fn to_bytes(&self) -> [u8; PUBLICKEYSIZE] {
         let mut res = [0u8; PUBLICKEYSIZE];
 
@@ -267,6 +524,91 @@ impl PublicKey {
     }
 }
 
+#[cfg(feature = "alloc")]
+impl PreparedUserSecretKey {
+    /// Precompute the line functions of a user secret key.
+    pub fn new(usk: &UserSecretKey) -> Self {
+        PreparedUserSecretKey {
+            usk: usk.clone(),
+            d2: G2Prepared::from(usk.d2),
+        }
+    }
+
+    /// The user secret key of which the line functions were precomputed.
+    pub fn user_secret_key(&self) -> &UserSecretKey {
+        &self.usk
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl PreparedPublicKey {
+    /// Precompute the tables for a public key.
+    pub fn new(pk: &PublicKey) -> Self {
+        PreparedPublicKey {
+            pk: *pk,
+            g: G2Table::fixed_base(&pk.g, SCALAR_WINDOWS),
+            u: G1Table::fixed_base(&pk.u, SCALAR_WINDOWS),
+            h: G1Table::subset_sums(&pk.h.0),
+        }
+    }
+
+    /// The public key of which the tables were precomputed.
+    pub fn public_key(&self) -> &PublicKey {
+        &self.pk
+    }
+
+    /// Hash an identity to the curve, as [`hash_to_curve`] does.
+    fn hash_to_curve(&self, v: &Identity) -> G1Projective {
+        self.pk.hzero + self.h.sum_bits(bits(&v.0))
+    }
+}
+
+impl CompactPublicKey {
+    /// Derive the full public key, recomputing the hash parameters from the seed.
+    ///
+    /// This is relatively expensive, hence the result should be kept around for repeated use.
+    pub fn expand(&self) -> PublicKey {
+        PublicKey {
+            g: self.g,
+            hzero: G1Affine::default(),
+            h: HashParameters::derive(&self.seed),
+            u: self.u,
+            z: self.z,
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; COMPACTPUBLICKEYSIZE] {
+        let mut res = [0u8; COMPACTPUBLICKEYSIZE];
+
+        let (seed, g, u, z) = mut_array_refs![&mut res, 32, 96, 48, 288];
+        *seed = self.seed;
+        *g = self.g.to_compressed();
+        *u = self.u.to_compressed();
+        *z = self.z.to_compressed();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; COMPACTPUBLICKEYSIZE]) -> CtOption<Self> {
+        let (seed, g, u, z) = array_refs![&bytes, 32, 96, 48, 288];
+
+        // See comment in PublicKey::from_bytes on cofactor.
+        let g = G2Affine::from_compressed_unchecked(g);
+        let u = G1Affine::from_compressed_unchecked(u);
+        let z = Gt::from_compressed_unchecked(z);
+
+        g.and_then(|g| {
+            u.and_then(|u| {
+                z.map(|z| CompactPublicKey {
+                    seed: *seed,
+                    g,
+                    u,
+                    z,
+                })
+            })
+        })
+    }
+}
+
 impl SecretKey {
     pub fn to_bytes(&self) -> [u8; 48] {
         self.alpha.to_compressed()
@@ -317,11 +659,201 @@ impl CipherText {
     }
 }
 
+impl Derive for Identity {
+    fn derive(b: &[u8]) -> Identity {
+        Identity::derive(b)
+    }
+}
+
+impl IBKEM for KiltzVahlisOne {
+    type PublicKey = PublicKey;
+    type SecretKey = SecretKey;
+    type UserSecretKey = UserSecretKey;
+    type Identity = Identity;
+    type CipherText = CipherText;
+    type SharedSecret = SymmetricKey;
+
+    fn setup<R: RngCore + CryptoRng>(rng: &mut R) -> (PublicKey, SecretKey) {
+        setup(rng)
+    }
+
+    fn extract_usk<R: RngCore + CryptoRng>(
+        pk: &PublicKey,
+        sk: &SecretKey,
+        v: &Identity,
+        rng: &mut R,
+    ) -> UserSecretKey {
+        extract_usk(pk, sk, v, rng)
+    }
+
+    fn encaps<R: RngCore + CryptoRng>(
+        pk: &PublicKey,
+        v: &Identity,
+        rng: &mut R,
+    ) -> (CipherText, SymmetricKey) {
+        encrypt(pk, v, rng)
+    }
+
+    fn decaps(_pk: &PublicKey, usk: &UserSecretKey, c: &CipherText) -> SymmetricKey {
+        decrypt(usk, c)
+    }
+}
+
+impl_serde!(PublicKey, PUBLICKEYSIZE);
+impl_serde!(CompactPublicKey, COMPACTPUBLICKEYSIZE);
+impl_serde!(SecretKey, 48);
+impl_serde!(UserSecretKey, 192);
+impl_serde!(Identity, N_BYTE_LEN);
+impl_serde!(CipherText, 144);
+impl_serde!(SymmetricKey, 288);
+
+impl Compress for PublicKey {
+    type Output = [u8; PUBLICKEYSIZE];
+
+    fn to_bytes(&self) -> [u8; PUBLICKEYSIZE] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; PUBLICKEYSIZE]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for CompactPublicKey {
+    type Output = [u8; COMPACTPUBLICKEYSIZE];
+
+    fn to_bytes(&self) -> [u8; COMPACTPUBLICKEYSIZE] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; COMPACTPUBLICKEYSIZE]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Expand for CompactPublicKey {
+    type PublicKey = PublicKey;
+
+    fn expand(&self) -> PublicKey {
+        self.expand()
+    }
+}
+
+impl Compress for SecretKey {
+    type Output = [u8; 48];
+
+    fn to_bytes(&self) -> [u8; 48] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; 48]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for UserSecretKey {
+    type Output = [u8; 192];
+
+    fn to_bytes(&self) -> [u8; 192] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; 192]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for Identity {
+    type Output = [u8; N_BYTE_LEN];
+
+    fn to_bytes(&self) -> [u8; N_BYTE_LEN] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; N_BYTE_LEN]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for CipherText {
+    type Output = [u8; 144];
+
+    fn to_bytes(&self) -> [u8; 144] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; 144]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for SymmetricKey {
+    type Output = [u8; 288];
+
+    fn to_bytes(&self) -> [u8; 288] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; 288]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl TryFrom<&[u8]> for PublicKey {
+    type Error = Error;
+
+    /// Like [`PublicKey::from_bytes`], skips the subgroup checks.
+    fn try_from(bytes: &[u8]) -> Result<Self, Error> {
+        let mut d = Decoder::new(bytes, PUBLICKEYSIZE)?;
+        let g = d.g2_unchecked("g", None)?;
+        let hzero = d.g1_unchecked("hzero", None)?;
+        let mut h = [G1Affine::default(); N];
+        for (i, hi) in h.iter_mut().enumerate() {
+            *hi = d.g1_unchecked("h", Some(i))?;
+        }
+
+        Ok(PublicKey {
+            g,
+            hzero,
+            h: HashParameters(h),
+            u: d.g1_unchecked("u", None)?,
+            z: d.gt_unchecked("z")?,
+        })
+    }
+}
+
+impl TryFrom<&[u8]> for CompactPublicKey {
+    type Error = Error;
+
+    /// Like [`CompactPublicKey::from_bytes`], skips the subgroup checks.
+    fn try_from(bytes: &[u8]) -> Result<Self, Error> {
+        let mut d = Decoder::new(bytes, COMPACTPUBLICKEYSIZE)?;
+        Ok(CompactPublicKey {
+            seed: *d.bytes(),
+            g: d.g2_unchecked("g", None)?,
+            u: d.g1_unchecked("u", None)?,
+            z: d.gt_unchecked("z")?,
+        })
+    }
+}
+
+impl TryFrom<&[u8]> for CipherText {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Error> {
+        let mut d = Decoder::new(bytes, 144)?;
+        Ok(CipherText {
+            c1: d.g2("c1", None)?,
+            c2: d.g1("c2", None)?,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    const ID: &'static str = "email:w.geraedts@sarif.nl";
+    const ID: &str = "email:w.geraedts@sarif.nl";
 
     #[allow(dead_code)]
     struct DefaultSubResults {
@@ -362,6 +894,181 @@ mod tests {
         assert_eq!(results.k, k2);
     }
 
+    #[test]
+    fn eq_compact_encrypt_decrypt() {
+        let mut rng = rand::thread_rng();
+        let kid = Identity::derive_str(ID);
+
+        let (cpk, sk) = setup_compact(&mut rng);
+        let cpk2 = CompactPublicKey::from_bytes(&cpk.to_bytes()).unwrap();
+        assert_eq!(cpk, cpk2);
+
+        let pk = cpk2.expand();
+        assert!(pk == cpk.expand());
+
+        let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+        let (c, k) = encrypt(&pk, &kid, &mut rng);
+        assert_eq!(k, decrypt(&usk, &c));
+    }
+
+    #[test]
+    fn eq_derive_key() {
+        let results = perform_default();
+        let (mut key, mut key2) = ([0u8; 32], [0u8; 32]);
+        results.k.derive_key(&results.c, b"test", &mut key);
+        decrypt(&results.usk, &results.c).derive_key(&results.c, b"test", &mut key2);
+        assert_eq!(key, key2);
+    }
+
+    #[test]
+    fn hash_to_curve_uses_all_bits() {
+        let (pk, _) = setup(&mut rand::thread_rng());
+        let zero = hash_to_curve(&pk, &Identity([0u8; N_BYTE_LEN]));
+
+        for i in 0..N_BYTE_LEN {
+            for bit in 0..8 {
+                let mut v = Identity([0u8; N_BYTE_LEN]);
+                v.0[i] = 1 << bit;
+                assert_ne!(zero, hash_to_curve(&pk, &v));
+            }
+        }
+    }
+
+    #[test]
+    fn verify_usk_identity() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+
+        let other = Identity::derive_str("email:other@example.com");
+        let usk_other = extract_usk(&results.pk, &results.sk, &other, &mut rng);
+
+        assert!(bool::from(verify_usk(
+            &results.pk,
+            &results.kid,
+            &results.usk
+        )));
+        assert!(!bool::from(verify_usk(
+            &results.pk,
+            &results.kid,
+            &usk_other
+        )));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn eq_prepared_decrypt() {
+        let results = perform_default();
+        let pusk = PreparedUserSecretKey::new(&results.usk);
+
+        assert_eq!(decrypt_prepared(&pusk, &results.c), results.k);
+        assert!(pusk.user_secret_key() == &results.usk);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn prepared_matches_unprepared() {
+        let results = perform_default();
+        let ppk = PreparedPublicKey::new(&results.pk);
+
+        for i in 0..N_BYTE_LEN {
+            let mut v = Identity([0x5au8; N_BYTE_LEN]);
+            v.0[i] = 0x81;
+            assert_eq!(ppk.hash_to_curve(&v), hash_to_curve(&results.pk, &v));
+        }
+
+        let mut rng = XofRng::new(b"ibe-kiltz-vahlis-one-test", &[0u8; 32]);
+        let mut rng2 = XofRng::new(b"ibe-kiltz-vahlis-one-test", &[0u8; 32]);
+
+        let usk = extract_usk(&results.pk, &results.sk, &results.kid, &mut rng);
+        let usk2 = extract_usk_prepared(&ppk, &results.sk, &results.kid, &mut rng2);
+        assert!(usk == usk2);
+
+        let (c, k) = encrypt(&results.pk, &results.kid, &mut rng);
+        let (c2, k2) = encrypt_prepared(&ppk, &results.kid, &mut rng2);
+        assert_eq!(c, c2);
+        assert_eq!(k, k2);
+        assert_eq!(decrypt(&usk2, &c2), k2);
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn zeroize_wipes_secrets() {
+        use zeroize::{Zeroize, ZeroizeOnDrop};
+
+        fn assert_zeroize_on_drop<T: ZeroizeOnDrop>(_: &T) {}
+
+        let mut results = perform_default();
+        assert_zeroize_on_drop(&results.sk);
+        assert_zeroize_on_drop(&results.usk);
+        assert_zeroize_on_drop(&results.k);
+
+        results.sk.zeroize();
+        results.usk.zeroize();
+        results.k.zeroize();
+
+        assert_eq!(results.sk.alpha, G1Affine::identity());
+        assert_eq!(results.usk.d1, G1Affine::identity());
+        assert_eq!(results.usk.d2, G2Affine::identity());
+        assert_eq!(results.usk.d3, G1Affine::identity());
+        assert_eq!(results.k.0, Gt::identity());
+    }
+
+    #[test]
+    fn validate_public_key() {
+        let results = perform_default();
+        assert_eq!(results.pk.validate(), Ok(()));
+
+        let mut pk = results.pk;
+        pk.h.0[3] = G1Affine::identity();
+        let err = pk.validate().unwrap_err();
+        assert_eq!(err.component(), "h");
+        assert_eq!(err.index(), Some(3));
+        assert_eq!(err.kind(), ValidationErrorKind::Identity);
+
+        let mut pk = results.pk;
+        pk.z = Gt::identity();
+        assert_eq!(
+            pk.validate(),
+            Err(ValidationError::new(
+                "z",
+                None,
+                ValidationErrorKind::Identity
+            ))
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn batch_matches_sequential() {
+        let (pk, sk) = setup_from_seed(&[1u8; 32]);
+        let ids = [
+            Identity::derive_str(ID),
+            Identity::derive_str("email:l.botros@cs.ru.nl"),
+            Identity([0xffu8; N_BYTE_LEN]),
+        ];
+
+        let mut rng = XofRng::new(b"ibe-kiltz-vahlis-one-test", &[0u8; 32]);
+        let mut rng2 = XofRng::new(b"ibe-kiltz-vahlis-one-test", &[0u8; 32]);
+
+        let usks = extract_usk_batch(&pk, &sk, &ids, &mut rng);
+        assert_eq!(usks.len(), ids.len());
+        for (v, usk) in ids.iter().zip(usks.iter()) {
+            assert!(usk == &extract_usk(&pk, &sk, v, &mut rng2));
+        }
+    }
+
+    #[test]
+    fn setup_from_seed_is_deterministic() {
+        let (pk, sk) = setup_from_seed(&[1u8; 32]);
+        let (pk2, sk2) = setup_from_seed(&[1u8; 32]);
+        let (pk3, sk3) = setup_from_seed(&[2u8; 32]);
+
+        assert!(pk == pk2);
+        assert!(sk == sk2);
+        assert!(pk != pk3);
+        assert!(sk != sk3);
+    }
+
     #[test]
     fn eq_serialize_deserialize() {
         let result = perform_default();
@@ -384,4 +1091,29 @@ mod tests {
             CipherText::from_bytes(&result.c.to_bytes()).unwrap()
         );
     }
+
+    #[test]
+    fn try_from_reports_errors() {
+        let result = perform_default();
+
+        let pk_bytes = result.pk.to_bytes();
+        assert!(result.pk == PublicKey::try_from(&pk_bytes[..]).unwrap());
+        assert_eq!(
+            PublicKey::try_from(&[0u8; 16][..]).err(),
+            Some(Error::InvalidLength {
+                expected: PUBLICKEYSIZE,
+                actual: 16,
+            })
+        );
+
+        let mut corrupted = pk_bytes;
+        *array_mut_ref![corrupted, 96 + 48 + 5 * 48, 48] = [0xff; 48];
+        assert_eq!(
+            PublicKey::try_from(&corrupted[..]).err(),
+            Some(Error::InvalidEncoding {
+                component: "h",
+                index: Some(5),
+            })
+        );
+    }
 }
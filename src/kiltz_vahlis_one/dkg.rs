@@ -0,0 +1,743 @@
+//! Distributed key generation for the Kiltz-Vahlis IBE1 scheme, without a trusted dealer.
+//!
+//! Lets `n` parties jointly generate a [`PublicKey`] and a [`SecretKeyShare`] for each party,
+//! such that any `t` of them can extract user secret keys using [`threshold`](super::threshold),
+//! while no party ever learns the master secret key.
+//! Implements the Pedersen variant of joint Feldman secret sharing,
+//! with Feldman commitments on G2 and a round of complaints against misbehaving dealers.
+//!
+//! The remaining public key parameters are derived from the session identifier using hash-to-curve,
+//! such that nobody knows their discrete logarithms.
+//! The master secret key and its shares are multiples of another such point on G1, instead of the generator.
+//! The commitments hence only reveal the jointly generated exponent on G2,
+//! from which the master secret key on G1 cannot be computed.
+//!
+//! The protocol driver is transport-agnostic: each round takes the messages of the previous round
+//! and returns the messages to send, which the caller is responsible for delivering.
+//!  1. [`Participant::new`] yields a [`Commitment`] to broadcast, and a [`DealtShare`] to send
+//!     to every other party over a private and authenticated channel.
+//!  2. [`Participant::receive`] checks the commitments and shares, and yields a [`Complaint`]
+//!     to broadcast against every dealer that sent an invalid share.
+//!  3. [`Participant::respond`] yields the shares to reveal publicly in response to complaints
+//!     against this party.
+//!  4. [`Participant::finalize`] disqualifies dealers that did not adequately respond,
+//!     and yields the [`Output`] of the protocol.
+//!
+//! Note that an adversary controlling some of the parties can slightly bias the distribution of the public key,
+//! which does not affect the security of the scheme.
+
+use alloc::vec::Vec;
+use arrayref::{array_refs, mut_array_refs};
+use rand::{CryptoRng, RngCore};
+use subtle::{ConstantTimeEq, CtOption};
+
+use super::threshold::{SecretKeyShare, VerificationKey};
+use super::{HashParameters, PublicKey, SecretKey, N};
+use crate::util::*;
+use irmaseal_curve::{G1Affine, G2Affine, G2Projective, Scalar};
+
+const DST: &[u8] = b"ibe-kiltz-vahlis-one-dkg-v1";
+
+/// Public parameters of a protocol run, which all parties must agree upon beforehand.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Parameters {
+    session: [u8; 32],
+    threshold: usize,
+    n: usize,
+}
+
+/// Broadcast commitment of a dealer to its secret polynomial.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Commitment {
+    dealer: u32,
+    coefficients: Vec<G2Affine>,
+}
+
+/// Share dealt by a dealer to a recipient.
+///
+/// Must be sent privately, unless revealed in response to a complaint.
+#[derive(Clone)]
+pub struct DealtShare {
+    dealer: u32,
+    recipient: u32,
+    value: Scalar,
+}
+
+zeroize_on_drop!(DealtShare: value);
+impl_secret!(DealtShare: dealer, recipient, value);
+
+/// Broadcast complaint by an accuser against a dealer that sent an invalid or no share.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Complaint {
+    accuser: u32,
+    accused: u32,
+}
+
+/// Result of a successful protocol run for a single party.
+#[derive(Clone)]
+pub struct Output {
+    pk: PublicKey,
+    share: SecretKeyShare,
+    verification_keys: Vec<VerificationKey>,
+    qualified: Vec<u32>,
+}
+
+zeroize_on_drop!(Output => share);
+
+/// Reasons why a protocol run could not be completed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// Fewer dealers than the threshold remain after disqualification.
+    NotEnoughQualified,
+    /// No valid share was received from a qualified dealer, nor revealed in response to a complaint.
+    MissingShare(u32),
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::NotEnoughQualified => f.write_str("not enough qualified dealers"),
+            Error::MissingShare(dealer) => write!(f, "missing share from dealer {}", dealer),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// State of a single party during a protocol run.
+pub struct Participant {
+    params: Parameters,
+    index: u32,
+    polynomial: Vec<Scalar>,
+    g: G2Affine,
+    commitments: Vec<Option<Vec<G2Projective>>>,
+    shares: Vec<Option<Scalar>>,
+}
+
+zeroize_on_drop!(Participant: polynomial, shares);
+
+impl Parameters {
+    /// Parameters for a protocol run between `n` parties, producing shares with the given threshold.
+    ///
+    /// The session identifier must be unique for every protocol run.
+    ///
+    /// # Panics
+    ///
+    /// If `threshold` is zero or larger than `n`, or if `n` does not fit in an `u32`.
+    pub fn new(session_id: &[u8], threshold: usize, n: usize) -> Parameters {
+        assert!(
+            threshold > 0 && threshold <= n && n <= u32::MAX as usize,
+            "invalid threshold parameters"
+        );
+
+        Parameters {
+            session: sha3_256(session_id),
+            threshold,
+            n,
+        }
+    }
+
+    fn hash_to_g1(&self, label: u8, index: u32) -> G1Affine {
+        let msg = [&self.session[..], &[label], &index.to_be_bytes()].concat();
+        hash_to_g1(&msg, DST).into()
+    }
+
+    /// The complaints of which both the accuser and the accused are parties, without duplicates.
+    fn valid_complaints(&self, complaints: &[Complaint]) -> Vec<Complaint> {
+        let is_party = |index: u32| index > 0 && index as usize <= self.n;
+        let mut res: Vec<Complaint> = complaints
+            .iter()
+            .filter(|c| is_party(c.accuser) && is_party(c.accused))
+            .copied()
+            .collect();
+        res.sort_unstable_by_key(|c| (c.accused, c.accuser));
+        res.dedup();
+        res
+    }
+
+    /// The generator on G2 of the public key, which is also the base of the commitments.
+    fn g(&self) -> G2Affine {
+        hash_to_g2(&[&self.session[..], &[0u8]].concat(), DST).into()
+    }
+
+    /// The base on G1 of the master secret key and its shares.
+    fn base(&self) -> G1Affine {
+        self.hash_to_g1(3, 0)
+    }
+
+    /// The public key parameters that are derived from the session identifier.
+    fn public_parameters(&self) -> (G2Affine, HashParameters, G1Affine) {
+        let g = self.g();
+        let u = self.hash_to_g1(1, 0);
+
+        let mut h = HashParameters([G1Affine::default(); N]);
+        for (i, hi) in h.0.iter_mut().enumerate() {
+            *hi = self.hash_to_g1(2, i as u32);
+        }
+
+        (g, h, u)
+    }
+}
+
+/// Verify a share dealt to `recipient` against the commitments of the dealer.
+fn verify_share(
+    g: &G2Affine,
+    commitments: &[G2Projective],
+    recipient: u32,
+    value: &Scalar,
+) -> bool {
+    let expected = eval_poly_g2(commitments, recipient);
+    bool::from((g * value).ct_eq(&expected))
+}
+
+impl Participant {
+    /// Start a protocol run as the party with the given index, starting at `1`.
+    ///
+    /// Returns the state of this party, the commitment to broadcast,
+    /// and the shares to privately send to the other parties.
+    ///
+    /// # Panics
+    ///
+    /// If the index is zero or larger than the number of parties.
+    pub fn new<R: RngCore + CryptoRng>(
+        params: &Parameters,
+        index: u32,
+        rng: &mut R,
+    ) -> (Participant, Commitment, Vec<DealtShare>) {
+        assert!(
+            index > 0 && index as usize <= params.n,
+            "invalid participant index"
+        );
+
+        let g = params.g();
+        let polynomial: Vec<Scalar> = (0..params.threshold).map(|_| rand_scalar(rng)).collect();
+        let coefficients: Vec<G2Projective> = polynomial.iter().map(|a| g * a).collect();
+
+        let mut commitment = Commitment {
+            dealer: index,
+            coefficients: alloc::vec![G2Affine::default(); params.threshold],
+        };
+        G2Projective::batch_normalize(&coefficients, &mut commitment.coefficients);
+
+        let shares = (1..=params.n as u32)
+            .filter(|&recipient| recipient != index)
+            .map(|recipient| DealtShare {
+                dealer: index,
+                recipient,
+                value: eval_poly_scalar(&polynomial, recipient),
+            })
+            .collect();
+
+        let mut participant = Participant {
+            params: *params,
+            index,
+            polynomial,
+            g,
+            commitments: alloc::vec![None; params.n],
+            shares: alloc::vec![None; params.n],
+        };
+        participant.shares[index as usize - 1] =
+            Some(eval_poly_scalar(&participant.polynomial, index));
+
+        (participant, commitment, shares)
+    }
+
+    /// The index of this party.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Process the commitments broadcast by all parties, including this party,
+    /// and the shares sent to this party.
+    ///
+    /// Returns the complaints to broadcast against dealers that sent an invalid share or none at all.
+    /// Dealers that did not broadcast a well-formed commitment are disqualified without complaint.
+    pub fn receive(&mut self, commitments: &[Commitment], shares: &[DealtShare]) -> Vec<Complaint> {
+        for c in commitments {
+            if c.dealer > 0
+                && c.dealer as usize <= self.params.n
+                && c.coefficients.len() == self.params.threshold
+            {
+                self.commitments[c.dealer as usize - 1] =
+                    Some(c.coefficients.iter().map(G2Projective::from).collect());
+            }
+        }
+
+        for s in shares {
+            if s.recipient != self.index || s.dealer == self.index || s.dealer == 0 {
+                continue;
+            }
+            let i = s.dealer as usize - 1;
+            if let Some(Some(commitments)) = self.commitments.get(i) {
+                if verify_share(&self.g, commitments, s.recipient, &s.value) {
+                    self.shares[i] = Some(s.value);
+                }
+            }
+        }
+
+        (1..=self.params.n as u32)
+            .filter(|&dealer| {
+                let i = dealer as usize - 1;
+                self.commitments[i].is_some() && self.shares[i].is_none()
+            })
+            .map(|dealer| Complaint {
+                accuser: self.index,
+                accused: dealer,
+            })
+            .collect()
+    }
+
+    /// Respond to the complaints broadcast by all parties.
+    ///
+    /// Returns the shares to publicly reveal for the complaints against this party.
+    pub fn respond(&self, complaints: &[Complaint]) -> Vec<DealtShare> {
+        self.params
+            .valid_complaints(complaints)
+            .into_iter()
+            .filter(|c| c.accused == self.index)
+            .map(|c| DealtShare {
+                dealer: self.index,
+                recipient: c.accuser,
+                value: eval_poly_scalar(&self.polynomial, c.accuser),
+            })
+            .collect()
+    }
+
+    /// Complete the protocol using all broadcast complaints and the publicly revealed responses to them.
+    ///
+    /// Dealers are disqualified if they did not broadcast a well-formed commitment,
+    /// or if they did not reveal a valid share for every complaint against them.
+    /// Complaints by or against parties outside of the protocol run are ignored.
+    pub fn finalize(
+        mut self,
+        complaints: &[Complaint],
+        responses: &[DealtShare],
+    ) -> Result<Output, Error> {
+        let mut qualified: Vec<bool> = self.commitments.iter().map(Option::is_some).collect();
+
+        for c in self.params.valid_complaints(complaints) {
+            let i = c.accused as usize - 1;
+            let commitments = match self.commitments.get(i) {
+                Some(Some(commitments)) => commitments,
+                _ => continue,
+            };
+
+            let response = responses.iter().find(|r| {
+                r.dealer == c.accused
+                    && r.recipient == c.accuser
+                    && verify_share(&self.g, commitments, r.recipient, &r.value)
+            });
+
+            match response {
+                Some(r) if r.recipient == self.index => self.shares[i] = Some(r.value),
+                Some(_) => {}
+                None => qualified[i] = false,
+            }
+        }
+
+        let qualified: Vec<u32> = (1..=self.params.n as u32)
+            .filter(|&dealer| qualified[dealer as usize - 1])
+            .collect();
+        if qualified.len() < self.params.threshold {
+            return Err(Error::NotEnoughQualified);
+        }
+
+        let mut share = Scalar::zero();
+        let mut coefficients = alloc::vec![G2Projective::identity(); self.params.threshold];
+        for &dealer in qualified.iter() {
+            let i = dealer as usize - 1;
+            share += self.shares[i].ok_or(Error::MissingShare(dealer))?;
+            for (a, c) in coefficients
+                .iter_mut()
+                .zip(self.commitments[i].as_ref().unwrap())
+            {
+                *a += c;
+            }
+        }
+
+        let (g, h, u) = self.params.public_parameters();
+        let base = self.params.base();
+        let z = irmaseal_curve::pairing(&base, &coefficients[0].into());
+        let pk = PublicKey {
+            g,
+            hzero: G1Affine::default(),
+            h,
+            u,
+            z,
+        };
+
+        let verification_keys = (1..=self.params.n as u32)
+            .map(|index| VerificationKey {
+                index,
+                vk: irmaseal_curve::pairing(&base, &eval_poly_g2(&coefficients, index).into()),
+            })
+            .collect();
+
+        let share = SecretKeyShare {
+            index: self.index,
+            sk: SecretKey {
+                alpha: (base * share).into(),
+            },
+        };
+
+        Ok(Output {
+            pk,
+            share,
+            verification_keys,
+            qualified,
+        })
+    }
+}
+
+impl Output {
+    /// The jointly generated public key.
+    pub fn pk(&self) -> &PublicKey {
+        &self.pk
+    }
+
+    /// The master secret key share of this party.
+    pub fn share(&self) -> &SecretKeyShare {
+        &self.share
+    }
+
+    /// The verification keys of the shares of all parties, ordered by index.
+    pub fn verification_keys(&self) -> &[VerificationKey] {
+        &self.verification_keys
+    }
+
+    /// The indices of the dealers that contributed to the master secret key.
+    pub fn qualified(&self) -> &[u32] {
+        &self.qualified
+    }
+}
+
+impl Commitment {
+    /// The index of the dealer that broadcast this commitment.
+    pub fn dealer(&self) -> u32 {
+        self.dealer
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut res = Vec::with_capacity(4 + self.coefficients.len() * 96);
+        res.extend_from_slice(&self.dealer.to_be_bytes());
+        for c in self.coefficients.iter() {
+            res.extend_from_slice(&c.to_compressed());
+        }
+        res
+    }
+
+    /// Returns `None` if the length is invalid or if any coefficient is not a valid point.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 4 {
+            return None;
+        }
+        let (dealer, coefficients) = bytes.split_at(4);
+
+        let chunks = coefficients.chunks_exact(96);
+        if !chunks.remainder().is_empty() {
+            return None;
+        }
+
+        let coefficients = chunks
+            .map(|c| {
+                let mut buf = [0u8; 96];
+                buf.copy_from_slice(c);
+                Option::from(G2Affine::from_compressed(&buf))
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(Commitment {
+            dealer: u32::from_be_bytes([dealer[0], dealer[1], dealer[2], dealer[3]]),
+            coefficients,
+        })
+    }
+}
+
+impl DealtShare {
+    /// The index of the dealer of this share.
+    pub fn dealer(&self) -> u32 {
+        self.dealer
+    }
+
+    /// The index of the party this share is dealt to.
+    pub fn recipient(&self) -> u32 {
+        self.recipient
+    }
+
+    pub fn to_bytes(&self) -> [u8; 40] {
+        let mut res = [0u8; 40];
+        let (dealer, recipient, value) = mut_array_refs![&mut res, 4, 4, 32];
+        *dealer = self.dealer.to_be_bytes();
+        *recipient = self.recipient.to_be_bytes();
+        *value = self.value.to_bytes();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; 40]) -> CtOption<Self> {
+        let (dealer, recipient, value) = array_refs![bytes, 4, 4, 32];
+        let dealer = u32::from_be_bytes(*dealer);
+        let recipient = u32::from_be_bytes(*recipient);
+
+        Scalar::from_bytes(value).map(|value| DealtShare {
+            dealer,
+            recipient,
+            value,
+        })
+    }
+}
+
+impl Complaint {
+    /// The index of the party that complains.
+    pub fn accuser(&self) -> u32 {
+        self.accuser
+    }
+
+    /// The index of the dealer that is complained about.
+    pub fn accused(&self) -> u32 {
+        self.accused
+    }
+
+    pub fn to_bytes(&self) -> [u8; 8] {
+        let mut res = [0u8; 8];
+        let (accuser, accused) = mut_array_refs![&mut res, 4, 4];
+        *accuser = self.accuser.to_be_bytes();
+        *accused = self.accused.to_be_bytes();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; 8]) -> Self {
+        let (accuser, accused) = array_refs![bytes, 4, 4];
+
+        Complaint {
+            accuser: u32::from_be_bytes(*accuser),
+            accused: u32::from_be_bytes(*accused),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::threshold::{combine, extract_partial_usk, verify_partial_usk};
+    use super::super::{decrypt, encrypt, verify_usk, Identity};
+    use super::*;
+    use irmaseal_curve::G1Projective;
+
+    const ID: &str = "email:w.geraedts@sarif.nl";
+
+    /// Start the protocol for all parties, returning their states, commitments and dealt shares.
+    fn deal(params: &Parameters) -> (Vec<Participant>, Vec<Commitment>, Vec<DealtShare>) {
+        let mut rng = rand::thread_rng();
+
+        let mut participants = Vec::new();
+        let mut commitments = Vec::new();
+        let mut shares = Vec::new();
+        for index in 1..=params.n as u32 {
+            let (p, c, s) = Participant::new(params, index, &mut rng);
+            participants.push(p);
+            commitments.push(c);
+            shares.extend(s);
+        }
+
+        (participants, commitments, shares)
+    }
+
+    /// Run the protocol in-process, with the given dealer sending corrupted shares to the given recipient.
+    fn run(
+        params: &Parameters,
+        corrupt: Option<(u32, u32)>,
+        respond: bool,
+        forged: &[Complaint],
+    ) -> Vec<Result<Output, Error>> {
+        let (mut participants, commitments, mut shares) = deal(params);
+
+        if let Some((dealer, recipient)) = corrupt {
+            for s in shares.iter_mut() {
+                if s.dealer == dealer && s.recipient == recipient {
+                    s.value += Scalar::one();
+                }
+            }
+        }
+
+        let complaints: Vec<_> = participants
+            .iter_mut()
+            .flat_map(|p| p.receive(&commitments, &shares))
+            .chain(forged.iter().copied())
+            .collect();
+
+        // `Option::is_none_or` requires Rust 1.82.
+        #[allow(clippy::unnecessary_map_or)]
+        let responses: Vec<_> = participants
+            .iter()
+            .filter(|p| respond || corrupt.map_or(true, |(dealer, _)| p.index != dealer))
+            .flat_map(|p| p.respond(&complaints))
+            .collect();
+
+        participants
+            .into_iter()
+            .map(|p| p.finalize(&complaints, &responses))
+            .collect()
+    }
+
+    fn check_outputs(outputs: &[Output], qualified: &[u32]) {
+        let mut rng = rand::thread_rng();
+        let kid = Identity::derive(ID.as_bytes());
+
+        let pk = outputs[0].pk;
+        for o in outputs {
+            assert_eq!(o.qualified, qualified);
+            assert_eq!(o.verification_keys, outputs[0].verification_keys);
+            assert_eq!(
+                o.share.verification_key(&pk),
+                o.verification_keys[o.share.index() as usize - 1]
+            );
+        }
+
+        let partials: Vec<_> = outputs
+            .iter()
+            .take(2)
+            .map(|o| extract_partial_usk(&o.pk, &o.share, &kid, &mut rng))
+            .collect();
+        for (p, o) in partials.iter().zip(outputs) {
+            assert!(bool::from(verify_partial_usk(
+                &pk,
+                &o.verification_keys[p.index() as usize - 1],
+                &kid,
+                p
+            )));
+        }
+
+        let usk = combine(&partials).unwrap();
+        assert!(bool::from(verify_usk(&pk, &kid, &usk)));
+
+        let (c, k) = encrypt(&pk, &kid, &mut rng);
+        assert_eq!(k, decrypt(&usk, &c));
+    }
+
+    #[test]
+    fn eq_dkg_encrypt_decrypt() {
+        let params = Parameters::new(b"test", 2, 3);
+        let outputs: Vec<_> = run(&params, None, true, &[])
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+
+        check_outputs(&outputs, &[1, 2, 3]);
+    }
+
+    #[test]
+    fn complaint_answered() {
+        let params = Parameters::new(b"test", 2, 3);
+        let outputs: Vec<_> = run(&params, Some((1, 3)), true, &[])
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+
+        check_outputs(&outputs, &[1, 2, 3]);
+    }
+
+    #[test]
+    fn complaint_disqualifies() {
+        let params = Parameters::new(b"test", 2, 3);
+        let outputs: Vec<_> = run(&params, Some((1, 3)), false, &[])
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+
+        check_outputs(&outputs, &[2, 3]);
+    }
+
+    #[test]
+    fn forged_complaints_ignored() {
+        let params = Parameters::new(b"test", 2, 3);
+        let forged = [
+            Complaint {
+                accuser: 0,
+                accused: 1,
+            },
+            Complaint {
+                accuser: 4,
+                accused: 2,
+            },
+            Complaint {
+                accuser: u32::MAX,
+                accused: 3,
+            },
+            Complaint {
+                accuser: 2,
+                accused: 0,
+            },
+            Complaint {
+                accuser: 3,
+                accused: 1,
+            },
+            Complaint {
+                accuser: 3,
+                accused: 1,
+            },
+        ];
+        let outputs: Vec<_> = run(&params, None, true, &forged)
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+
+        check_outputs(&outputs, &[1, 2, 3]);
+    }
+
+    #[test]
+    fn broadcasts_do_not_reveal_master_key() {
+        let params = Parameters::new(b"test", 2, 3);
+        let (mut participants, commitments, shares) = deal(&params);
+        for p in participants.iter_mut() {
+            assert!(p.receive(&commitments, &shares).is_empty());
+        }
+        let outputs: Vec<_> = participants
+            .into_iter()
+            .map(|p| p.finalize(&[], &[]).unwrap())
+            .collect();
+        let pk = outputs[0].pk;
+
+        // Reconstruct the master secret key from the shares, which no party can do by itself.
+        let indices = [1u32, 2];
+        let alpha: G1Affine = indices
+            .iter()
+            .map(|&i| {
+                outputs[i as usize - 1].share.sk.alpha
+                    * lagrange_coefficient(i, indices.iter().copied())
+            })
+            .fold(G1Projective::identity(), |acc, p| acc + p)
+            .into();
+        assert_eq!(irmaseal_curve::pairing(&alpha, &pk.g), pk.z);
+
+        // The constant terms of the commitments only determine the master secret key
+        // relative to a base of which the discrete logarithm is unknown.
+        let constant = commitments
+            .iter()
+            .fold(G2Projective::identity(), |acc, c| acc + c.coefficients[0]);
+        assert_ne!(alpha, params.base());
+        assert_ne!(
+            irmaseal_curve::pairing(&G1Affine::generator(), &constant.into()),
+            pk.z
+        );
+
+        let alpha = alpha.to_compressed();
+        for c in commitments.iter() {
+            assert!(!c.to_bytes().windows(alpha.len()).any(|w| w == alpha));
+        }
+    }
+
+    #[test]
+    fn eq_serialize_deserialize() {
+        let mut rng = rand::thread_rng();
+        let params = Parameters::new(b"test", 2, 3);
+        let (_, c, s) = Participant::new(&params, 1, &mut rng);
+        let complaint = Complaint {
+            accuser: 2,
+            accused: 1,
+        };
+
+        assert_eq!(c, Commitment::from_bytes(&c.to_bytes()).unwrap());
+        assert_eq!(s[0], DealtShare::from_bytes(&s[0].to_bytes()).unwrap());
+        assert_eq!(complaint, Complaint::from_bytes(&complaint.to_bytes()));
+    }
+}
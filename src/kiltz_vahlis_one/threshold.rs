@@ -0,0 +1,381 @@
+//! Threshold extraction of user secret keys for the Kiltz-Vahlis IBE1 scheme.
+//!
+//! The master [`SecretKey`] is split into `n` shares using Shamir secret sharing in the exponent,
+//! such that any `t` share holders can jointly extract user secret keys,
+//! without the master secret key ever being reconstructed.
+//!
+//! Each share holder extracts a [`PartialUserSecretKey`] for an identity using [`extract_partial_usk`].
+//! The user then combines any `t` of those partial keys into a regular [`UserSecretKey`] using [`combine`],
+//! which works with [`decrypt`](super::decrypt) as usual.
+//!
+//! Every share has a public [`VerificationKey`], with which anyone can check a partial key
+//! using [`verify_partial_usk`], such that misbehaving share holders can be identified and excluded.
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use arrayref::{array_refs, mut_array_refs};
+use rand::{CryptoRng, RngCore};
+use subtle::{Choice, CtOption};
+
+use super::{extract_usk, verify_usk_with, Identity, PublicKey, SecretKey, UserSecretKey};
+use crate::util::*;
+use crate::Error;
+use core::convert::TryFrom;
+use irmaseal_curve::{G1Affine, G1Projective, G2Affine, G2Projective, Gt};
+
+/// Share of the master secret key, held by one of the share holders.
+#[derive(Clone)]
+pub struct SecretKeyShare {
+    pub(super) index: u32,
+    pub(super) sk: SecretKey,
+}
+
+zeroize_on_drop!(SecretKeyShare => sk);
+impl_secret!(SecretKeyShare: index, sk);
+impl_try_from_secret!(SecretKeyShare, 52);
+
+/// User secret key extracted using a single master secret key share.
+#[derive(Clone)]
+pub struct PartialUserSecretKey {
+    index: u32,
+    usk: UserSecretKey,
+}
+
+zeroize_on_drop!(PartialUserSecretKey => usk);
+impl_secret!(PartialUserSecretKey: index, usk);
+impl_try_from_secret!(PartialUserSecretKey, 196);
+
+/// Public key to verify partial user secret keys extracted using the corresponding share.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct VerificationKey {
+    pub(super) index: u32,
+    pub(super) vk: Gt,
+}
+
+/// Split the master secret key into `n` shares, any `threshold` of which suffice to extract user secret keys.
+///
+/// The shares are numbered `1` up to and including `n`.
+///
+/// # Panics
+///
+/// If `threshold` is zero or larger than `n`, or if `n` does not fit in an `u32`.
+#[cfg(feature = "alloc")]
+pub fn deal<R: RngCore + CryptoRng>(
+    sk: &SecretKey,
+    threshold: usize,
+    n: usize,
+    rng: &mut R,
+) -> Vec<SecretKeyShare> {
+    assert!(
+        threshold > 0 && threshold <= n && n <= u32::MAX as usize,
+        "invalid threshold parameters"
+    );
+
+    let mut coefficients = Vec::with_capacity(threshold);
+    coefficients.push(G1Projective::from(sk.alpha));
+    for _ in 1..threshold {
+        coefficients.push(rand_g1(rng));
+    }
+
+    let shares = (1..=n as u32)
+        .map(|index| SecretKeyShare {
+            index,
+            sk: SecretKey {
+                alpha: eval_poly_g1(&coefficients, index).into(),
+            },
+        })
+        .collect();
+    coefficients.wipe();
+
+    shares
+}
+
+/// Extract a partial user secret key for a given identity using a master secret key share.
+pub fn extract_partial_usk<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    share: &SecretKeyShare,
+    v: &Identity,
+    rng: &mut R,
+) -> PartialUserSecretKey {
+    PartialUserSecretKey {
+        index: share.index,
+        usk: extract_usk(pk, &share.sk, v, rng),
+    }
+}
+
+/// Verify that a partial user secret key for the given identity was extracted
+/// using the share corresponding to the verification key.
+pub fn verify_partial_usk(
+    pk: &PublicKey,
+    vk: &VerificationKey,
+    v: &Identity,
+    partial: &PartialUserSecretKey,
+) -> Choice {
+    Choice::from((vk.index == partial.index) as u8) & verify_usk_with(pk, v, &partial.usk, &vk.vk)
+}
+
+/// Combine partial user secret keys for the same identity into a user secret key.
+///
+/// Requires at least as many partial keys as the threshold used when dealing,
+/// otherwise the resulting key will not decrypt.
+/// Returns `None` if no partial keys are given, or if any share index occurs twice.
+pub fn combine(partials: &[PartialUserSecretKey]) -> Option<UserSecretKey> {
+    let indices = partials.iter().map(|p| p.index);
+    if !valid_share_indices(indices.clone()) {
+        return None;
+    }
+
+    let mut d1 = G1Projective::identity();
+    let mut d2 = G2Projective::identity();
+    let mut d3 = G1Projective::identity();
+    for p in partials {
+        let lambda = lagrange_coefficient(p.index, indices.clone());
+        d1 += p.usk.d1 * lambda;
+        d2 += p.usk.d2 * lambda;
+        d3 += p.usk.d3 * lambda;
+    }
+
+    let usk = UserSecretKey {
+        d1: d1.into(),
+        d2: d2.into(),
+        d3: d3.into(),
+    };
+    d1.wipe();
+    d2.wipe();
+    d3.wipe();
+
+    Some(usk)
+}
+
+impl SecretKeyShare {
+    /// The index of this share, starting at `1`.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// The public verification key corresponding to this share, to be published by the dealer or share holder.
+    pub fn verification_key(&self, pk: &PublicKey) -> VerificationKey {
+        VerificationKey {
+            index: self.index,
+            vk: irmaseal_curve::pairing(&self.sk.alpha, &pk.g),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; 52] {
+        let mut res = [0u8; 52];
+        let (index, alpha) = mut_array_refs![&mut res, 4, 48];
+        *index = self.index.to_be_bytes();
+        *alpha = self.sk.alpha.to_compressed();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; 52]) -> CtOption<Self> {
+        let (index, alpha) = array_refs![bytes, 4, 48];
+        let index = u32::from_be_bytes(*index);
+
+        G1Affine::from_compressed(alpha).and_then(|alpha| {
+            CtOption::new(
+                SecretKeyShare {
+                    index,
+                    sk: SecretKey { alpha },
+                },
+                Choice::from((index != 0) as u8),
+            )
+        })
+    }
+}
+
+impl VerificationKey {
+    /// The index of the share this verification key corresponds to.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn to_bytes(&self) -> [u8; 292] {
+        let mut res = [0u8; 292];
+        let (index, vk) = mut_array_refs![&mut res, 4, 288];
+        *index = self.index.to_be_bytes();
+        *vk = self.vk.to_compressed();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; 292]) -> CtOption<Self> {
+        let (index, vk) = array_refs![bytes, 4, 288];
+        let index = u32::from_be_bytes(*index);
+
+        Gt::from_compressed(vk).and_then(|vk| {
+            CtOption::new(
+                VerificationKey { index, vk },
+                Choice::from((index != 0) as u8),
+            )
+        })
+    }
+}
+
+impl TryFrom<&[u8]> for VerificationKey {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Error> {
+        let mut d = Decoder::new(bytes, 292)?;
+        let index = u32::from_be_bytes(*d.bytes());
+        if index == 0 {
+            return Err(Error::InvalidEncoding {
+                component: "index",
+                index: None,
+            });
+        }
+
+        Ok(VerificationKey {
+            index,
+            vk: d.gt("vk")?,
+        })
+    }
+}
+
+impl PartialUserSecretKey {
+    /// The index of the share this partial key was extracted with.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn to_bytes(&self) -> [u8; 196] {
+        let mut res = [0u8; 196];
+        let (index, usk) = mut_array_refs![&mut res, 4, 192];
+        *index = self.index.to_be_bytes();
+        *usk = self.usk.to_bytes();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; 196]) -> CtOption<Self> {
+        let (index, d1, d2, d3) = array_refs![bytes, 4, 48, 96, 48];
+        let index = u32::from_be_bytes(*index);
+
+        let d1 = G1Affine::from_compressed(d1);
+        let d2 = G2Affine::from_compressed(d2);
+        let d3 = G1Affine::from_compressed(d3);
+
+        d1.and_then(|d1| {
+            d2.and_then(|d2| {
+                d3.and_then(|d3| {
+                    CtOption::new(
+                        PartialUserSecretKey {
+                            index,
+                            usk: UserSecretKey { d1, d2, d3 },
+                        },
+                        Choice::from((index != 0) as u8),
+                    )
+                })
+            })
+        })
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::super::{decrypt, encrypt, setup, verify_usk};
+    use super::*;
+
+    const ID: &str = "email:w.geraedts@sarif.nl";
+
+    #[test]
+    fn eq_threshold_encrypt_decrypt() {
+        let mut rng = rand::thread_rng();
+
+        let kid = Identity::derive(ID.as_bytes());
+
+        let (pk, sk) = setup(&mut rng);
+        let shares = deal(&sk, 3, 5, &mut rng);
+
+        let partials: Vec<_> = shares
+            .iter()
+            .skip(1)
+            .step_by(2)
+            .chain(shares.iter().take(1))
+            .map(|share| extract_partial_usk(&pk, share, &kid, &mut rng))
+            .collect();
+
+        let usk = combine(&partials).unwrap();
+        let (c, k) = encrypt(&pk, &kid, &mut rng);
+
+        assert_eq!(k, decrypt(&usk, &c));
+
+        // Too few partial keys yield a key that does not decrypt.
+        let usk = combine(&partials[..2]).unwrap();
+        assert_ne!(k, decrypt(&usk, &c));
+    }
+
+    #[test]
+    fn verify_partial_usk_excludes_corrupted() {
+        let mut rng = rand::thread_rng();
+
+        let kid = Identity::derive(ID.as_bytes());
+        let (pk, sk) = setup(&mut rng);
+        let shares = deal(&sk, 2, 3, &mut rng);
+        let vks: Vec<_> = shares.iter().map(|s| s.verification_key(&pk)).collect();
+
+        let mut partials: Vec<_> = shares
+            .iter()
+            .map(|share| extract_partial_usk(&pk, share, &kid, &mut rng))
+            .collect();
+
+        // The first share holder misbehaves by handing out a key of another share.
+        partials[0].usk = partials[1].usk.clone();
+
+        let valid: Vec<_> = partials
+            .iter()
+            .zip(vks.iter())
+            .filter(|(p, vk)| bool::from(verify_partial_usk(&pk, vk, &kid, p)))
+            .map(|(p, _)| p.clone())
+            .collect();
+
+        assert_eq!(valid.len(), 2);
+        assert!(!bool::from(verify_partial_usk(
+            &pk,
+            &vks[2],
+            &kid,
+            &partials[1]
+        )));
+
+        let usk = combine(&valid).unwrap();
+        assert!(bool::from(verify_usk(&pk, &kid, &usk)));
+        assert!(!bool::from(verify_usk(
+            &pk,
+            &kid,
+            &combine(&partials).unwrap()
+        )));
+    }
+
+    #[test]
+    fn combine_rejects_duplicates() {
+        let mut rng = rand::thread_rng();
+
+        let kid = Identity::derive(ID.as_bytes());
+        let (pk, sk) = setup(&mut rng);
+        let shares = deal(&sk, 2, 3, &mut rng);
+
+        let p = extract_partial_usk(&pk, &shares[0], &kid, &mut rng);
+        let q = extract_partial_usk(&pk, &shares[0], &kid, &mut rng);
+
+        assert!(combine(&[p, q]).is_none());
+        assert!(combine(&[]).is_none());
+    }
+
+    #[test]
+    fn eq_serialize_deserialize() {
+        let mut rng = rand::thread_rng();
+
+        let kid = Identity::derive(ID.as_bytes());
+        let (pk, sk) = setup(&mut rng);
+        let share = deal(&sk, 2, 3, &mut rng).remove(2);
+        let partial = extract_partial_usk(&pk, &share, &kid, &mut rng);
+
+        assert_eq!(
+            share,
+            SecretKeyShare::from_bytes(&share.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            partial,
+            PartialUserSecretKey::from_bytes(&partial.to_bytes()).unwrap()
+        );
+    }
+}
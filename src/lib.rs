@@ -4,6 +4,37 @@
 //! * Waters
 //! * Waters-Naccache
 //! * Kiltz-Vahlis IBE1
+//! * Boneh-Franklin (BasicIdent and FullIdent)
+//! * Boneh-Boyen BB1
+//! * Boneh-Boyen-Goh (hierarchical, with key delegation)
+//!
+//! Additionally, the CPA-secure Waters and Waters-Naccache schemes can be turned into
+//! CCA2-secure key encapsulation mechanisms using the [Fujisaki-Okamoto transform](fujisaki_okamoto).
+//! The Waters and Kiltz-Vahlis IBE1 schemes support threshold extraction of user secret keys,
+//! splitting the master secret key among multiple parties (see [`waters::threshold`] and [`kiltz_vahlis_one::threshold`]).
+//! With the `alloc` feature, the Kiltz-Vahlis IBE1 master secret key shares can also be generated
+//! without a trusted dealer using distributed key generation (see `kiltz_vahlis_one::dkg`).
+//! The Waters and Waters-Naccache schemes support blind extraction of user secret keys,
+//! such that the PKG does not learn the identity (see [`waters::blind`] and [`waters_naccache::blind`]).
+//! The Waters, Waters-Naccache and Kiltz-Vahlis IBE1 public keys can be generated in a compact form,
+//! deriving most parameters from a public seed (see [`compact`]).
+//! With the `alloc` feature, these public keys can be prepared with precomputed tables
+//! for faster extraction and encryption (see `waters::PreparedPublicKey`).
+//! Every scheme can extract the user secret keys of many identities in a single batch
+//! (see `waters::extract_usk_batch`), which runs in parallel with the `rayon` feature.
+//!
+//! Keys and ciphertexts are serialized using `to_bytes`, and deserialized either using `from_bytes`,
+//! which returns a [`CtOption`](subtle::CtOption), or using `TryFrom<&[u8]>`, which reports
+//! what is wrong with the input as an [`Error`].
+//! As this byte representation may change between releases, objects that are stored or sent
+//! should be wrapped in the stable, versioned format of the [`envelope`] module.
+//! With the `armor` feature, envelopes can be encoded as PEM-style text blocks (see `armor`).
+//!
+//! With the `serde` feature, these types also implement `Serialize` and `Deserialize`,
+//! encoding the byte representation as a base64 string for human-readable formats.
+//!
+//! With the `zeroize` feature, all secret types implement `Zeroize` and `ZeroizeOnDrop`,
+//! and are wiped from memory when dropped. For this reason secret types do not implement `Copy`.
 //!
 //! ## How to use
 //! The following example is similar for all the schemes.
@@ -12,7 +43,7 @@
 //! ```
 //! use ibe::kiltz_vahlis_one::*;
 //!
-//! const ID: &'static str = "email:w.geraedts@sarif.nl";
+//! const ID: &str = "email:w.geraedts@sarif.nl";
 //! let mut rng = rand::thread_rng();
 //!
 //! // Hash the identity to a set of scalars.
@@ -31,16 +62,110 @@
 //! let k2 = decrypt(&usk, &c);
 //!
 //! assert_eq!(k, k2);
+//!
+//! // Derive a 256-bit key for symmetric encryption from the shared symmetric key.
+//! let mut aes_key = [0u8; 32];
+//! k2.derive_key(&c, b"example AES-256 key", &mut aes_key);
+//! ```
+//!
+//! ## Key derivation
+//! The shared secrets of the schemes are points on the curve, whose byte representation is not
+//! uniformly random and must not be used as a symmetric key directly. Instead, use their `derive_key`
+//! method, which fills its output with a [SHAKE256](https://crates.io/crates/tiny-keccak) based key
+//! derivation function. The derived key is bound to the ciphertext and to the application-specific
+//! `info`, which should be used to separate keys derived for different purposes.
+//!
+//! ## Generic usage
+//! The Waters, Waters-Naccache, Kiltz-Vahlis IBE1 and Boneh-Boyen BB1 schemes implement the [`IBKEM`] trait,
+//! as does the [Fujisaki-Okamoto transform](fujisaki_okamoto) of every scheme implementing [`DerandomizedIBE`].
+//! All schemes that encrypt messages directly, that is all except Kiltz-Vahlis IBE1, implement the [`IBE`] trait.
+//! This allows application code to be generic over the scheme used.
+//!
+//! ```
+//! use ibe::{kiltz_vahlis_one::KiltzVahlisOne, Derive, IBKEM};
+//!
+//! fn roundtrip<S: IBKEM>(id: &str) -> bool
+//! where
+//!     S::SharedSecret: PartialEq,
+//! {
+//!     let mut rng = rand::thread_rng();
+//!
+//!     let kid = S::Identity::derive_str(id);
+//!     let (pk, sk) = S::setup(&mut rng);
+//!     let usk = S::extract_usk(&pk, &sk, &kid, &mut rng);
+//!
+//!     let (c, k) = S::encaps(&pk, &kid, &mut rng);
+//!     k == S::decaps(&pk, &usk, &c)
+//! }
+//!
+//! assert!(roundtrip::<KiltzVahlisOne>("email:w.geraedts@sarif.nl"));
 //! ```
 
 #![no_std]
 
-#[cfg(test)]
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(any(test, feature = "std"))]
 #[macro_use]
 extern crate std;
 
+#[macro_use]
 mod util;
 
+#[cfg(feature = "armor")]
+pub mod armor;
+pub mod compact;
+pub mod envelope;
+mod error;
+pub mod kem;
+pub mod pke;
+
+pub mod bbg_hibe;
+pub mod boneh_boyen_1;
+pub mod boneh_franklin;
+pub mod fujisaki_okamoto;
+#[cfg(feature = "hybrid")]
+pub mod hybrid;
 pub mod kiltz_vahlis_one;
+#[cfg(feature = "stream")]
+pub mod stream;
+#[cfg(feature = "alloc")]
+mod table;
+pub mod validation;
 pub mod waters;
 pub mod waters_naccache;
+
+pub use error::Error;
+pub use kem::IBKEM;
+pub use pke::{DerandomizedIBE, IBE};
+pub use validation::{ValidationError, ValidationErrorKind};
+
+/// Identities that can be derived from arbitrary byte strings.
+pub trait Derive {
+    /// Hash a byte slice to an identity.
+    fn derive(b: &[u8]) -> Self;
+
+    /// Hash a string slice to an identity.
+    /// Directly converts characters to UTF-8 byte representation.
+    fn derive_str(s: &str) -> Self
+    where
+        Self: Sized,
+    {
+        Self::derive(s.as_bytes())
+    }
+}
+
+/// Types with a fixed-size byte representation.
+///
+/// Allows generic code to serialize the datastructures of any scheme.
+pub trait Compress: Sized {
+    /// Byte representation of this type.
+    type Output: AsRef<[u8]>;
+
+    /// Serialize to the byte representation.
+    fn to_bytes(&self) -> Self::Output;
+
+    /// Deserialize from the byte representation.
+    fn from_bytes(bytes: &Self::Output) -> subtle::CtOption<Self>;
+}
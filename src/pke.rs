@@ -0,0 +1,124 @@
+//! Public-key encryption style interface shared by the schemes in this crate.
+//!
+//! Allows application code to be generic over the scheme used,
+//! such that switching schemes is a matter of changing a single type parameter.
+
+use crate::Derive;
+use rand::{CryptoRng, RngCore};
+
+/// Identity Based Encryption scheme that encrypts messages directly.
+pub trait IBE {
+    /// Public key parameters generated by the PKG used to encrypt messages.
+    type PublicKey;
+
+    /// Secret key generated by the PKG used to extract user secret keys.
+    type SecretKey;
+
+    /// Secret key for a single identity, used to decrypt messages.
+    type UserSecretKey;
+
+    /// Identity under which messages are encrypted.
+    type Identity: Derive;
+
+    /// Message that can be encrypted and decrypted.
+    type Message;
+
+    /// Encrypted message. Can only be decrypted with an user secret key.
+    type CipherText;
+
+    /// Generate a keypair used by the Private Key Generator (PKG).
+    fn setup<R: RngCore + CryptoRng>(rng: &mut R) -> (Self::PublicKey, Self::SecretKey);
+
+    /// Extract an user secret key for a given identity.
+    fn extract_usk<R: RngCore + CryptoRng>(
+        pk: &Self::PublicKey,
+        sk: &Self::SecretKey,
+        id: &Self::Identity,
+        rng: &mut R,
+    ) -> Self::UserSecretKey;
+
+    /// Encrypt a message using the PKG public key and an identity.
+    fn encrypt<R: RngCore + CryptoRng>(
+        pk: &Self::PublicKey,
+        id: &Self::Identity,
+        message: &Self::Message,
+        rng: &mut R,
+    ) -> Self::CipherText;
+
+    /// Decrypt ciphertext to a message using a user secret key.
+    fn decrypt(usk: &Self::UserSecretKey, c: &Self::CipherText) -> Self::Message;
+}
+
+/// IBE scheme of which the encryption randomness can be supplied by the caller.
+///
+/// Encrypting the same message with the same coins yields the same ciphertext,
+/// which is what the [Fujisaki-Okamoto transform](crate::fujisaki_okamoto) relies on.
+pub trait DerandomizedIBE: IBE {
+    /// Generate a uniformly random message.
+    fn generate_message<R: RngCore + CryptoRng>(rng: &mut R) -> Self::Message;
+
+    /// Encrypt a message using the PKG public key and an identity,
+    /// deriving all randomness from the given coins.
+    fn encrypt_derandomized(
+        pk: &Self::PublicKey,
+        id: &Self::Identity,
+        message: &Self::Message,
+        coins: &[u8; 64],
+    ) -> Self::CipherText;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bbg_hibe, boneh_boyen_1, boneh_franklin, waters, waters_naccache};
+    use core::fmt::Debug;
+
+    const ID: &str = "email:w.geraedts@sarif.nl";
+
+    fn eq_encrypt_decrypt<S: IBE>(m: S::Message)
+    where
+        S::Message: Debug + PartialEq,
+    {
+        let mut rng = rand::thread_rng();
+
+        let kid = S::Identity::derive_str(ID);
+
+        let (pk, sk) = S::setup(&mut rng);
+        let usk = S::extract_usk(&pk, &sk, &kid, &mut rng);
+
+        let c = S::encrypt(&pk, &kid, &m, &mut rng);
+        let m2 = S::decrypt(&usk, &c);
+
+        assert_eq!(m, m2);
+    }
+
+    #[test]
+    fn eq_encrypt_decrypt_waters() {
+        let m = waters::Message::generate(&mut rand::thread_rng());
+        eq_encrypt_decrypt::<waters::Waters>(m);
+    }
+
+    #[test]
+    fn eq_encrypt_decrypt_waters_naccache() {
+        let m = waters_naccache::Message::generate(&mut rand::thread_rng());
+        eq_encrypt_decrypt::<waters_naccache::WatersNaccache>(m);
+    }
+
+    #[test]
+    fn eq_encrypt_decrypt_boneh_franklin() {
+        let m = boneh_franklin::Message::generate(&mut rand::thread_rng());
+        eq_encrypt_decrypt::<boneh_franklin::BonehFranklin>(m);
+    }
+
+    #[test]
+    fn eq_encrypt_decrypt_boneh_boyen_1() {
+        let m = boneh_boyen_1::Message::generate(&mut rand::thread_rng());
+        eq_encrypt_decrypt::<boneh_boyen_1::BonehBoyen1>(m);
+    }
+
+    #[test]
+    fn eq_encrypt_decrypt_bbg_hibe() {
+        let m = bbg_hibe::Message::generate(&mut rand::thread_rng());
+        eq_encrypt_decrypt::<bbg_hibe::BonehBoyenGoh>(m);
+    }
+}
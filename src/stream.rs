@@ -0,0 +1,506 @@
+//! Streaming encryption of large messages using the Kiltz-Vahlis IBE1 scheme and AES-256-GCM.
+//!  * Inspired by: "[Online Authenticated-Encryption and its Nonce-Reuse Misuse-Resistance](https://eprint.iacr.org/2015/189)"
+//!  * Published in: CRYPTO, 2015
+//!
+//! A fresh symmetric key is encapsulated for an identity using [`kiltz_vahlis_one`](crate::kiltz_vahlis_one),
+//! from which an AES-256-GCM key is derived using [`SymmetricKey::derive_key`].
+//! The plaintext is then split into segments of equal size, which are sealed separately following
+//! the STREAM construction: each nonce consists of a random prefix, a segment counter and a flag
+//! indicating the final segment. Reordering, duplicating or truncating segments is thus detected.
+//!
+//! The stream starts with a header, laid out as follows:
+//!
+//! | Field                      | Size (bytes) |
+//! |----------------------------|--------------|
+//! | Magic bytes `IBES`         | 4            |
+//! | Format version             | 1            |
+//! | Segment size (big endian)  | 4            |
+//! | IBE ciphertext             | 144          |
+//! | Nonce prefix               | 7            |
+//!
+//! The header is followed by the sealed segments, each consisting of the AES-GCM ciphertext
+//! of the segment and its 16 byte tag. All segments except the final one contain exactly
+//! segment size bytes of plaintext, the final segment contains strictly less (possibly none).
+//! The header is authenticated as associated data of every segment.
+//!
+//! The [`Sealer`] and [`Opener`] operate on caller-supplied buffers and do not require an allocator.
+//! With the `std` feature, [`seal`] and [`open`] operate on [`std::io::Read`] and [`std::io::Write`].
+//!
+//! Note that the [`Opener`] releases plaintext segment by segment:
+//! the stream as a whole can only be trusted when the final segment has been opened successfully.
+//!
+//! Requires the `stream` feature.
+
+use aes_gcm::aead::{AeadInPlace, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce, Tag};
+use arrayref::{array_ref, array_refs, mut_array_refs};
+use rand::{CryptoRng, RngCore};
+
+use crate::kiltz_vahlis_one::{
+    decrypt, encrypt, CipherText, Identity, PublicKey, SymmetricKey, UserSecretKey,
+};
+use crate::util::Wipe;
+
+const MAGIC: &[u8; 4] = b"IBES";
+const VERSION: u8 = 1;
+
+const CIPHERTEXT_SIZE: usize = 144;
+const NONCE_PREFIX_SIZE: usize = 7;
+
+/// Size of the header preceding the sealed segments.
+pub const HEADER_SIZE: usize = MAGIC.len() + 1 + 4 + CIPHERTEXT_SIZE + NONCE_PREFIX_SIZE;
+
+/// Size of the tag appended to every sealed segment.
+pub const TAG_SIZE: usize = 16;
+
+/// Segment size used by [`seal`].
+pub const DEFAULT_SEGMENT_SIZE: u32 = 64 * 1024;
+
+/// Largest segment size accepted by the [`Opener`].
+pub const MAX_SEGMENT_SIZE: u32 = 16 * 1024 * 1024;
+
+const KDF_INFO: &[u8] = b"ibe-stream-aes256gcm";
+
+/// Reasons why a stream could not be sealed or opened.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The magic bytes, format version or segment size in the header are not recognized.
+    InvalidHeader,
+    /// The IBE ciphertext in the header does not consist of valid curve points.
+    InvalidCipherText,
+    /// A segment has the wrong size: non-final segments must be exactly the segment size,
+    /// the final segment must be strictly smaller.
+    InvalidSegmentSize,
+    /// The stream ended before the final segment.
+    Truncated,
+    /// The maximum number of segments for a single stream has been exceeded.
+    TooManySegments,
+    /// The AES-GCM tag of a segment does not match, either because the stream was tampered with
+    /// or because it was not sealed for the identity of the user secret key.
+    Authentication,
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let msg = match self {
+            Error::InvalidHeader => "invalid stream header",
+            Error::InvalidCipherText => "invalid IBE ciphertext in stream header",
+            Error::InvalidSegmentSize => "invalid segment size",
+            Error::Truncated => "stream is truncated",
+            Error::TooManySegments => "too many segments",
+            Error::Authentication => "segment failed to authenticate",
+        };
+        f.write_str(msg)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// State shared by the sealer and the opener.
+struct Stream {
+    cipher: Aes256Gcm,
+    header: [u8; HEADER_SIZE],
+    segment_size: usize,
+    counter: u32,
+}
+
+impl Stream {
+    fn new(k: &SymmetricKey, c: &CipherText, header: [u8; HEADER_SIZE]) -> Stream {
+        let (_, _, segment_size, _) =
+            array_refs![&header, 4, 1, 4, CIPHERTEXT_SIZE + NONCE_PREFIX_SIZE];
+
+        let mut key = [0u8; 32];
+        k.derive_key(c, KDF_INFO, &mut key);
+
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+        key.wipe();
+
+        Stream {
+            cipher,
+            segment_size: u32::from_be_bytes(*segment_size) as usize,
+            header,
+            counter: 0,
+        }
+    }
+
+    /// Nonce for the next segment, advancing the counter.
+    fn next_nonce(&mut self, last: bool) -> Result<[u8; 12], Error> {
+        let mut nonce = [0u8; 12];
+        let (prefix, counter, flag) = mut_array_refs![&mut nonce, NONCE_PREFIX_SIZE, 4, 1];
+        *prefix = *array_ref![
+            self.header,
+            HEADER_SIZE - NONCE_PREFIX_SIZE,
+            NONCE_PREFIX_SIZE
+        ];
+        *counter = self.counter.to_be_bytes();
+        flag[0] = last as u8;
+
+        self.counter = self.counter.checked_add(1).ok_or(Error::TooManySegments)?;
+        Ok(nonce)
+    }
+
+    fn check_size(&self, len: usize, last: bool) -> Result<(), Error> {
+        let valid = if last {
+            len < self.segment_size
+        } else {
+            len == self.segment_size
+        };
+
+        if valid {
+            Ok(())
+        } else {
+            Err(Error::InvalidSegmentSize)
+        }
+    }
+
+    fn seal(&mut self, segment: &mut [u8], last: bool) -> Result<[u8; TAG_SIZE], Error> {
+        self.check_size(segment.len(), last)?;
+        let nonce = self.next_nonce(last)?;
+
+        let tag = self
+            .cipher
+            .encrypt_in_place_detached(Nonce::from_slice(&nonce), &self.header, segment)
+            .map_err(|_| Error::InvalidSegmentSize)?;
+
+        Ok(tag.into())
+    }
+
+    fn open(&mut self, segment: &mut [u8], tag: &[u8; TAG_SIZE], last: bool) -> Result<(), Error> {
+        self.check_size(segment.len(), last)?;
+        let nonce = self.next_nonce(last)?;
+
+        self.cipher
+            .decrypt_in_place_detached(
+                Nonce::from_slice(&nonce),
+                &self.header,
+                segment,
+                Tag::from_slice(tag),
+            )
+            .map_err(|_| Error::Authentication)
+    }
+}
+
+/// Seals a stream segment by segment, in place in caller-supplied buffers.
+pub struct Sealer(Stream);
+
+impl Sealer {
+    /// Encapsulate a fresh key for an identity using the PKG public key,
+    /// and start a stream with the given segment size.
+    ///
+    /// # Panics
+    /// If the segment size is zero or larger than [`MAX_SEGMENT_SIZE`].
+    pub fn new<R: RngCore + CryptoRng>(
+        pk: &PublicKey,
+        v: &Identity,
+        segment_size: u32,
+        rng: &mut R,
+    ) -> Sealer {
+        assert!(segment_size > 0 && segment_size <= MAX_SEGMENT_SIZE);
+
+        let (c, k) = encrypt(pk, v, rng);
+
+        let mut header = [0u8; HEADER_SIZE];
+        {
+            let (magic, version, size, cbytes, prefix) =
+                mut_array_refs![&mut header, 4, 1, 4, CIPHERTEXT_SIZE, NONCE_PREFIX_SIZE];
+            *magic = *MAGIC;
+            version[0] = VERSION;
+            *size = segment_size.to_be_bytes();
+            *cbytes = c.to_bytes();
+            rng.fill_bytes(prefix);
+        }
+
+        Sealer(Stream::new(&k, &c, header))
+    }
+
+    /// The header to be written before the sealed segments.
+    pub fn header(&self) -> &[u8; HEADER_SIZE] {
+        &self.0.header
+    }
+
+    /// The number of plaintext bytes in every non-final segment.
+    pub fn segment_size(&self) -> usize {
+        self.0.segment_size
+    }
+
+    /// Seal a non-final segment in place, which must be exactly the segment size.
+    ///
+    /// Returns the tag to be written after the sealed segment.
+    pub fn seal_segment(&mut self, segment: &mut [u8]) -> Result<[u8; TAG_SIZE], Error> {
+        self.0.seal(segment, false)
+    }
+
+    /// Seal the final segment in place, which must be strictly smaller than the segment size.
+    ///
+    /// Returns the tag to be written after the sealed segment.
+    pub fn seal_last_segment(mut self, segment: &mut [u8]) -> Result<[u8; TAG_SIZE], Error> {
+        self.0.seal(segment, true)
+    }
+}
+
+/// Opens a stream segment by segment, in place in caller-supplied buffers.
+pub struct Opener(Stream);
+
+impl Opener {
+    /// Parse the header of a stream and decapsulate its key using a user secret key.
+    pub fn new(usk: &UserSecretKey, header: &[u8; HEADER_SIZE]) -> Result<Opener, Error> {
+        let (magic, version, size, cbytes, _) =
+            array_refs![header, 4, 1, 4, CIPHERTEXT_SIZE, NONCE_PREFIX_SIZE];
+
+        let size = u32::from_be_bytes(*size);
+        if magic != MAGIC || version[0] != VERSION || size == 0 || size > MAX_SEGMENT_SIZE {
+            return Err(Error::InvalidHeader);
+        }
+
+        let c: Option<CipherText> = CipherText::from_bytes(cbytes).into();
+        let c = c.ok_or(Error::InvalidCipherText)?;
+
+        let k = decrypt(usk, &c);
+        Ok(Opener(Stream::new(&k, &c, *header)))
+    }
+
+    /// The number of plaintext bytes in every non-final segment.
+    pub fn segment_size(&self) -> usize {
+        self.0.segment_size
+    }
+
+    /// Open a non-final segment in place, which must be exactly the segment size.
+    pub fn open_segment(&mut self, segment: &mut [u8], tag: &[u8; TAG_SIZE]) -> Result<(), Error> {
+        self.0.open(segment, tag, false)
+    }
+
+    /// Open the final segment in place, which must be strictly smaller than the segment size.
+    pub fn open_last_segment(
+        mut self,
+        segment: &mut [u8],
+        tag: &[u8; TAG_SIZE],
+    ) -> Result<(), Error> {
+        self.0.open(segment, tag, true)
+    }
+}
+
+/// Read until the buffer is full or the reader is exhausted, returning the number of bytes read.
+#[cfg(feature = "std")]
+fn read_full<Rd: std::io::Read>(r: &mut Rd, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut len = 0;
+    while len < buf.len() {
+        match r.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(len)
+}
+
+#[cfg(feature = "std")]
+fn invalid_data(e: Error) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, e)
+}
+
+/// Seal everything from a reader for an identity using the PKG public key, writing to a writer.
+///
+/// Uses segments of [`DEFAULT_SEGMENT_SIZE`] bytes.
+#[cfg(feature = "std")]
+pub fn seal<R: RngCore + CryptoRng, Rd: std::io::Read, W: std::io::Write>(
+    pk: &PublicKey,
+    v: &Identity,
+    rng: &mut R,
+    r: Rd,
+    w: W,
+) -> std::io::Result<()> {
+    seal_with_segment_size(pk, v, DEFAULT_SEGMENT_SIZE, rng, r, w)
+}
+
+#[cfg(feature = "std")]
+fn seal_with_segment_size<R: RngCore + CryptoRng, Rd: std::io::Read, W: std::io::Write>(
+    pk: &PublicKey,
+    v: &Identity,
+    segment_size: u32,
+    rng: &mut R,
+    mut r: Rd,
+    mut w: W,
+) -> std::io::Result<()> {
+    let mut sealer = Sealer::new(pk, v, segment_size, rng);
+    w.write_all(sealer.header())?;
+
+    let mut buf = vec![0u8; sealer.segment_size()];
+    loop {
+        let len = read_full(&mut r, &mut buf)?;
+        if len < buf.len() {
+            let tag = sealer
+                .seal_last_segment(&mut buf[..len])
+                .map_err(invalid_data)?;
+            w.write_all(&buf[..len])?;
+            w.write_all(&tag)?;
+            return w.flush();
+        }
+
+        let tag = sealer.seal_segment(&mut buf).map_err(invalid_data)?;
+        w.write_all(&buf)?;
+        w.write_all(&tag)?;
+    }
+}
+
+/// Open a stream from a reader using a user secret key, writing the plaintext to a writer.
+///
+/// Plaintext is written segment by segment as soon as it has been authenticated.
+/// If an error is returned, everything written so far must be discarded.
+/// Failures to authenticate are reported as [`std::io::ErrorKind::InvalidData`] wrapping an [`Error`].
+#[cfg(feature = "std")]
+pub fn open<Rd: std::io::Read, W: std::io::Write>(
+    usk: &UserSecretKey,
+    mut r: Rd,
+    mut w: W,
+) -> std::io::Result<()> {
+    let mut header = [0u8; HEADER_SIZE];
+    if read_full(&mut r, &mut header)? < HEADER_SIZE {
+        return Err(invalid_data(Error::Truncated));
+    }
+
+    let mut opener = Opener::new(usk, &header).map_err(invalid_data)?;
+
+    let segment_size = opener.segment_size();
+    let mut buf = vec![0u8; segment_size + TAG_SIZE];
+    loop {
+        let len = read_full(&mut r, &mut buf)?;
+        if len < TAG_SIZE {
+            return Err(invalid_data(Error::Truncated));
+        }
+
+        let last = len < buf.len();
+        let (segment, tag) = buf[..len].split_at_mut(len - TAG_SIZE);
+        let tag = array_ref![tag, 0, TAG_SIZE];
+
+        if last {
+            opener
+                .open_last_segment(segment, tag)
+                .map_err(invalid_data)?;
+            w.write_all(segment)?;
+            return w.flush();
+        }
+
+        opener.open_segment(segment, tag).map_err(invalid_data)?;
+        w.write_all(segment)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kiltz_vahlis_one::{extract_usk, setup};
+
+    const ID: &str = "email:w.geraedts@sarif.nl";
+    const SEGMENT_SIZE: u32 = 32;
+
+    #[test]
+    fn eq_seal_open_segments() {
+        let mut rng = rand::thread_rng();
+
+        let kid = Identity::derive_str(ID);
+        let (pk, sk) = setup(&mut rng);
+        let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+
+        let mut first = [1u8; SEGMENT_SIZE as usize];
+        let mut last = [2u8; 5];
+
+        let mut sealer = Sealer::new(&pk, &kid, SEGMENT_SIZE, &mut rng);
+        let header = *sealer.header();
+        let first_tag = sealer.seal_segment(&mut first).unwrap();
+        let last_tag = sealer.seal_last_segment(&mut last).unwrap();
+
+        let mut opener = Opener::new(&usk, &header).unwrap();
+        opener.open_segment(&mut first, &first_tag).unwrap();
+        opener.open_last_segment(&mut last, &last_tag).unwrap();
+
+        assert_eq!(first, [1u8; SEGMENT_SIZE as usize]);
+        assert_eq!(last, [2u8; 5]);
+    }
+
+    #[test]
+    fn reject_segment_sizes() {
+        let mut rng = rand::thread_rng();
+
+        let kid = Identity::derive_str(ID);
+        let (pk, _) = setup(&mut rng);
+
+        let mut sealer = Sealer::new(&pk, &kid, SEGMENT_SIZE, &mut rng);
+        let mut buf = [0u8; SEGMENT_SIZE as usize];
+
+        assert_eq!(
+            sealer.seal_segment(&mut buf[1..]),
+            Err(Error::InvalidSegmentSize)
+        );
+        assert_eq!(
+            sealer.seal_last_segment(&mut buf),
+            Err(Error::InvalidSegmentSize)
+        );
+    }
+
+    #[test]
+    fn reject_reordered_segments() {
+        let mut rng = rand::thread_rng();
+
+        let kid = Identity::derive_str(ID);
+        let (pk, sk) = setup(&mut rng);
+        let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+
+        let mut first = [1u8; SEGMENT_SIZE as usize];
+        let mut second = [2u8; SEGMENT_SIZE as usize];
+
+        let mut sealer = Sealer::new(&pk, &kid, SEGMENT_SIZE, &mut rng);
+        let header = *sealer.header();
+        let first_tag = sealer.seal_segment(&mut first).unwrap();
+        let second_tag = sealer.seal_segment(&mut second).unwrap();
+
+        let mut opener = Opener::new(&usk, &header).unwrap();
+        assert_eq!(
+            opener.open_segment(&mut second, &second_tag),
+            Err(Error::Authentication)
+        );
+
+        // A non-final segment can not be passed off as the final segment.
+        let opener = Opener::new(&usk, &header).unwrap();
+        assert_eq!(
+            opener.open_last_segment(&mut first[..SEGMENT_SIZE as usize - 1], &first_tag),
+            Err(Error::Authentication)
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn eq_seal_open_io() {
+        let mut rng = rand::thread_rng();
+
+        let kid = Identity::derive_str(ID);
+        let (pk, sk) = setup(&mut rng);
+        let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+
+        let n = SEGMENT_SIZE as usize;
+        for len in &[0, 1, n - 1, n, n + 1, 3 * n] {
+            let plaintext: std::vec::Vec<u8> = (0..*len).map(|i| i as u8).collect();
+
+            let mut sealed = vec![];
+            seal_with_segment_size(
+                &pk,
+                &kid,
+                SEGMENT_SIZE,
+                &mut rng,
+                &plaintext[..],
+                &mut sealed,
+            )
+            .unwrap();
+
+            let mut opened = vec![];
+            open(&usk, &sealed[..], &mut opened).unwrap();
+            assert_eq!(opened, plaintext);
+
+            // Truncation at any segment boundary must be detected.
+            for end in (HEADER_SIZE..sealed.len()).step_by(n + TAG_SIZE) {
+                assert!(open(&usk, &sealed[..end], std::io::sink()).is_err());
+            }
+        }
+    }
+}
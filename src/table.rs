@@ -0,0 +1,175 @@
+//! Precomputed tables of curve points, used by the prepared public keys.
+//!
+//! A table consists of windows of 2^[`WINDOW`] points, of which one point per window is selected
+//! and summed. Entries are always selected by scanning the entire window in constant time,
+//! such that the memory access pattern does not depend on the (possibly secret) indices.
+
+use alloc::vec::Vec;
+use irmaseal_curve::{G1Affine, G1Projective, G2Affine, G2Projective, Scalar};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+use crate::util::Wipe;
+
+/// Number of bits selecting an entry of a window.
+pub const WINDOW: usize = 4;
+
+const ENTRIES: usize = 1 << WINDOW;
+
+/// Number of windows needed to multiply with any scalar.
+pub const SCALAR_WINDOWS: usize = 256 / WINDOW;
+
+macro_rules! table {
+    ($name:ident, $affine:ty, $projective:ty) => {
+        pub struct $name(Vec<[$affine; ENTRIES]>);
+
+        // Not every table is used for both fixed-base multiplication and subset sums.
+        #[allow(dead_code)]
+        impl $name {
+            /// Normalize the windows of projective points in a single batch.
+            fn from_projective(points: &[$projective]) -> Self {
+                let mut affine = alloc::vec![<$affine>::identity(); points.len()];
+                <$projective>::batch_normalize(points, &mut affine);
+
+                $name(
+                    affine
+                        .chunks(ENTRIES)
+                        .map(|w| {
+                            let mut window = [<$affine>::identity(); ENTRIES];
+                            window.copy_from_slice(w);
+                            window
+                        })
+                        .collect(),
+                )
+            }
+
+            /// Fixed-base table of `p`, multiplying with scalars of up to `windows * WINDOW` bits.
+            ///
+            /// Window `i` contains the multiples `j * 2^(i * WINDOW) * p` for all `j < 2^WINDOW`,
+            /// hence multiplication takes one addition per window and no doublings.
+            pub fn fixed_base(p: &$affine, windows: usize) -> Self {
+                let mut points = Vec::with_capacity(windows * ENTRIES);
+                let mut base = <$projective>::from(p);
+                for _ in 0..windows {
+                    let mut acc = <$projective>::identity();
+                    for _ in 0..ENTRIES {
+                        points.push(acc);
+                        acc += base;
+                    }
+                    base = acc;
+                }
+                Self::from_projective(&points)
+            }
+
+            /// Table of the sums of all subsets of every group of `WINDOW` consecutive points.
+            ///
+            /// Entry `j` of window `i` is the sum of the points `points[i * WINDOW + k]`
+            /// for which bit `k` of `j` is set.
+            pub fn subset_sums(points: &[$affine]) -> Self {
+                debug_assert_eq!(points.len() % WINDOW, 0);
+
+                let mut sums = Vec::with_capacity(points.len() / WINDOW * ENTRIES);
+                for group in points.chunks(WINDOW) {
+                    let start = sums.len();
+                    sums.push(<$projective>::identity());
+                    for j in 1..ENTRIES {
+                        let sum = sums[start + (j & (j - 1))] + group[j.trailing_zeros() as usize];
+                        sums.push(sum);
+                    }
+                }
+                Self::from_projective(&sums)
+            }
+
+            /// Sum one entry per window, selected by `indices`.
+            pub fn sum<I: Iterator<Item = u8>>(&self, indices: I) -> $projective {
+                let mut acc = <$projective>::identity();
+                for (window, index) in self.0.iter().zip(indices) {
+                    let mut entry = <$affine>::identity();
+                    for (j, e) in (0u8..).zip(window.iter()) {
+                        entry.conditional_assign(e, j.ct_eq(&index));
+                    }
+                    acc += entry;
+                }
+                acc
+            }
+
+            /// Sum the subsets of the points of a [`subset_sums`](Self::subset_sums) table selected by `bits`.
+            ///
+            /// Supports tables of up to 512 points.
+            pub fn sum_bits<I: Iterator<Item = Choice>>(&self, bits: I) -> $projective {
+                let mut indices = [0u8; SCALAR_WINDOWS * 2];
+                for (i, bit) in bits.enumerate() {
+                    indices[i / WINDOW] |= bit.unwrap_u8() << (i % WINDOW);
+                }
+                self.sum(indices.iter().copied())
+            }
+
+            /// Multiply the point of a [`fixed_base`](Self::fixed_base) table with a scalar.
+            pub fn mul(&self, s: &Scalar) -> $projective {
+                let mut bytes = s.to_bytes();
+                let res = self.sum(bytes.iter().flat_map(|b| {
+                    core::iter::once(b & 0x0f).chain(core::iter::once(b >> WINDOW))
+                }));
+                bytes.wipe();
+                res
+            }
+        }
+
+        impl Wipe for $name {
+            fn wipe(&mut self) {
+                self.0.wipe();
+            }
+        }
+    };
+}
+
+table!(G1Table, G1Affine, G1Projective);
+table!(G2Table, G2Affine, G2Projective);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{rand_g1, rand_g2, rand_scalar, XofRng};
+
+    #[test]
+    fn fixed_base() {
+        let mut rng = XofRng::new(b"ibe-table-test", &[0u8; 32]);
+        let p: G1Affine = rand_g1(&mut rng).into();
+        let q: G2Affine = rand_g2(&mut rng).into();
+        let g1 = G1Table::fixed_base(&p, SCALAR_WINDOWS);
+        let g2 = G2Table::fixed_base(&q, SCALAR_WINDOWS);
+
+        for s in [
+            Scalar::zero(),
+            Scalar::one(),
+            -Scalar::one(),
+            rand_scalar(&mut rng),
+        ]
+        .iter()
+        {
+            assert_eq!(g1.mul(s), p * s);
+            assert_eq!(g2.mul(s), q * s);
+        }
+
+        let short = G1Table::fixed_base(&p, 8);
+        let s = Scalar::from(0xdead_beef);
+        assert_eq!(short.mul(&s), p * s);
+    }
+
+    #[test]
+    fn subset_sums() {
+        let mut rng = XofRng::new(b"ibe-table-test", &[1u8; 32]);
+        let points: Vec<G1Affine> = (0..8).map(|_| rand_g1(&mut rng).into()).collect();
+        let table = G1Table::subset_sums(&points);
+
+        let bits = [1u8, 0, 1, 1, 0, 0, 0, 1];
+        let expected = points
+            .iter()
+            .zip(bits.iter())
+            .filter(|(_, b)| **b == 1)
+            .fold(G1Projective::identity(), |acc, (p, _)| acc + p);
+        assert_eq!(
+            table.sum_bits(bits.iter().map(|b| Choice::from(*b))),
+            expected
+        );
+    }
+}
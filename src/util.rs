@@ -1,39 +1,52 @@
+use crate::Error;
+use core::convert::TryFrom;
 use irmaseal_curve::{G1Affine, G1Projective, G2Affine, G2Projective, Gt, Scalar};
+use rand::{CryptoRng, RngCore};
 
-pub fn rand_scalar<R: ::rand::Rng>(rng: &mut R) -> Scalar {
+pub fn rand_scalar<R: RngCore + CryptoRng>(rng: &mut R) -> Scalar {
     let mut buf = [0u8; 64];
     rng.fill_bytes(&mut buf);
 
-    Scalar::from_bytes_wide(&buf)
+    let res = Scalar::from_bytes_wide(&buf);
+    buf.wipe();
+
+    res
 }
 
-pub fn rand_g1<R: ::rand::Rng>(rng: &mut R) -> G1Projective {
+pub fn rand_g1<R: RngCore + CryptoRng>(rng: &mut R) -> G1Projective {
     use core::ops::Mul;
     let g = G1Projective::generator();
-    let x = rand_scalar(rng);
-    g.mul(x)
+    let mut x = rand_scalar(rng);
+    let res = g.mul(x);
+    x.wipe();
+
+    res
 }
 
-pub fn rand_g2<R: ::rand::Rng>(rng: &mut R) -> G2Projective {
+pub fn rand_g2<R: RngCore + CryptoRng>(rng: &mut R) -> G2Projective {
     use core::ops::Mul;
     let g = G2Projective::generator();
-    let x = rand_scalar(rng);
-    g.mul(x)
+    let mut x = rand_scalar(rng);
+    let res = g.mul(x);
+    x.wipe();
+
+    res
 }
 
-pub fn rand_gt<R: ::rand::Rng>(rng: &mut R) -> Gt {
+pub fn rand_gt<R: RngCore + CryptoRng>(rng: &mut R) -> Gt {
     let generator = irmaseal_curve::pairing(&G1Affine::generator(), &G2Affine::generator());
 
     let r = rand_scalar(rng);
     generator * r
 }
 
+/// Iterate over all bits of a byte slice, starting at the most significant bit of the last byte.
 pub fn bits<'a>(slice: &'a [u8]) -> impl Iterator<Item = subtle::Choice> + 'a {
-    slice
-        .iter()
-        .rev()
-        .zip((0..8).rev())
-        .map(|(x, i)| subtle::Choice::from((*x >> i) & 1))
+    slice.iter().rev().flat_map(|x| {
+        (0..8)
+            .rev()
+            .map(move |i| subtle::Choice::from((*x >> i) & 1))
+    })
 }
 
 pub fn sha3_256(slice: &[u8]) -> [u8; 32] {
@@ -45,7 +58,7 @@ pub fn sha3_256(slice: &[u8]) -> [u8; 32] {
     let mut buf = [0u8; 32];
     digest.finalize(&mut buf);
 
-    return buf;
+    buf
 }
 
 pub fn sha3_512(slice: &[u8]) -> [u8; 64] {
@@ -57,5 +70,706 @@ pub fn sha3_512(slice: &[u8]) -> [u8; 64] {
     let mut buf = [0u8; 64];
     digest.finalize(&mut buf);
 
-    return buf;
+    buf
+}
+
+/// SHA3-256 over the concatenation of multiple byte slices.
+pub fn sha3_256_multi(slices: &[&[u8]]) -> [u8; 32] {
+    use tiny_keccak::Hasher;
+
+    let mut digest = tiny_keccak::Sha3::v256();
+    for slice in slices {
+        digest.update(slice);
+    }
+
+    let mut buf = [0u8; 32];
+    digest.finalize(&mut buf);
+
+    buf
+}
+
+/// SHA3-512 over the concatenation of multiple byte slices.
+pub fn sha3_512_multi(slices: &[&[u8]]) -> [u8; 64] {
+    use tiny_keccak::Hasher;
+
+    let mut digest = tiny_keccak::Sha3::v512();
+    for slice in slices {
+        digest.update(slice);
+    }
+
+    let mut buf = [0u8; 64];
+    digest.finalize(&mut buf);
+
+    buf
+}
+
+/// Hash a byte slice to a point on G1, following the random oracle construction of
+/// [hash-to-curve](https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-hash-to-curve-12)
+/// using SHAKE256 for message expansion.
+pub fn hash_to_g1(msg: &[u8], dst: &[u8]) -> G1Projective {
+    use irmaseal_curve::hash_to_curve::{ExpandMsgXof, HashToCurve};
+    <G1Projective as HashToCurve<ExpandMsgXof<sha3::Shake256>>>::hash_to_curve(msg, dst)
+}
+
+/// Hash a byte slice to a point on G2, following the random oracle construction of
+/// [hash-to-curve](https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-hash-to-curve-12)
+/// using SHAKE256 for message expansion.
+pub fn hash_to_g2(msg: &[u8], dst: &[u8]) -> G2Projective {
+    use irmaseal_curve::hash_to_curve::{ExpandMsgXof, HashToCurve};
+    <G2Projective as HashToCurve<ExpandMsgXof<sha3::Shake256>>>::hash_to_curve(msg, dst)
+}
+
+const KDF_DST: &[u8] = b"ibe-kdf-shake256";
+
+/// Input to hash-to-curve for the `index`-th public parameter derived from a seed.
+pub fn seeded_parameter_input(seed: &[u8; 32], index: u32) -> [u8; 36] {
+    let mut res = [0u8; 36];
+    let (s, i) = arrayref::mut_array_refs![&mut res, 32, 4];
+    *s = *seed;
+    *i = index.to_be_bytes();
+    res
+}
+
+/// Key derivation function based on SHAKE256.
+///
+/// Derives `out.len()` bytes of key material from the input key material,
+/// bound to the ciphertext it was decapsulated from and to some context information.
+/// Each input is prefixed with its length, such that the encoding is injective.
+pub fn kdf(ikm: &[u8], ciphertext: &[u8], info: &[u8], out: &mut [u8]) {
+    use tiny_keccak::{Hasher, Xof};
+
+    let mut xof = tiny_keccak::Shake::v256();
+    xof.update(KDF_DST);
+    for input in &[ikm, ciphertext, info] {
+        xof.update(&(input.len() as u64).to_be_bytes());
+        xof.update(input);
+    }
+    xof.squeeze(out);
+    xof.wipe();
+}
+
+/// Deterministic random number generator based on SHAKE256.
+///
+/// Used to derive parameters reproducibly from a seed. The output depends only
+/// on the domain separation tag and the seed.
+///
+/// The sponge state allows recovering the seed, hence it is wiped when dropped.
+pub struct XofRng(tiny_keccak::Shake);
+
+impl XofRng {
+    pub fn new(dst: &[u8], seed: &[u8; 32]) -> Self {
+        use tiny_keccak::Hasher;
+
+        let mut xof = tiny_keccak::Shake::v256();
+        xof.update(&(dst.len() as u64).to_be_bytes());
+        xof.update(dst);
+        xof.update(seed);
+        XofRng(xof)
+    }
+}
+
+impl RngCore for XofRng {
+    fn next_u32(&mut self) -> u32 {
+        let mut buf = [0u8; 4];
+        self.fill_bytes(&mut buf);
+        u32::from_le_bytes(buf)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut buf = [0u8; 8];
+        self.fill_bytes(&mut buf);
+        u64::from_le_bytes(buf)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        use tiny_keccak::Xof;
+        self.0.squeeze(dest);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl CryptoRng for XofRng {}
+
+impl Wipe for XofRng {
+    fn wipe(&mut self) {
+        self.0.wipe();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for XofRng {
+    fn drop(&mut self) {
+        self.wipe();
+    }
+}
+
+/// Sequential decoder of a fixed-size byte representation from a slice of any length.
+///
+/// Reports the component that failed to decode, for use in `TryFrom<&[u8]>` implementations.
+pub struct Decoder<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Decoder<'a> {
+    /// Start decoding, checking that the input has exactly the expected length.
+    pub fn new(bytes: &'a [u8], expected: usize) -> Result<Self, Error> {
+        if bytes.len() != expected {
+            return Err(Error::InvalidLength {
+                expected,
+                actual: bytes.len(),
+            });
+        }
+        Ok(Decoder { bytes, offset: 0 })
+    }
+
+    /// Take the next `N` bytes.
+    pub fn bytes<const N: usize>(&mut self) -> &'a [u8; N] {
+        let res = &self.bytes[self.offset..self.offset + N];
+        self.offset += N;
+        <&[u8; N]>::try_from(res).unwrap()
+    }
+
+    /// Decode a point on G1, checking that it is in the prime order subgroup.
+    pub fn g1(&mut self, component: &'static str, index: Option<usize>) -> Result<G1Affine, Error> {
+        let p = self.g1_unchecked(component, index)?;
+        if !bool::from(p.is_torsion_free()) {
+            return Err(Error::NotInSubgroup { component, index });
+        }
+        Ok(p)
+    }
+
+    /// Decode a point on G1, without checking that it is in the prime order subgroup.
+    pub fn g1_unchecked(
+        &mut self,
+        component: &'static str,
+        index: Option<usize>,
+    ) -> Result<G1Affine, Error> {
+        Option::from(G1Affine::from_compressed_unchecked(self.bytes()))
+            .ok_or(Error::InvalidEncoding { component, index })
+    }
+
+    /// Decode a point on G2, checking that it is in the prime order subgroup.
+    pub fn g2(&mut self, component: &'static str, index: Option<usize>) -> Result<G2Affine, Error> {
+        let p = self.g2_unchecked(component, index)?;
+        if !bool::from(p.is_torsion_free()) {
+            return Err(Error::NotInSubgroup { component, index });
+        }
+        Ok(p)
+    }
+
+    /// Decode a point on G2, without checking that it is in the prime order subgroup.
+    pub fn g2_unchecked(
+        &mut self,
+        component: &'static str,
+        index: Option<usize>,
+    ) -> Result<G2Affine, Error> {
+        Option::from(G2Affine::from_compressed_unchecked(self.bytes()))
+            .ok_or(Error::InvalidEncoding { component, index })
+    }
+
+    /// Decode an element of the target group, checking that it is in the prime order subgroup.
+    pub fn gt(&mut self, component: &'static str) -> Result<Gt, Error> {
+        let bytes = self.bytes();
+        if bool::from(Gt::from_compressed_unchecked(bytes).is_none()) {
+            return Err(Error::InvalidEncoding {
+                component,
+                index: None,
+            });
+        }
+        Option::from(Gt::from_compressed(bytes)).ok_or(Error::NotInSubgroup {
+            component,
+            index: None,
+        })
+    }
+
+    /// Decode an element of the target group, without checking that it is in the prime order subgroup.
+    pub fn gt_unchecked(&mut self, component: &'static str) -> Result<Gt, Error> {
+        Option::from(Gt::from_compressed_unchecked(self.bytes())).ok_or(Error::InvalidEncoding {
+            component,
+            index: None,
+        })
+    }
+}
+
+/// Base64 representation of a byte slice, encoded while formatting to avoid allocation.
+#[cfg(feature = "serde")]
+struct Base64Display<'a>(&'a [u8]);
+
+#[cfg(feature = "serde")]
+impl core::fmt::Display for Base64Display<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        use base64ct::Encoding;
+
+        // Chunks of a multiple of three bytes encode without padding, hence can be concatenated.
+        let mut buf = [0u8; 64];
+        let res = self.0.chunks(48).try_for_each(|chunk| {
+            let encoded =
+                base64ct::Base64::encode(chunk, &mut buf).map_err(|_| core::fmt::Error)?;
+            f.write_str(encoded)
+        });
+        buf.wipe();
+
+        res
+    }
+}
+
+/// Serialize a byte representation, as a base64 string for human-readable formats.
+#[cfg(feature = "serde")]
+pub fn serialize_bytes<S: serde::Serializer>(
+    bytes: &[u8],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        serializer.collect_str(&Base64Display(bytes))
+    } else {
+        serializer.serialize_bytes(bytes)
+    }
+}
+
+/// Deserialize a byte representation of exactly `N` bytes, serialized using [`serialize_bytes`].
+///
+/// Errors do not include the input, as it might be secret.
+#[cfg(feature = "serde")]
+pub fn deserialize_bytes<'de, D: serde::Deserializer<'de>, const N: usize>(
+    deserializer: D,
+) -> Result<[u8; N], D::Error> {
+    use serde::de::{Error, SeqAccess, Visitor};
+
+    struct BytesVisitor<const N: usize>;
+
+    impl<'de, const N: usize> Visitor<'de> for BytesVisitor<N> {
+        type Value = [u8; N];
+
+        fn expecting(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            write!(f, "{} bytes or their base64 encoding", N)
+        }
+
+        fn visit_str<E: Error>(self, v: &str) -> Result<[u8; N], E> {
+            use base64ct::Encoding;
+
+            let mut res = [0u8; N];
+            match base64ct::Base64::decode(v, &mut res).map(|decoded| decoded.len()) {
+                Ok(len) if len == N => Ok(res),
+                decoded => {
+                    res.wipe();
+                    Err(match decoded {
+                        Ok(len) => E::invalid_length(len, &self),
+                        Err(_) => E::custom("invalid base64 encoding"),
+                    })
+                }
+            }
+        }
+
+        fn visit_bytes<E: Error>(self, v: &[u8]) -> Result<[u8; N], E> {
+            <[u8; N]>::try_from(v).map_err(|_| E::invalid_length(v.len(), &self))
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<[u8; N], A::Error> {
+            // Counts one element too many for overlong sequences.
+            fn read<'de, A: SeqAccess<'de>>(
+                seq: &mut A,
+                res: &mut [u8],
+            ) -> Result<usize, A::Error> {
+                for (i, ri) in res.iter_mut().enumerate() {
+                    match seq.next_element()? {
+                        Some(b) => *ri = b,
+                        None => return Ok(i),
+                    }
+                }
+                Ok(res.len() + seq.next_element::<u8>()?.map_or(0, |_| 1))
+            }
+
+            let mut res = [0u8; N];
+            match read(&mut seq, &mut res) {
+                Ok(len) if len == N => Ok(res),
+                read => {
+                    res.wipe();
+                    Err(match read {
+                        Ok(len) => A::Error::invalid_length(len, &self),
+                        Err(e) => e,
+                    })
+                }
+            }
+        }
+    }
+
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_str(BytesVisitor)
+    } else {
+        deserializer.deserialize_bytes(BytesVisitor)
+    }
+}
+
+/// Evaluate a polynomial with coefficients on G1, ordered from the constant term upwards, at `x`.
+#[cfg(feature = "alloc")]
+pub fn eval_poly_g1(coefficients: &[G1Projective], x: u32) -> G1Projective {
+    let x = Scalar::from(u64::from(x));
+    coefficients
+        .iter()
+        .rev()
+        .fold(G1Projective::identity(), |acc, c| acc * x + c)
+}
+
+/// Evaluate a polynomial with coefficients on G2, ordered from the constant term upwards, at `x`.
+#[cfg(feature = "alloc")]
+pub fn eval_poly_g2(coefficients: &[G2Projective], x: u32) -> G2Projective {
+    let x = Scalar::from(u64::from(x));
+    coefficients
+        .iter()
+        .rev()
+        .fold(G2Projective::identity(), |acc, c| acc * x + c)
+}
+
+/// Evaluate a polynomial with scalar coefficients, ordered from the constant term upwards, at `x`.
+#[cfg(feature = "alloc")]
+pub fn eval_poly_scalar(coefficients: &[Scalar], x: u32) -> Scalar {
+    let x = Scalar::from(u64::from(x));
+    coefficients
+        .iter()
+        .rev()
+        .fold(Scalar::zero(), |acc, c| acc * x + c)
+}
+
+/// Draw one random scalar per key of a batch, in order.
+///
+/// A batch hence uses the same randomness as extracting the keys one by one.
+#[cfg(feature = "alloc")]
+pub fn rand_scalars<R: RngCore + CryptoRng>(n: usize, rng: &mut R) -> alloc::vec::Vec<Scalar> {
+    (0..n).map(|_| rand_scalar(rng)).collect()
+}
+
+/// Apply `f` to every item of a batch along with its index, in parallel when the `rayon` feature is enabled.
+#[cfg(feature = "alloc")]
+pub fn batch_map<I, T, F>(items: &[I], f: F) -> alloc::vec::Vec<T>
+where
+    I: Sync,
+    T: Send,
+    F: Fn(usize, &I) -> T + Sync + Send,
+{
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        items.par_iter().enumerate().map(|(i, x)| f(i, x)).collect()
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    {
+        items.iter().enumerate().map(|(i, x)| f(i, x)).collect()
+    }
+}
+
+macro_rules! batch_normalize {
+    ($name:ident, $affine:ty, $projective:ty) => {
+        /// Convert points to affine coordinates using a single inversion, wiping the projective points.
+        #[cfg(feature = "alloc")]
+        pub fn $name(points: &mut [$projective]) -> alloc::vec::Vec<$affine> {
+            let mut res = alloc::vec![<$affine>::identity(); points.len()];
+            <$projective>::batch_normalize(points, &mut res);
+            points.wipe();
+            res
+        }
+    };
+}
+
+batch_normalize!(batch_normalize_g1, G1Affine, G1Projective);
+batch_normalize!(batch_normalize_g2, G2Affine, G2Projective);
+
+/// Whether a set of share indices is non-empty, excludes zero and contains no duplicates.
+pub fn valid_share_indices<I: Iterator<Item = u32> + Clone>(indices: I) -> bool {
+    indices.clone().next().is_some()
+        && indices
+            .clone()
+            .enumerate()
+            .all(|(n, i)| i != 0 && indices.clone().skip(n + 1).all(|j| i != j))
+}
+
+/// Lagrange coefficient of share index `i` to interpolate at zero over the given share indices.
+///
+/// The indices must satisfy [`valid_share_indices`] and contain `i`.
+pub fn lagrange_coefficient<I: Iterator<Item = u32>>(i: u32, indices: I) -> Scalar {
+    let xi = Scalar::from(u64::from(i));
+    let (num, den) =
+        indices
+            .filter(|&j| j != i)
+            .fold((Scalar::one(), Scalar::one()), |(num, den), j| {
+                let xj = Scalar::from(u64::from(j));
+                (num * xj, den * (xj - xi))
+            });
+
+    num * den.invert().unwrap()
+}
+
+/// Secret values that are wiped from memory when the `zeroize` feature is enabled.
+///
+/// Without the feature, wiping does nothing.
+pub trait Wipe {
+    fn wipe(&mut self);
+}
+
+macro_rules! impl_wipe {
+    ($($t:ty),+) => {
+        $(impl Wipe for $t {
+            fn wipe(&mut self) {
+                #[cfg(feature = "zeroize")]
+                zeroize::Zeroize::zeroize(self);
+            }
+        })+
+    };
+}
+
+impl_wipe!(u8, Scalar, G1Affine, G1Projective, G2Affine, G2Projective);
+
+impl Wipe for Gt {
+    fn wipe(&mut self) {
+        // Gt does not implement Zeroize, hence it is overwritten with the identity instead.
+        #[cfg(feature = "zeroize")]
+        {
+            // Safety: self is a valid, aligned and exclusive reference.
+            unsafe { core::ptr::write_volatile(self, Gt::identity()) };
+            core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+        }
+    }
+}
+
+impl Wipe for tiny_keccak::Shake {
+    fn wipe(&mut self) {
+        // Shake does not implement Zeroize, hence it is overwritten with a fresh sponge instead.
+        #[cfg(feature = "zeroize")]
+        {
+            // Safety: self is a valid, aligned and exclusive reference, and Shake does not implement Drop.
+            unsafe { core::ptr::write_volatile(self, tiny_keccak::Shake::v256()) };
+            core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+        }
+    }
+}
+
+impl<T: Wipe, const N: usize> Wipe for [T; N] {
+    fn wipe(&mut self) {
+        for x in self.iter_mut() {
+            x.wipe();
+        }
+    }
+}
+
+impl<T: Wipe> Wipe for [T] {
+    fn wipe(&mut self) {
+        for x in self.iter_mut() {
+            x.wipe();
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Wipe> Wipe for alloc::vec::Vec<T> {
+    fn wipe(&mut self) {
+        for x in self.iter_mut() {
+            x.wipe();
+        }
+    }
+}
+
+impl<T: Wipe> Wipe for Option<T> {
+    fn wipe(&mut self) {
+        if let Some(x) = self {
+            x.wipe();
+        }
+    }
+}
+
+/// Implement `Wipe`, and when the `zeroize` feature is enabled `Zeroize` and `ZeroizeOnDrop`,
+/// for a secret type by wiping the given fields.
+///
+/// The `=>` form is meant for types whose fields already wipe themselves on drop,
+/// and hence does not implement `Drop`.
+macro_rules! zeroize_on_drop {
+    ($t:ty: $($field:tt),+) => {
+        zeroize_on_drop!($t => $($field),+);
+
+        #[cfg(feature = "zeroize")]
+        impl Drop for $t {
+            fn drop(&mut self) {
+                crate::util::Wipe::wipe(self);
+            }
+        }
+    };
+    ($t:ty => $($field:tt),+) => {
+        impl crate::util::Wipe for $t {
+            fn wipe(&mut self) {
+                $(crate::util::Wipe::wipe(&mut self.$field);)+
+            }
+        }
+
+        #[cfg(feature = "zeroize")]
+        impl zeroize::Zeroize for $t {
+            fn zeroize(&mut self) {
+                crate::util::Wipe::wipe(self);
+            }
+        }
+
+        #[cfg(feature = "zeroize")]
+        impl zeroize::ZeroizeOnDrop for $t {}
+    };
+}
+
+/// Size of the fingerprint of a secret value, in bytes.
+pub const FINGERPRINT_SIZE: usize = 8;
+
+const FINGERPRINT_DST: &[u8] = b"ibe-fingerprint";
+
+/// Non-secret fingerprint of the byte representation of a secret value.
+pub fn fingerprint(bytes: &[u8]) -> [u8; FINGERPRINT_SIZE] {
+    let hash = sha3_256_multi(&[FINGERPRINT_DST, bytes]);
+    *arrayref::array_ref![hash, 0, FINGERPRINT_SIZE]
+}
+
+/// Write a redacted debug representation, showing only the fingerprint.
+pub fn fmt_redacted(
+    f: &mut core::fmt::Formatter<'_>,
+    name: &str,
+    fingerprint: &[u8; FINGERPRINT_SIZE],
+) -> core::fmt::Result {
+    struct Hex<'a>(&'a [u8]);
+
+    impl core::fmt::Debug for Hex<'_> {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            self.0.iter().try_for_each(|b| write!(f, "{:02x}", b))
+        }
+    }
+
+    f.debug_struct(name)
+        .field("fingerprint", &Hex(fingerprint))
+        .finish_non_exhaustive()
+}
+
+/// Implement constant-time equality and a redacted `Debug` for a secret type,
+/// comparing the given fields and printing only the fingerprint of its byte representation.
+macro_rules! impl_secret {
+    ($t:ident: $($field:tt),+) => {
+        impl subtle::ConstantTimeEq for $t {
+            fn ct_eq(&self, other: &Self) -> subtle::Choice {
+                // Method syntax, such that arrays are compared as slices.
+                #[allow(unused_imports)]
+                use subtle::ConstantTimeEq as _;
+                subtle::Choice::from(1u8) $(& self.$field.ct_eq(&other.$field))+
+            }
+        }
+
+        impl PartialEq for $t {
+            fn eq(&self, other: &Self) -> bool {
+                subtle::ConstantTimeEq::ct_eq(self, other).into()
+            }
+        }
+
+        impl Eq for $t {}
+
+        impl core::fmt::Debug for $t {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                crate::util::fmt_redacted(f, stringify!($t), &self.fingerprint())
+            }
+        }
+
+        impl $t {
+            /// Non-secret fingerprint of this value, for diagnostics.
+            ///
+            /// Equal values have equal fingerprints.
+            pub fn fingerprint(&self) -> [u8; crate::util::FINGERPRINT_SIZE] {
+                crate::util::fingerprint(&self.to_bytes())
+            }
+        }
+    };
+}
+
+/// Implement `TryFrom<&[u8]>` for a secret type, decoding it in constant time using `from_bytes`.
+///
+/// Apart from length mismatches, only reports that the input is invalid, not which component.
+macro_rules! impl_try_from_secret {
+    ($t:ident, $size:expr) => {
+        impl core::convert::TryFrom<&[u8]> for $t {
+            type Error = crate::Error;
+
+            fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+                let bytes = crate::util::Decoder::new(bytes, $size)?.bytes::<{ $size }>();
+                Option::from($t::from_bytes(bytes)).ok_or(crate::Error::InvalidSecret)
+            }
+        }
+    };
+}
+
+/// Implement `Serialize` and `Deserialize` when the `serde` feature is enabled,
+/// based on the fixed-size byte representation of `to_bytes` and `from_bytes`.
+///
+/// Human-readable formats use a base64 string, binary formats the bytes themselves.
+macro_rules! impl_serde {
+    ($t:ident, $size:expr) => {
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for $t {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let mut bytes = self.to_bytes();
+                let res = crate::util::serialize_bytes(&bytes, serializer);
+                crate::util::Wipe::wipe(&mut bytes);
+
+                res
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> serde::Deserialize<'de> for $t {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let mut bytes = crate::util::deserialize_bytes::<D, { $size }>(deserializer)?;
+                let res = Option::from($t::from_bytes(&bytes));
+                crate::util::Wipe::wipe(&mut bytes);
+
+                res.ok_or_else(|| {
+                    serde::de::Error::custom(concat!("invalid encoding of ", stringify!($t)))
+                })
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kdf_separates_inputs() {
+        let derive = |ikm: &[u8], c: &[u8], info: &[u8]| {
+            let mut out = [0u8; 32];
+            kdf(ikm, c, info, &mut out);
+            out
+        };
+
+        let key = derive(b"ikm", b"ciphertext", b"info");
+        assert_eq!(key, derive(b"ikm", b"ciphertext", b"info"));
+        assert_ne!(key, derive(b"ikm", b"ciphertext", b"other"));
+        assert_ne!(key, derive(b"ikm", b"other", b"info"));
+        assert_ne!(key, derive(b"other", b"ciphertext", b"info"));
+
+        // Moving bytes across input boundaries changes the key, as the inputs are length-prefixed.
+        assert_ne!(key, derive(b"ikmc", b"iphertext", b"info"));
+
+        // Shorter outputs are prefixes of longer ones.
+        let mut long = [0u8; 64];
+        kdf(b"ikm", b"ciphertext", b"info", &mut long);
+        assert_eq!(key[..], long[..32]);
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn wiped_xof_rng_forgets_seed() {
+        use tiny_keccak::Xof;
+
+        let mut rng = XofRng::new(b"test", &[7u8; 32]);
+        rng.wipe();
+
+        let (mut wiped, mut fresh) = ([0u8; 32], [0u8; 32]);
+        rng.fill_bytes(&mut wiped);
+        tiny_keccak::Shake::v256().squeeze(&mut fresh);
+        assert_eq!(wiped, fresh);
+    }
 }
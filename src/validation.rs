@@ -0,0 +1,192 @@
+//! Validation of public keys obtained from untrusted sources.
+//!
+//! Deserializing a public key only checks that its components are valid encodings,
+//! and in some schemes skips the subgroup checks altogether.
+//! The `PublicKey::validate` method of every scheme additionally checks that each point is in the
+//! prime order subgroup, that no point is the identity, and, where the scheme allows it,
+//! that the components are consistent with each other.
+
+use irmaseal_curve::{G1Affine, G2Affine, Gt, Scalar};
+
+/// What is wrong with a public key component.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValidationErrorKind {
+    /// The element is not in the prime order subgroup.
+    NotInSubgroup,
+    /// The element is the identity, which makes the key degenerate.
+    Identity,
+    /// The element is not consistent with the other components of the key.
+    Inconsistent,
+}
+
+/// Reason why a public key failed validation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ValidationError {
+    component: &'static str,
+    index: Option<usize>,
+    kind: ValidationErrorKind,
+}
+
+impl ValidationError {
+    pub(crate) fn new(
+        component: &'static str,
+        index: Option<usize>,
+        kind: ValidationErrorKind,
+    ) -> Self {
+        ValidationError {
+            component,
+            index,
+            kind,
+        }
+    }
+
+    /// Name of the public key component that failed validation.
+    pub fn component(&self) -> &'static str {
+        self.component
+    }
+
+    /// Position of the failing element, for components consisting of multiple elements.
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+
+    /// What is wrong with the component.
+    pub fn kind(&self) -> ValidationErrorKind {
+        self.kind
+    }
+}
+
+impl core::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("invalid public key component ")?;
+        match self.index {
+            Some(i) => write!(f, "{}[{}]", self.component, i)?,
+            None => f.write_str(self.component)?,
+        }
+        f.write_str(match self.kind {
+            ValidationErrorKind::NotInSubgroup => ": not in the prime order subgroup",
+            ValidationErrorKind::Identity => ": identity element",
+            ValidationErrorKind::Inconsistent => ": inconsistent with the other components",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ValidationError {}
+
+/// Check that a point on G1 is in the prime order subgroup and not the identity.
+pub(crate) fn check_g1(
+    p: &G1Affine,
+    component: &'static str,
+    index: Option<usize>,
+) -> Result<(), ValidationError> {
+    if !bool::from(p.is_torsion_free()) {
+        return Err(ValidationError::new(
+            component,
+            index,
+            ValidationErrorKind::NotInSubgroup,
+        ));
+    }
+    if bool::from(p.is_identity()) {
+        return Err(ValidationError::new(
+            component,
+            index,
+            ValidationErrorKind::Identity,
+        ));
+    }
+    Ok(())
+}
+
+/// Check that a point on G2 is in the prime order subgroup and not the identity.
+pub(crate) fn check_g2(
+    p: &G2Affine,
+    component: &'static str,
+    index: Option<usize>,
+) -> Result<(), ValidationError> {
+    if !bool::from(p.is_torsion_free()) {
+        return Err(ValidationError::new(
+            component,
+            index,
+            ValidationErrorKind::NotInSubgroup,
+        ));
+    }
+    if bool::from(p.is_identity()) {
+        return Err(ValidationError::new(
+            component,
+            index,
+            ValidationErrorKind::Identity,
+        ));
+    }
+    Ok(())
+}
+
+/// Check that an element of the target group is in the prime order subgroup and not the identity.
+pub(crate) fn check_gt(x: &Gt, component: &'static str) -> Result<(), ValidationError> {
+    // The scalar -1 is r - 1, hence this computes x^r, which is the identity only within the subgroup.
+    if x * -Scalar::one() + x != Gt::identity() {
+        return Err(ValidationError::new(
+            component,
+            None,
+            ValidationErrorKind::NotInSubgroup,
+        ));
+    }
+    if *x == Gt::identity() {
+        return Err(ValidationError::new(
+            component,
+            None,
+            ValidationErrorKind::Identity,
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Find a point on the curve that is not in the prime order subgroup.
+    fn non_subgroup_g1() -> G1Affine {
+        (0u8..)
+            .find_map(|i| {
+                let mut bytes = [0u8; 48];
+                bytes[0] = 0x80;
+                bytes[47] = i;
+                Option::from(G1Affine::from_compressed_unchecked(&bytes))
+                    .filter(|p: &G1Affine| !bool::from(p.is_torsion_free()))
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn check_points() {
+        assert_eq!(check_g1(&G1Affine::generator(), "g", None), Ok(()));
+        assert_eq!(
+            check_g1(&G1Affine::identity(), "h", Some(3)),
+            Err(ValidationError::new(
+                "h",
+                Some(3),
+                ValidationErrorKind::Identity
+            ))
+        );
+        assert_eq!(
+            check_g1(&non_subgroup_g1(), "u", None).unwrap_err().kind(),
+            ValidationErrorKind::NotInSubgroup
+        );
+
+        let gt = irmaseal_curve::pairing(&G1Affine::generator(), &G2Affine::generator());
+        assert_eq!(check_gt(&gt, "z"), Ok(()));
+        assert_eq!(
+            check_gt(&Gt::identity(), "z").unwrap_err().kind(),
+            ValidationErrorKind::Identity
+        );
+    }
+
+    #[test]
+    fn display() {
+        let err = ValidationError::new("h", Some(3), ValidationErrorKind::Identity);
+        assert_eq!(
+            format!("{}", err),
+            "invalid public key component h[3]: identity element"
+        );
+    }
+}
@@ -9,11 +9,22 @@
 //! All operations in this library are implemented to run in constant time.
 
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
-use rand::Rng;
-use subtle::{Choice, ConditionallySelectable, CtOption};
+use rand::{CryptoRng, RngCore};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption};
 
+use crate::compact::Expand;
+#[cfg(feature = "alloc")]
+use crate::table::{G1Table, G2Table, SCALAR_WINDOWS};
 use crate::util::*;
-use irmaseal_curve::{G1Affine, G1Projective, G2Affine, Gt};
+use crate::validation::*;
+use crate::{Compress, DerandomizedIBE, Derive, Error, IBE, IBKEM};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use irmaseal_curve::{multi_miller_loop, G1Affine, G1Projective, G2Affine, G2Prepared, Gt, Scalar};
+
+pub mod blind;
+pub mod threshold;
 
 const HASH_BIT_LEN: usize = 256;
 const HASH_BYTE_LEN: usize = HASH_BIT_LEN / 8;
@@ -22,6 +33,10 @@ const CHUNKS: usize = HASH_BIT_LEN;
 
 const PARAMETERSIZE: usize = CHUNKS * 48;
 const PUBLICKEYSIZE: usize = 2 * 48 + 2 * 96 + PARAMETERSIZE;
+const COMPACTPUBLICKEYSIZE: usize = 32 + 2 * 48 + 2 * 96;
+
+const SEED_DST: &[u8] = b"ibe-waters-setup-seed";
+const PARAMETERS_DST: &[u8] = b"ibe-waters-parameters-BLS12381G1_XOF:SHAKE-256_SSWU_RO_";
 
 /// Public key parameters used for entanglement with identities.
 struct Parameters([G1Affine; CHUNKS]);
@@ -36,19 +51,52 @@ pub struct PublicKey {
     u: Parameters,
 }
 
-/// Secret key parameter generated by the PKG used to extract user secret keys.
+/// Public key of which the parameters are derived from a public seed, see [`setup_compact`].
+///
+/// Must be expanded to a [`PublicKey`] before use.
 #[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CompactPublicKey {
+    seed: [u8; 32],
+    g: G2Affine,
+    g1: G1Affine,
+    g2: G2Affine,
+    uprime: G1Affine,
+}
+
+/// Secret key parameter generated by the PKG used to extract user secret keys.
+#[derive(Clone)]
 pub struct SecretKey {
     g1prime: G1Affine,
 }
 
+zeroize_on_drop!(SecretKey: g1prime);
+impl_secret!(SecretKey: g1prime);
+impl_try_from_secret!(SecretKey, 48);
+
 /// Points on the paired curves that form the user secret key.
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone)]
 pub struct UserSecretKey {
     d1: G1Affine,
     d2: G2Affine,
 }
 
+zeroize_on_drop!(UserSecretKey: d1, d2);
+impl_secret!(UserSecretKey: d1, d2);
+impl_try_from_secret!(UserSecretKey, 144);
+
+/// User secret key with the precomputed line functions of `d2`, for faster repeated decryption.
+///
+/// When the `zeroize` feature is enabled, only the contained user secret key is wiped when dropped,
+/// as the line functions cannot be wiped. These only depend on `d2`, which does not allow decryption by itself.
+///
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+#[derive(Clone)]
+pub struct PreparedUserSecretKey {
+    usk: UserSecretKey,
+    d2: G2Prepared,
+}
+
 /// Field parameters for an identity.
 ///
 /// Effectively a hash of an identity, mapped to the curve field.
@@ -57,10 +105,27 @@ pub struct Identity([u8; HASH_BYTE_LEN]);
 
 /// A point on the paired curve that can be encrypted and decrypted.
 ///
-/// You can use the byte representation to derive an AES key.
-#[derive(Clone, Copy, Debug, PartialEq)]
+/// Use [`derive_key`](Self::derive_key) to derive symmetric keys, such as for AES.
+#[derive(Clone)]
 pub struct Message(Gt);
 
+zeroize_on_drop!(Message: 0);
+impl_secret!(Message: 0);
+
+/// Public key with precomputed tables, for faster extraction and encryption using a long-lived public key.
+///
+/// Contains a fixed-base table for `g`, the sums of all subsets of every four parameters
+/// used for entanglement, and the pairing of `g1` and `g2`. Takes about 300 KiB of memory.
+///
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+pub struct PreparedPublicKey {
+    pk: PublicKey,
+    g: G2Table,
+    u: G1Table,
+    z: Gt,
+}
+
 /// Encrypted message. Can only be decrypted with an user secret key.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct CipherText {
@@ -69,11 +134,18 @@ pub struct CipherText {
     c3: G1Affine,
 }
 
+/// The Waters identity-based encryption scheme.
+///
+/// Implements [`IBE`] on top of the functions in this module,
+/// and [`IBKEM`] by encrypting a randomly generated [`Message`].
+#[derive(Clone, Copy, Debug)]
+pub struct Waters;
+
 /// Generate a keypair used by the Private Key Generator (PKG).
-pub fn setup<R: Rng>(rng: &mut R) -> (PublicKey, SecretKey) {
+pub fn setup<R: RngCore + CryptoRng>(rng: &mut R) -> (PublicKey, SecretKey) {
     let g: G2Affine = rand_g2(rng).into();
 
-    let alpha = rand_scalar(rng);
+    let mut alpha = rand_scalar(rng);
     let g2 = (g * alpha).into();
 
     let g1 = rand_g1(rng).into();
@@ -93,6 +165,46 @@ pub fn setup<R: Rng>(rng: &mut R) -> (PublicKey, SecretKey) {
     };
 
     let g1prime: G1Affine = (g1 * alpha).into();
+    alpha.wipe();
+
+    let sk = SecretKey { g1prime };
+
+    (pk, sk)
+}
+
+/// Deterministically derive a keypair for the PKG from a seed.
+///
+/// The same seed always results in the same keypair, hence the seed must be kept as secret as the master secret key.
+pub fn setup_from_seed(seed: &[u8; 32]) -> (PublicKey, SecretKey) {
+    setup(&mut XofRng::new(SEED_DST, seed))
+}
+
+/// Generate a keypair used by the Private Key Generator (PKG), with a compact public key.
+///
+/// The parameters used for entanglement with identities are derived by hash-to-curve from a random public seed,
+/// such that their discrete logarithms are unknown to anyone, including the PKG.
+pub fn setup_compact<R: RngCore + CryptoRng>(rng: &mut R) -> (CompactPublicKey, SecretKey) {
+    let mut seed = [0u8; 32];
+    rng.fill_bytes(&mut seed);
+
+    let g: G2Affine = rand_g2(rng).into();
+
+    let mut alpha = rand_scalar(rng);
+    let g2 = (g * alpha).into();
+
+    let g1 = rand_g1(rng).into();
+    let uprime = rand_g1(rng).into();
+
+    let pk = CompactPublicKey {
+        seed,
+        g,
+        g1,
+        g2,
+        uprime,
+    };
+
+    let g1prime: G1Affine = (g1 * alpha).into();
+    alpha.wipe();
 
     let sk = SecretKey { g1prime };
 
@@ -110,24 +222,37 @@ fn entangle(pk: &PublicKey, v: &Identity) -> G1Projective {
 }
 
 /// Extract an user secret key for a given identity.
-pub fn extract_usk<R: Rng>(
+pub fn extract_usk<R: RngCore + CryptoRng>(
     pk: &PublicKey,
     sk: &SecretKey,
     v: &Identity,
     rng: &mut R,
 ) -> UserSecretKey {
-    let r = rand_scalar(rng);
+    let mut r = rand_scalar(rng);
     let ucoll = entangle(pk, v);
     let d1 = (sk.g1prime + (ucoll * r)).into();
     let d2 = (pk.g * r).into();
+    r.wipe();
 
     UserSecretKey { d1, d2 }
 }
 
 /// Encrypt a message using the PKG public key and an identity.
-pub fn encrypt<R: Rng>(pk: &PublicKey, v: &Identity, m: &Message, rng: &mut R) -> CipherText {
-    let t = rand_scalar(rng);
+pub fn encrypt<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    v: &Identity,
+    m: &Message,
+    rng: &mut R,
+) -> CipherText {
+    let mut t = rand_scalar(rng);
+    let c = encrypt_with_randomness(pk, v, m, &t);
+    t.wipe();
+
+    c
+}
 
+/// Encrypt a message using the given randomness.
+fn encrypt_with_randomness(pk: &PublicKey, v: &Identity, m: &Message, t: &Scalar) -> CipherText {
     let c3coll = entangle(pk, v);
     let c1 = irmaseal_curve::pairing(&pk.g1, &pk.g2) * t + m.0;
     let c2 = (pk.g * t).into();
@@ -138,14 +263,127 @@ pub fn encrypt<R: Rng>(pk: &PublicKey, v: &Identity, m: &Message, rng: &mut R) -
 
 /// Decrypt ciphertext to a message using a user secret key.
 pub fn decrypt(usk: &UserSecretKey, c: &CipherText) -> Message {
-    let num = irmaseal_curve::pairing(&c.c3, &usk.d2);
-    let dem = irmaseal_curve::pairing(&usk.d1, &c.c2);
+    decrypt_with(usk, &G2Prepared::from(usk.d2), c)
+}
 
-    let m = c.c1 + num - dem;
+/// Decrypt ciphertext to a message using a prepared user secret key.
+///
+/// Results in the same message as [`decrypt`].
+#[cfg(feature = "alloc")]
+pub fn decrypt_prepared(pusk: &PreparedUserSecretKey, c: &CipherText) -> Message {
+    decrypt_with(&pusk.usk, &pusk.d2, c)
+}
+
+/// Compute `c1 + e(c3, d2) - e(d1, c2)` using a single Miller loop and final exponentiation.
+fn decrypt_with(usk: &UserSecretKey, d2: &G2Prepared, c: &CipherText) -> Message {
+    let c2 = G2Prepared::from(-c.c2);
+    let m = c.c1 + multi_miller_loop(&[(&c.c3, d2), (&usk.d1, &c2)]).final_exponentiation();
     Message(m)
 }
 
+/// Extract an user secret key for a given identity using a prepared public key.
+///
+/// Results in the same key as [`extract_usk`] given the same randomness.
+#[cfg(feature = "alloc")]
+pub fn extract_usk_prepared<R: RngCore + CryptoRng>(
+    ppk: &PreparedPublicKey,
+    sk: &SecretKey,
+    v: &Identity,
+    rng: &mut R,
+) -> UserSecretKey {
+    let mut r = rand_scalar(rng);
+    let ucoll = ppk.entangle(v);
+    let d1 = (sk.g1prime + (ucoll * r)).into();
+    let d2 = ppk.g.mul(&r).into();
+    r.wipe();
+
+    UserSecretKey { d1, d2 }
+}
+
+/// Extract user secret keys for many identities at once.
+///
+/// Prepares the public key once for the entire batch, and converts all keys to affine coordinates
+/// using a single inversion. Extracts the keys in parallel when the `rayon` feature is enabled.
+/// Results in the same keys as calling [`extract_usk`] for every identity in order, given the same randomness.
+///
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+pub fn extract_usk_batch<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    sk: &SecretKey,
+    ids: &[Identity],
+    rng: &mut R,
+) -> Vec<UserSecretKey> {
+    let ppk = PreparedPublicKey::new(pk);
+    let mut rs = rand_scalars(ids.len(), rng);
+
+    let mut d1 = batch_map(ids, |i, v| sk.g1prime + ppk.entangle(v) * rs[i]);
+    let mut d2 = batch_map(&rs, |_, r| ppk.g.mul(r));
+    rs.wipe();
+
+    let mut d1 = batch_normalize_g1(&mut d1);
+    let mut d2 = batch_normalize_g2(&mut d2);
+    let res = d1
+        .iter()
+        .zip(d2.iter())
+        .map(|(d1, d2)| UserSecretKey { d1: *d1, d2: *d2 })
+        .collect();
+    d1.wipe();
+    d2.wipe();
+
+    res
+}
+
+/// Encrypt a message using a prepared public key and an identity.
+///
+/// Results in the same ciphertext as [`encrypt`] given the same randomness.
+#[cfg(feature = "alloc")]
+pub fn encrypt_prepared<R: RngCore + CryptoRng>(
+    ppk: &PreparedPublicKey,
+    v: &Identity,
+    m: &Message,
+    rng: &mut R,
+) -> CipherText {
+    let mut t = rand_scalar(rng);
+    let c3coll = ppk.entangle(v);
+    let c1 = ppk.z * t + m.0;
+    let c2 = ppk.g.mul(&t).into();
+    let c3 = (c3coll * t).into();
+    t.wipe();
+
+    CipherText { c1, c2, c3 }
+}
+
+/// Verify that a user secret key is valid for the given identity.
+///
+/// Checks the key against the public key using pairings, without requiring a ciphertext.
+pub fn verify_usk(pk: &PublicKey, v: &Identity, usk: &UserSecretKey) -> Choice {
+    verify_usk_with(pk, v, usk, &irmaseal_curve::pairing(&pk.g1, &pk.g2))
+}
+
+/// Verify a user secret key against the pairing of its master secret key component with `g`.
+fn verify_usk_with(pk: &PublicKey, v: &Identity, usk: &UserSecretKey, target: &Gt) -> Choice {
+    let ucoll: G1Affine = entangle(pk, v).into();
+    let lhs = irmaseal_curve::pairing(&usk.d1, &pk.g) - irmaseal_curve::pairing(&ucoll, &usk.d2);
+
+    lhs.ct_eq(target)
+}
+
 impl PublicKey {
+    /// Validate a public key obtained from an untrusted source.
+    ///
+    /// Checks that all points are in the prime order subgroup and that none of them is the identity.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        check_g2(&self.g, "g", None)?;
+        check_g1(&self.g1, "g1", None)?;
+        check_g2(&self.g2, "g2", None)?;
+        check_g1(&self.uprime, "uprime", None)?;
+        for (i, ui) in self.u.0.iter().enumerate() {
+            check_g1(ui, "u", Some(i))?;
+        }
+        Ok(())
+    }
+
     pub fn to_bytes(&self) -> [u8; PUBLICKEYSIZE] {
         let mut res = [0u8; PUBLICKEYSIZE];
         let (g, g1, g2, uprime, u) = mut_array_refs![&mut res, 96, 48, 96, 48, PARAMETERSIZE];
@@ -184,6 +422,94 @@ impl PublicKey {
     }
 }
 
+#[cfg(feature = "alloc")]
+impl PreparedUserSecretKey {
+    /// Precompute the line functions of a user secret key.
+    pub fn new(usk: &UserSecretKey) -> Self {
+        PreparedUserSecretKey {
+            usk: usk.clone(),
+            d2: G2Prepared::from(usk.d2),
+        }
+    }
+
+    /// The user secret key of which the line functions were precomputed.
+    pub fn user_secret_key(&self) -> &UserSecretKey {
+        &self.usk
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl PreparedPublicKey {
+    /// Precompute the tables for a public key.
+    pub fn new(pk: &PublicKey) -> Self {
+        PreparedPublicKey {
+            pk: *pk,
+            g: G2Table::fixed_base(&pk.g, SCALAR_WINDOWS),
+            u: G1Table::subset_sums(&pk.u.0),
+            z: irmaseal_curve::pairing(&pk.g1, &pk.g2),
+        }
+    }
+
+    /// The public key of which the tables were precomputed.
+    pub fn public_key(&self) -> &PublicKey {
+        &self.pk
+    }
+
+    /// Entangle the public key with an identity, as [`entangle`] does.
+    fn entangle(&self, v: &Identity) -> G1Projective {
+        self.pk.uprime + self.u.sum_bits(bits(&v.0))
+    }
+}
+
+impl CompactPublicKey {
+    /// Derive the full public key, recomputing the parameters from the seed.
+    ///
+    /// This is relatively expensive, hence the result should be kept around for repeated use.
+    pub fn expand(&self) -> PublicKey {
+        PublicKey {
+            g: self.g,
+            g1: self.g1,
+            g2: self.g2,
+            uprime: self.uprime,
+            u: Parameters::derive(&self.seed),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; COMPACTPUBLICKEYSIZE] {
+        let mut res = [0u8; COMPACTPUBLICKEYSIZE];
+        let (seed, g, g1, g2, uprime) = mut_array_refs![&mut res, 32, 96, 48, 96, 48];
+        *seed = self.seed;
+        *g = self.g.to_compressed();
+        *g1 = self.g1.to_compressed();
+        *g2 = self.g2.to_compressed();
+        *uprime = self.uprime.to_compressed();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; COMPACTPUBLICKEYSIZE]) -> CtOption<Self> {
+        let (seed, g, g1, g2, uprime) = array_refs![bytes, 32, 96, 48, 96, 48];
+
+        let g = G2Affine::from_compressed(g);
+        let g1 = G1Affine::from_compressed(g1);
+        let g2 = G2Affine::from_compressed(g2);
+        let uprime = G1Affine::from_compressed(uprime);
+
+        g.and_then(|g| {
+            g1.and_then(|g1| {
+                g2.and_then(|g2| {
+                    uprime.map(|uprime| CompactPublicKey {
+                        seed: *seed,
+                        g,
+                        g1,
+                        g2,
+                        uprime,
+                    })
+                })
+            })
+        })
+    }
+}
+
 impl SecretKey {
     pub fn to_bytes(&self) -> [u8; 48] {
         self.g1prime.to_compressed()
@@ -215,7 +541,7 @@ impl UserSecretKey {
 
 impl Message {
     /// Generate a random point on the paired curve.
-    pub fn generate<R: Rng>(rng: &mut R) -> Self {
+    pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
         Self(rand_gt(rng))
     }
 
@@ -226,13 +552,28 @@ impl Message {
     pub fn from_bytes(bytes: &[u8; 288]) -> CtOption<Self> {
         Gt::from_compressed(bytes).map(Message)
     }
+
+    /// Derive symmetric key material bound to `c` and `info`, see [key derivation](crate#key-derivation).
+    pub fn derive_key(&self, c: &CipherText, info: &[u8], out: &mut [u8]) {
+        kdf(&self.to_bytes(), &c.to_bytes(), info, out)
+    }
 }
 
 impl Parameters {
+    /// Derive the parameters from a public seed using hash-to-curve.
+    fn derive(seed: &[u8; 32]) -> Self {
+        let mut res = [G1Affine::default(); CHUNKS];
+        for (i, ui) in (0u32..).zip(res.iter_mut()) {
+            *ui = hash_to_g1(&seeded_parameter_input(seed, i), PARAMETERS_DST).into();
+        }
+        Parameters(res)
+    }
+
+    #[allow(clippy::wrong_self_convention)]
     pub fn to_bytes(&self) -> [u8; PARAMETERSIZE] {
         let mut res = [0u8; PARAMETERSIZE];
-        for i in 0..CHUNKS {
-            *array_mut_ref![&mut res, i * 48, 48] = self.0[i].to_compressed();
+        for (i, ui) in self.0.iter().enumerate() {
+            *array_mut_ref![&mut res, i * 48, 48] = ui.to_compressed();
         }
         res
     }
@@ -240,10 +581,10 @@ impl Parameters {
     pub fn from_bytes(bytes: &[u8; PARAMETERSIZE]) -> CtOption<Self> {
         let mut res = [G1Affine::default(); CHUNKS];
         let mut is_some = Choice::from(1u8);
-        for i in 0..CHUNKS {
+        for (i, ri) in res.iter_mut().enumerate() {
             is_some &= G1Affine::from_compressed(array_ref![bytes, i * 48, 48])
                 .map(|s| {
-                    res[i] = s;
+                    *ri = s;
                 })
                 .is_some();
         }
@@ -255,7 +596,7 @@ impl ConditionallySelectable for Parameters {
     fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
         let mut res = [G1Affine::default(); CHUNKS];
         for (i, (ai, bi)) in a.0.iter().zip(b.0.iter()).enumerate() {
-            res[i] = G1Affine::conditional_select(&ai, &bi, choice);
+            res[i] = G1Affine::conditional_select(ai, bi, choice);
         }
         Parameters(res)
     }
@@ -263,11 +604,7 @@ impl ConditionallySelectable for Parameters {
 
 impl Clone for Parameters {
     fn clone(&self) -> Self {
-        let mut res = [G1Affine::default(); CHUNKS];
-        for (src, dst) in self.0.iter().zip(res.as_mut().iter_mut()) {
-            *dst = *src;
-        }
-        Parameters(res)
+        *self
     }
 }
 
@@ -297,15 +634,34 @@ impl Identity {
     pub fn derive_str(s: &str) -> Identity {
         Self::derive(s.as_bytes())
     }
+
+    /// Hash an attribute to a set of Identity parameters, such that the identity starts with
+    /// the [`attribute_tag`](blind::attribute_tag) of the attribute type.
+    ///
+    /// Required for [blind extraction](blind), which reveals the tag to the PKG.
+    pub fn derive_attribute(attribute_type: &[u8], value: &[u8]) -> Identity {
+        let mut hash = sha3_256_multi(&[
+            &(attribute_type.len() as u64).to_be_bytes(),
+            attribute_type,
+            value,
+        ]);
+        *array_mut_ref![hash, 0, blind::TAG_BYTE_LEN] = blind::attribute_tag(attribute_type);
+
+        Identity(hash)
+    }
+
+    pub fn to_bytes(&self) -> [u8; HASH_BYTE_LEN] {
+        self.0
+    }
+
+    pub fn from_bytes(bytes: &[u8; HASH_BYTE_LEN]) -> CtOption<Self> {
+        CtOption::new(Identity(*bytes), 1u8.into())
+    }
 }
 
 impl Clone for Identity {
     fn clone(&self) -> Self {
-        let mut res = [u8::default(); HASH_BYTE_LEN];
-        for (src, dst) in self.0.iter().zip(res.as_mut().iter_mut()) {
-            *dst = *src;
-        }
-        Identity(res)
+        *self
     }
 }
 
@@ -332,11 +688,256 @@ impl CipherText {
     }
 }
 
+impl Derive for Identity {
+    fn derive(b: &[u8]) -> Identity {
+        Identity::derive(b)
+    }
+}
+
+impl IBE for Waters {
+    type PublicKey = PublicKey;
+    type SecretKey = SecretKey;
+    type UserSecretKey = UserSecretKey;
+    type Identity = Identity;
+    type Message = Message;
+    type CipherText = CipherText;
+
+    fn setup<R: RngCore + CryptoRng>(rng: &mut R) -> (PublicKey, SecretKey) {
+        setup(rng)
+    }
+
+    fn extract_usk<R: RngCore + CryptoRng>(
+        pk: &PublicKey,
+        sk: &SecretKey,
+        v: &Identity,
+        rng: &mut R,
+    ) -> UserSecretKey {
+        extract_usk(pk, sk, v, rng)
+    }
+
+    fn encrypt<R: RngCore + CryptoRng>(
+        pk: &PublicKey,
+        v: &Identity,
+        m: &Message,
+        rng: &mut R,
+    ) -> CipherText {
+        encrypt(pk, v, m, rng)
+    }
+
+    fn decrypt(usk: &UserSecretKey, c: &CipherText) -> Message {
+        decrypt(usk, c)
+    }
+}
+
+impl IBKEM for Waters {
+    type PublicKey = PublicKey;
+    type SecretKey = SecretKey;
+    type UserSecretKey = UserSecretKey;
+    type Identity = Identity;
+    type CipherText = CipherText;
+    type SharedSecret = Message;
+
+    fn setup<R: RngCore + CryptoRng>(rng: &mut R) -> (PublicKey, SecretKey) {
+        setup(rng)
+    }
+
+    fn extract_usk<R: RngCore + CryptoRng>(
+        pk: &PublicKey,
+        sk: &SecretKey,
+        v: &Identity,
+        rng: &mut R,
+    ) -> UserSecretKey {
+        extract_usk(pk, sk, v, rng)
+    }
+
+    fn encaps<R: RngCore + CryptoRng>(
+        pk: &PublicKey,
+        v: &Identity,
+        rng: &mut R,
+    ) -> (CipherText, Message) {
+        let m = Message::generate(rng);
+        let c = encrypt(pk, v, &m, rng);
+
+        (c, m)
+    }
+
+    fn decaps(_pk: &PublicKey, usk: &UserSecretKey, c: &CipherText) -> Message {
+        decrypt(usk, c)
+    }
+}
+
+impl DerandomizedIBE for Waters {
+    fn generate_message<R: RngCore + CryptoRng>(rng: &mut R) -> Message {
+        Message::generate(rng)
+    }
+
+    fn encrypt_derandomized(
+        pk: &PublicKey,
+        v: &Identity,
+        m: &Message,
+        coins: &[u8; 64],
+    ) -> CipherText {
+        encrypt_with_randomness(pk, v, m, &Scalar::from_bytes_wide(coins))
+    }
+}
+
+impl_serde!(PublicKey, PUBLICKEYSIZE);
+impl_serde!(CompactPublicKey, COMPACTPUBLICKEYSIZE);
+impl_serde!(SecretKey, 48);
+impl_serde!(UserSecretKey, 144);
+impl_serde!(Identity, HASH_BYTE_LEN);
+impl_serde!(Message, 288);
+impl_serde!(CipherText, 432);
+
+impl Compress for PublicKey {
+    type Output = [u8; PUBLICKEYSIZE];
+
+    fn to_bytes(&self) -> [u8; PUBLICKEYSIZE] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; PUBLICKEYSIZE]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for CompactPublicKey {
+    type Output = [u8; COMPACTPUBLICKEYSIZE];
+
+    fn to_bytes(&self) -> [u8; COMPACTPUBLICKEYSIZE] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; COMPACTPUBLICKEYSIZE]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Expand for CompactPublicKey {
+    type PublicKey = PublicKey;
+
+    fn expand(&self) -> PublicKey {
+        self.expand()
+    }
+}
+
+impl Compress for SecretKey {
+    type Output = [u8; 48];
+
+    fn to_bytes(&self) -> [u8; 48] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; 48]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for UserSecretKey {
+    type Output = [u8; 144];
+
+    fn to_bytes(&self) -> [u8; 144] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; 144]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for Identity {
+    type Output = [u8; HASH_BYTE_LEN];
+
+    fn to_bytes(&self) -> [u8; HASH_BYTE_LEN] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; HASH_BYTE_LEN]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for Message {
+    type Output = [u8; 288];
+
+    fn to_bytes(&self) -> [u8; 288] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; 288]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for CipherText {
+    type Output = [u8; 432];
+
+    fn to_bytes(&self) -> [u8; 432] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; 432]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl TryFrom<&[u8]> for PublicKey {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Error> {
+        let mut d = Decoder::new(bytes, PUBLICKEYSIZE)?;
+        let g = d.g2("g", None)?;
+        let g1 = d.g1("g1", None)?;
+        let g2 = d.g2("g2", None)?;
+        let uprime = d.g1("uprime", None)?;
+        let mut u = [G1Affine::default(); CHUNKS];
+        for (i, ui) in u.iter_mut().enumerate() {
+            *ui = d.g1("u", Some(i))?;
+        }
+
+        Ok(PublicKey {
+            g,
+            g1,
+            g2,
+            uprime,
+            u: Parameters(u),
+        })
+    }
+}
+
+impl TryFrom<&[u8]> for CompactPublicKey {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Error> {
+        let mut d = Decoder::new(bytes, COMPACTPUBLICKEYSIZE)?;
+        Ok(CompactPublicKey {
+            seed: *d.bytes(),
+            g: d.g2("g", None)?,
+            g1: d.g1("g1", None)?,
+            g2: d.g2("g2", None)?,
+            uprime: d.g1("uprime", None)?,
+        })
+    }
+}
+
+impl TryFrom<&[u8]> for CipherText {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Error> {
+        let mut d = Decoder::new(bytes, 432)?;
+        Ok(CipherText {
+            c1: d.gt("c1")?,
+            c2: d.g2("c2", None)?,
+            c3: d.g1("c3", None)?,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    const ID: &'static str = "email:w.geraedts@sarif.nl";
+    const ID: &str = "email:w.geraedts@sarif.nl";
 
     #[allow(dead_code)]
     struct DefaultSubResults {
@@ -379,6 +980,158 @@ mod tests {
         assert_eq!(results.m, m2);
     }
 
+    #[test]
+    fn eq_compact_encrypt_decrypt() {
+        let mut rng = rand::thread_rng();
+        let kid = Identity::derive_str(ID);
+
+        let (cpk, sk) = setup_compact(&mut rng);
+        let cpk2 = CompactPublicKey::from_bytes(&cpk.to_bytes()).unwrap();
+        assert_eq!(cpk, cpk2);
+
+        let pk = cpk2.expand();
+        assert!(pk == cpk.expand());
+
+        let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+        let m = Message::generate(&mut rng);
+        let c = encrypt(&pk, &kid, &m, &mut rng);
+        assert_eq!(m, decrypt(&usk, &c));
+    }
+
+    #[test]
+    fn eq_derive_key() {
+        let results = perform_default();
+        let (mut key, mut key2) = ([0u8; 32], [0u8; 32]);
+        results.m.derive_key(&results.c, b"test", &mut key);
+        decrypt(&results.usk, &results.c).derive_key(&results.c, b"test", &mut key2);
+        assert_eq!(key, key2);
+    }
+
+    #[test]
+    fn entangle_uses_all_bits() {
+        let (pk, _) = setup(&mut rand::thread_rng());
+        let zero = entangle(&pk, &Identity([0u8; HASH_BYTE_LEN]));
+
+        for i in 0..HASH_BYTE_LEN {
+            for bit in 0..8 {
+                let mut v = Identity([0u8; HASH_BYTE_LEN]);
+                v.0[i] = 1 << bit;
+                assert_ne!(zero, entangle(&pk, &v));
+            }
+        }
+    }
+
+    #[test]
+    fn verify_usk_identity() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+
+        let other = Identity::derive_str("email:other@example.com");
+        let usk_other = extract_usk(&results.pk, &results.sk, &other, &mut rng);
+
+        assert!(bool::from(verify_usk(
+            &results.pk,
+            &results.kid,
+            &results.usk
+        )));
+        assert!(!bool::from(verify_usk(
+            &results.pk,
+            &results.kid,
+            &usk_other
+        )));
+    }
+
+    #[test]
+    fn debug_is_redacted() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+
+        let debug = format!("{:?}", results.usk);
+        let fingerprint: std::string::String = results
+            .usk
+            .fingerprint()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        assert_eq!(
+            debug,
+            format!("UserSecretKey {{ fingerprint: {}, .. }}", fingerprint)
+        );
+
+        let usk2 = extract_usk(&results.pk, &results.sk, &results.kid, &mut rng);
+        assert!(bool::from(results.usk.ct_eq(&results.usk.clone())));
+        assert!(!bool::from(results.usk.ct_eq(&usk2)));
+        assert_ne!(results.usk.fingerprint(), usk2.fingerprint());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn prepared_matches_unprepared() {
+        let results = perform_default();
+        let ppk = PreparedPublicKey::new(&results.pk);
+
+        for i in 0..HASH_BYTE_LEN {
+            let mut v = Identity([0x5au8; HASH_BYTE_LEN]);
+            v.0[i] = 0x81;
+            assert_eq!(ppk.entangle(&v), entangle(&results.pk, &v));
+        }
+
+        let mut rng = XofRng::new(b"ibe-waters-test", &[0u8; 32]);
+        let mut rng2 = XofRng::new(b"ibe-waters-test", &[0u8; 32]);
+
+        let usk = extract_usk(&results.pk, &results.sk, &results.kid, &mut rng);
+        let usk2 = extract_usk_prepared(&ppk, &results.sk, &results.kid, &mut rng2);
+        assert!(usk == usk2);
+
+        let c = encrypt(&results.pk, &results.kid, &results.m, &mut rng);
+        let c2 = encrypt_prepared(&ppk, &results.kid, &results.m, &mut rng2);
+        assert_eq!(c, c2);
+        assert_eq!(decrypt(&usk2, &c2), results.m);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn eq_prepared_decrypt() {
+        let results = perform_default();
+        let pusk = PreparedUserSecretKey::new(&results.usk);
+
+        assert_eq!(decrypt_prepared(&pusk, &results.c), results.m);
+        assert!(pusk.user_secret_key() == &results.usk);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn batch_matches_sequential() {
+        let (pk, sk) = setup_from_seed(&[1u8; 32]);
+        let ids = [
+            Identity::derive_str(ID),
+            Identity::derive_str("email:l.botros@cs.ru.nl"),
+            Identity([0xffu8; HASH_BYTE_LEN]),
+        ];
+
+        let mut rng = XofRng::new(b"ibe-waters-test", &[0u8; 32]);
+        let mut rng2 = XofRng::new(b"ibe-waters-test", &[0u8; 32]);
+
+        let usks = extract_usk_batch(&pk, &sk, &ids, &mut rng);
+        assert_eq!(usks.len(), ids.len());
+        for (v, usk) in ids.iter().zip(usks.iter()) {
+            assert!(usk == &extract_usk(&pk, &sk, v, &mut rng2));
+        }
+        assert!(extract_usk_batch(&pk, &sk, &[], &mut rng).is_empty());
+    }
+
+    #[test]
+    fn setup_from_seed_is_deterministic() {
+        let (pk, sk) = setup_from_seed(&[1u8; 32]);
+        let (pk2, sk2) = setup_from_seed(&[1u8; 32]);
+        let (pk3, sk3) = setup_from_seed(&[2u8; 32]);
+
+        assert!(pk == pk2);
+        assert!(sk == sk2);
+        assert!(pk != pk3);
+        assert!(sk != sk3);
+    }
+
     #[test]
     fn eq_serialize_deserialize() {
         let result = perform_default();
@@ -398,4 +1151,79 @@ mod tests {
             CipherText::from_bytes(&result.c.to_bytes()).unwrap()
         );
     }
+
+    #[test]
+    fn try_from_reports_errors() {
+        let result = perform_default();
+
+        let pk_bytes = result.pk.to_bytes();
+        assert!(result.pk == PublicKey::try_from(&pk_bytes[..]).unwrap());
+        assert_eq!(
+            CipherText::try_from(&result.c.to_bytes()[..]).unwrap(),
+            result.c
+        );
+        assert_eq!(
+            PublicKey::try_from(&pk_bytes[1..]).err(),
+            Some(Error::InvalidLength {
+                expected: PUBLICKEYSIZE,
+                actual: PUBLICKEYSIZE - 1,
+            })
+        );
+
+        // A point on the curve outside the prime order subgroup.
+        let p = (0u8..)
+            .find_map(|i| {
+                let mut bytes = [0u8; 48];
+                bytes[0] = 0x80;
+                bytes[47] = i;
+                Option::from(G1Affine::from_compressed_unchecked(&bytes))
+                    .filter(|p: &G1Affine| !bool::from(p.is_torsion_free()))
+            })
+            .unwrap();
+        let mut corrupted = pk_bytes;
+        *array_mut_ref![corrupted, 2 * 48 + 2 * 96 + 3 * 48, 48] = p.to_compressed();
+        assert_eq!(
+            PublicKey::try_from(&corrupted[..]).err(),
+            Some(Error::NotInSubgroup {
+                component: "u",
+                index: Some(3),
+            })
+        );
+
+        let usk_bytes = result.usk.to_bytes();
+        assert_eq!(UserSecretKey::try_from(&usk_bytes[..]).unwrap(), result.usk);
+        let mut corrupted = usk_bytes;
+        corrupted[..48].copy_from_slice(&[0xff; 48]);
+        assert_eq!(
+            UserSecretKey::try_from(&corrupted[..]).err(),
+            Some(Error::InvalidSecret)
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_roundtrip() {
+        let result = perform_default();
+
+        let json = serde_json::to_string(&result.c).unwrap();
+        assert_eq!(json.len(), 432 / 3 * 4 + 2);
+        assert_eq!(serde_json::from_str::<CipherText>(&json).unwrap(), result.c);
+
+        // Not a multiple of three bytes, hence padded.
+        let json = serde_json::to_string(&result.kid).unwrap();
+        assert!(json.ends_with("=\""));
+        let kid: Identity = serde_json::from_str(&json).unwrap();
+        assert_eq!(kid.to_bytes(), result.kid.to_bytes());
+
+        let usk: UserSecretKey =
+            serde_json::from_str(&serde_json::to_string(&result.usk).unwrap()).unwrap();
+        assert_eq!(usk, result.usk);
+
+        let bytes = bincode::serialize(&result.pk).unwrap();
+        assert!(bytes.ends_with(&result.pk.to_bytes()));
+        assert!(bincode::deserialize::<PublicKey>(&bytes).unwrap() == result.pk);
+
+        assert!(serde_json::from_str::<SecretKey>("\"AAAA\"").is_err());
+        assert!(serde_json::from_str::<Message>("\"not base64!\"").is_err());
+    }
 }
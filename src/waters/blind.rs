@@ -0,0 +1,441 @@
+//! Blind extraction of user secret keys for the Waters scheme.
+//!
+//! Allows a user to obtain a user secret key from the PKG without revealing the identity,
+//! apart from a short tag identifying the type of attribute that the identity consists of.
+//! The PKG can thus decide whether to honour a request, for example after the user proved to own
+//! some attribute of that type, without learning the value of the attribute.
+//!
+//!  1. The user derives its identity using [`Identity::derive_attribute`],
+//!     and calls [`blind`] to obtain a [`BlindRequest`] to send to the PKG.
+//!  2. The PKG checks the [`attribute_tag`](BlindRequest::attribute_tag) of the request,
+//!     and calls [`extract_blind_usk`] to verify the request and obtain a [`BlindUserSecretKey`].
+//!  3. The user calls [`unblind`] to obtain a regular [`UserSecretKey`],
+//!     which it should check using [`verify_usk`](super::verify_usk).
+//!
+//! The user commits to every hidden bit of its identity separately, blinding each commitment with an
+//! independent generator, such that the commitments add up to the blinded point the identity is entangled into.
+//! For every commitment, the user proves in zero-knowledge that it hides either zero or one using a
+//! non-interactive OR-proof of knowledge of its blinding factor. The PKG thus learns nothing about the hidden
+//! bits, while being assured that the request is for a single identity with the revealed tag.
+//! As a consequence, a request takes about 27 KiB.
+
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use rand::{CryptoRng, RngCore};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption};
+
+use super::{entangle, Identity, PublicKey, SecretKey, UserSecretKey, HASH_BYTE_LEN};
+use crate::util::*;
+use irmaseal_curve::{G1Affine, G1Projective, G2Affine, Scalar};
+use tiny_keccak::{Hasher, Sha3};
+
+/// Number of bytes of the identity that are revealed to the PKG.
+pub const TAG_BYTE_LEN: usize = 8;
+
+// The bits of the identity are entangled starting at the last byte, hence the hidden bits come first.
+const HIDDEN_BITS: usize = (HASH_BYTE_LEN - TAG_BYTE_LEN) * 8;
+
+const PROOFSIZE: usize = (3 * HIDDEN_BITS + 1) * 32;
+const BLINDREQUESTSIZE: usize = TAG_BYTE_LEN + HIDDEN_BITS * 48 + PROOFSIZE;
+
+const GENERATOR_DST: &[u8] = b"ibe-waters-blind-generator";
+const CHALLENGE_DST: &[u8] = b"ibe-waters-blind-challenge";
+
+/// Blinding factor, kept by the user to unblind the blind user secret key.
+#[derive(Clone)]
+pub struct Blinding {
+    beta: Scalar,
+}
+
+zeroize_on_drop!(Blinding: beta);
+impl_secret!(Blinding: beta);
+
+/// Non-interactive proof that every commitment hides a bit, with knowledge of its blinding factor.
+///
+/// For the `i`-th commitment, branch 0 proves that it equals `ρ·x` and branch 1 that it equals `u_i + ρ·x`.
+/// The challenges `e0[i]` and `e - e0[i]` of both branches sum to the challenge `e`,
+/// such that one of them may be simulated.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Proof {
+    e: Scalar,
+    e0: [Scalar; HIDDEN_BITS],
+    s0: [Scalar; HIDDEN_BITS],
+    s1: [Scalar; HIDDEN_BITS],
+}
+
+/// Request for a blind user secret key, sent by the user to the PKG.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BlindRequest {
+    tag: [u8; TAG_BYTE_LEN],
+    commitments: [G1Affine; HIDDEN_BITS],
+    proof: Proof,
+}
+
+/// Blinded user secret key, sent by the PKG to the user.
+#[derive(Clone)]
+pub struct BlindUserSecretKey {
+    d1: G1Affine,
+    d2: G2Affine,
+    d3: G1Affine,
+}
+
+zeroize_on_drop!(BlindUserSecretKey: d1, d2, d3);
+impl_secret!(BlindUserSecretKey: d1, d2, d3);
+impl_try_from_secret!(BlindUserSecretKey, 192);
+
+/// Derive the tag for an attribute type, which forms the revealed part of the identity.
+pub fn attribute_tag(attribute_type: &[u8]) -> [u8; TAG_BYTE_LEN] {
+    let hash = sha3_256(attribute_type);
+    *array_ref![hash, 0, TAG_BYTE_LEN]
+}
+
+/// Independent generator with which the identity is blinded.
+fn generator() -> G1Projective {
+    hash_to_g1(b"", GENERATOR_DST)
+}
+
+/// The part of the entangled identity that is revealed to the PKG.
+fn entangle_tag(pk: &PublicKey, tag: &[u8; TAG_BYTE_LEN]) -> G1Projective {
+    let mut v = Identity([0u8; HASH_BYTE_LEN]);
+    v.0[..TAG_BYTE_LEN].copy_from_slice(tag);
+    entangle(pk, &v)
+}
+
+/// Start the Fiat-Shamir transcript of the proof.
+///
+/// Binds the entire public key, as the parameters `u` and `uprime` are part of the proven statement.
+fn transcript(
+    pk: &PublicKey,
+    tag: &[u8; TAG_BYTE_LEN],
+    commitments: &[G1Affine; HIDDEN_BITS],
+) -> Sha3 {
+    let mut transcript = Sha3::v512();
+    transcript.update(CHALLENGE_DST);
+    transcript.update(&pk.to_bytes());
+    transcript.update(tag);
+    for ci in commitments.iter() {
+        transcript.update(&ci.to_compressed());
+    }
+    transcript
+}
+
+fn absorb(transcript: &mut Sha3, t: &G1Projective) {
+    transcript.update(&G1Affine::from(t).to_compressed());
+}
+
+fn challenge(transcript: Sha3) -> Scalar {
+    let mut buf = [0u8; 64];
+    transcript.finalize(&mut buf);
+    Scalar::from_bytes_wide(&buf)
+}
+
+/// The blinded point the identity is entangled into, which is the sum of the commitments and the revealed tag.
+fn blinded_entangle(pk: &PublicKey, request: &BlindRequest) -> G1Projective {
+    let mut r = entangle_tag(pk, &request.tag);
+    for ci in request.commitments.iter() {
+        r += ci;
+    }
+    r
+}
+
+/// Verify that every commitment of the request hides a bit.
+fn verify(pk: &PublicKey, request: &BlindRequest) -> bool {
+    let Proof { e, e0, s0, s1 } = &request.proof;
+    let x = generator();
+
+    let mut transcript = transcript(pk, &request.tag, &request.commitments);
+    for (i, (ci, ui)) in request.commitments.iter().zip(pk.u.0.iter()).enumerate() {
+        let ci = G1Projective::from(ci);
+        absorb(&mut transcript, &(x * s0[i] - ci * e0[i]));
+        absorb(&mut transcript, &(x * s1[i] - (ci - ui) * (e - e0[i])));
+    }
+
+    bool::from(challenge(transcript).ct_eq(e))
+}
+
+/// Blind an identity, yielding the blinding factor to keep and the request to send to the PKG.
+///
+/// The first [`TAG_BYTE_LEN`] bytes of the identity are revealed to the PKG.
+pub fn blind<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    v: &Identity,
+    rng: &mut R,
+) -> (Blinding, BlindRequest) {
+    let tag = *array_ref![v.0, 0, TAG_BYTE_LEN];
+    let x = generator();
+
+    let mut rho = [Scalar::zero(); HIDDEN_BITS];
+    let mut commitments = [G1Affine::identity(); HIDDEN_BITS];
+    for ((ci, rhoi), (ui, vi)) in commitments
+        .iter_mut()
+        .zip(rho.iter_mut())
+        .zip(pk.u.0.iter().zip(bits(&v.0)))
+    {
+        *rhoi = rand_scalar(rng);
+        let c = x * *rhoi;
+        *ci = G1Projective::conditional_select(&c, &(c + ui), vi).into();
+    }
+
+    // Per bit, the nonce of the proven branch, and the challenge and response of the simulated branch.
+    let mut k = [Scalar::zero(); HIDDEN_BITS];
+    let mut e_sim = [Scalar::zero(); HIDDEN_BITS];
+    let mut s_sim = [Scalar::zero(); HIDDEN_BITS];
+
+    let mut transcript = transcript(pk, &tag, &commitments);
+    for (i, (ci, (ui, vi))) in commitments
+        .iter()
+        .zip(pk.u.0.iter().zip(bits(&v.0)))
+        .enumerate()
+    {
+        k[i] = rand_scalar(rng);
+        e_sim[i] = rand_scalar(rng);
+        s_sim[i] = rand_scalar(rng);
+
+        // The simulated branch claims that the commitment equals `u_i + ρ·x` for a zero bit,
+        // or `ρ·x` for a one bit.
+        let ci = G1Projective::from(ci);
+        let d = G1Projective::conditional_select(&(ci - ui), &ci, vi);
+        let t = x * k[i];
+        let t_sim = x * s_sim[i] - d * e_sim[i];
+
+        absorb(
+            &mut transcript,
+            &G1Projective::conditional_select(&t, &t_sim, vi),
+        );
+        absorb(
+            &mut transcript,
+            &G1Projective::conditional_select(&t_sim, &t, vi),
+        );
+    }
+    let e = challenge(transcript);
+
+    let mut proof = Proof {
+        e,
+        e0: [Scalar::zero(); HIDDEN_BITS],
+        s0: [Scalar::zero(); HIDDEN_BITS],
+        s1: [Scalar::zero(); HIDDEN_BITS],
+    };
+    for (i, vi) in bits(&v.0).take(HIDDEN_BITS).enumerate() {
+        let e_real = e - e_sim[i];
+        let s_real = k[i] + e_real * rho[i];
+
+        proof.e0[i] = Scalar::conditional_select(&e_real, &e_sim[i], vi);
+        proof.s0[i] = Scalar::conditional_select(&s_real, &s_sim[i], vi);
+        proof.s1[i] = Scalar::conditional_select(&s_sim[i], &s_real, vi);
+    }
+
+    let beta = rho.iter().sum();
+    rho.wipe();
+    k.wipe();
+
+    let request = BlindRequest {
+        tag,
+        commitments,
+        proof,
+    };
+
+    (Blinding { beta }, request)
+}
+
+/// Verify a blind request and extract a blind user secret key for it.
+///
+/// The PKG should check the [`attribute_tag`](BlindRequest::attribute_tag) of the request beforehand.
+/// Returns `None` if the proof of the request does not verify.
+pub fn extract_blind_usk<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    sk: &SecretKey,
+    request: &BlindRequest,
+    rng: &mut R,
+) -> Option<BlindUserSecretKey> {
+    if !verify(pk, request) {
+        return None;
+    }
+
+    let mut r = rand_scalar(rng);
+    let d1 = (sk.g1prime + (blinded_entangle(pk, request) * r)).into();
+    let d2 = (pk.g * r).into();
+    let d3 = (generator() * r).into();
+    r.wipe();
+
+    Some(BlindUserSecretKey { d1, d2, d3 })
+}
+
+/// Unblind a blind user secret key into a regular user secret key.
+pub fn unblind(blinding: &Blinding, busk: &BlindUserSecretKey) -> UserSecretKey {
+    UserSecretKey {
+        d1: (busk.d1 - busk.d3 * blinding.beta).into(),
+        d2: busk.d2,
+    }
+}
+
+impl Blinding {
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.beta.to_bytes()
+    }
+
+    pub fn from_bytes(bytes: &[u8; 32]) -> CtOption<Self> {
+        Scalar::from_bytes(bytes).map(|beta| Blinding { beta })
+    }
+}
+
+impl BlindRequest {
+    /// The revealed part of the identity, to be compared with the [`attribute_tag`] of an authorized attribute type.
+    pub fn attribute_tag(&self) -> [u8; TAG_BYTE_LEN] {
+        self.tag
+    }
+
+    pub fn to_bytes(&self) -> [u8; BLINDREQUESTSIZE] {
+        let mut res = [0u8; BLINDREQUESTSIZE];
+        let (tag, commitments, e, proof) = mut_array_refs![
+            &mut res,
+            TAG_BYTE_LEN,
+            HIDDEN_BITS * 48,
+            32,
+            HIDDEN_BITS * 96
+        ];
+        *tag = self.tag;
+        for (i, ci) in self.commitments.iter().enumerate() {
+            *array_mut_ref![commitments, i * 48, 48] = ci.to_compressed();
+        }
+        *e = self.proof.e.to_bytes();
+        for i in 0..HIDDEN_BITS {
+            let (e0, s0, s1) = mut_array_refs![array_mut_ref![proof, i * 96, 96], 32, 32, 32];
+            *e0 = self.proof.e0[i].to_bytes();
+            *s0 = self.proof.s0[i].to_bytes();
+            *s1 = self.proof.s1[i].to_bytes();
+        }
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; BLINDREQUESTSIZE]) -> CtOption<Self> {
+        let (tag, commitments, e, proof) =
+            array_refs![bytes, TAG_BYTE_LEN, HIDDEN_BITS * 48, 32, HIDDEN_BITS * 96];
+
+        let mut is_some = Choice::from(1u8);
+        let mut cres = [G1Affine::identity(); HIDDEN_BITS];
+        for (i, ci) in cres.iter_mut().enumerate() {
+            is_some &= G1Affine::from_compressed(array_ref![commitments, i * 48, 48])
+                .map(|x| {
+                    *ci = x;
+                })
+                .is_some();
+        }
+
+        let mut pres = Proof {
+            e: Scalar::zero(),
+            e0: [Scalar::zero(); HIDDEN_BITS],
+            s0: [Scalar::zero(); HIDDEN_BITS],
+            s1: [Scalar::zero(); HIDDEN_BITS],
+        };
+        is_some &= Scalar::from_bytes(e).map(|x| pres.e = x).is_some();
+        for i in 0..HIDDEN_BITS {
+            let (e0, s0, s1) = array_refs![array_ref![proof, i * 96, 96], 32, 32, 32];
+            is_some &= Scalar::from_bytes(e0).map(|x| pres.e0[i] = x).is_some();
+            is_some &= Scalar::from_bytes(s0).map(|x| pres.s0[i] = x).is_some();
+            is_some &= Scalar::from_bytes(s1).map(|x| pres.s1[i] = x).is_some();
+        }
+
+        CtOption::new(
+            BlindRequest {
+                tag: *tag,
+                commitments: cres,
+                proof: pres,
+            },
+            is_some,
+        )
+    }
+}
+
+impl BlindUserSecretKey {
+    pub fn to_bytes(&self) -> [u8; 192] {
+        let mut res = [0u8; 192];
+        let (d1, d2, d3) = mut_array_refs![&mut res, 48, 96, 48];
+        *d1 = self.d1.to_compressed();
+        *d2 = self.d2.to_compressed();
+        *d3 = self.d3.to_compressed();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; 192]) -> CtOption<Self> {
+        let (d1, d2, d3) = array_refs![bytes, 48, 96, 48];
+
+        let d1 = G1Affine::from_compressed(d1);
+        let d2 = G2Affine::from_compressed(d2);
+        let d3 = G1Affine::from_compressed(d3);
+
+        d1.and_then(|d1| d2.and_then(|d2| d3.map(|d3| BlindUserSecretKey { d1, d2, d3 })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{decrypt, encrypt, setup, verify_usk, Message};
+    use super::*;
+
+    const ATTRIBUTE_TYPE: &[u8] = b"pbdf.sidn-pbdf.email.email";
+    const VALUE: &[u8] = b"w.geraedts@sarif.nl";
+
+    #[test]
+    fn eq_blind_extract_decrypt() {
+        let mut rng = rand::thread_rng();
+
+        let kid = Identity::derive_attribute(ATTRIBUTE_TYPE, VALUE);
+        let m = Message::generate(&mut rng);
+
+        let (pk, sk) = setup(&mut rng);
+        let (blinding, request) = blind(&pk, &kid, &mut rng);
+
+        assert_eq!(request.attribute_tag(), attribute_tag(ATTRIBUTE_TYPE));
+
+        let busk = extract_blind_usk(&pk, &sk, &request, &mut rng).unwrap();
+        let usk = unblind(&blinding, &busk);
+
+        assert!(bool::from(verify_usk(&pk, &kid, &usk)));
+
+        let c = encrypt(&pk, &kid, &m, &mut rng);
+        assert_eq!(m, decrypt(&usk, &c));
+    }
+
+    #[test]
+    fn reject_malformed_request() {
+        let mut rng = rand::thread_rng();
+
+        let kid = Identity::derive_attribute(ATTRIBUTE_TYPE, VALUE);
+        let (pk, sk) = setup(&mut rng);
+        let (_, request) = blind(&pk, &kid, &mut rng);
+
+        // Claiming another attribute type invalidates the proof.
+        let mut other = request;
+        other.tag = attribute_tag(b"pbdf.gemeente.personalData.bsn");
+        assert!(extract_blind_usk(&pk, &sk, &other, &mut rng).is_none());
+
+        // Shifting a commitment towards another identity invalidates the proof.
+        let mut other = request;
+        other.commitments[0] = (G1Projective::from(other.commitments[0]) + pk.u.0[0]).into();
+        assert!(extract_blind_usk(&pk, &sk, &other, &mut rng).is_none());
+
+        // Exchanging commitments between positions invalidates the proof,
+        // even when the commitments still add up to the blinded identity.
+        let mut other = request;
+        other.commitments.swap(0, 1);
+        assert!(extract_blind_usk(&pk, &sk, &other, &mut rng).is_none());
+    }
+
+    #[test]
+    fn eq_serialize_deserialize() {
+        let mut rng = rand::thread_rng();
+
+        let kid = Identity::derive_attribute(ATTRIBUTE_TYPE, VALUE);
+        let (pk, sk) = setup(&mut rng);
+        let (_, request) = blind(&pk, &kid, &mut rng);
+        let busk = extract_blind_usk(&pk, &sk, &request, &mut rng).unwrap();
+
+        assert_eq!(
+            request,
+            BlindRequest::from_bytes(&request.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            busk,
+            BlindUserSecretKey::from_bytes(&busk.to_bytes()).unwrap()
+        );
+    }
+}
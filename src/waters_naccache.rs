@@ -10,11 +10,21 @@
 //! All operations in this library are implemented to run in constant time.
 
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
-use rand::Rng;
-use subtle::{Choice, ConditionallySelectable, CtOption};
+use rand::{CryptoRng, RngCore};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption};
 
+use crate::compact::Expand;
+#[cfg(feature = "alloc")]
+use crate::table::{G1Table, G2Table, SCALAR_WINDOWS, WINDOW};
 use crate::util::*;
-use irmaseal_curve::{G1Affine, G2Affine, G2Projective, Gt, Scalar};
+use crate::validation::*;
+use crate::{Compress, DerandomizedIBE, Derive, Error, IBE, IBKEM};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use irmaseal_curve::{multi_miller_loop, G1Affine, G2Affine, G2Prepared, G2Projective, Gt, Scalar};
+
+pub mod blind;
 
 const HASH_BIT_LEN: usize = 512;
 const HASH_BYTE_LEN: usize = HASH_BIT_LEN / 8;
@@ -25,6 +35,10 @@ const CHUNKS: usize = HASH_BYTE_LEN / CHUNKSIZE;
 
 const PARAMETERSIZE: usize = CHUNKS * 96;
 const PUBLICKEYSIZE: usize = 2 * 48 + 2 * 96 + PARAMETERSIZE;
+const COMPACTPUBLICKEYSIZE: usize = 32 + 2 * 48 + 2 * 96;
+
+const SEED_DST: &[u8] = b"ibe-waters-naccache-setup-seed";
+const PARAMETERS_DST: &[u8] = b"ibe-waters-naccache-parameters-BLS12381G2_XOF:SHAKE-256_SSWU_RO_";
 
 #[derive(Default, Clone, Copy, PartialEq, Debug)]
 struct Parameters([G2Affine; CHUNKS]);
@@ -33,17 +47,27 @@ impl ConditionallySelectable for Parameters {
     fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
         let mut res = [G2Affine::default(); CHUNKS];
         for (i, (ai, bi)) in a.0.iter().zip(b.0.iter()).enumerate() {
-            res[i] = G2Affine::conditional_select(&ai, &bi, choice);
+            res[i] = G2Affine::conditional_select(ai, bi, choice);
         }
         Parameters(res)
     }
 }
 
 impl Parameters {
+    /// Derive the parameters from a public seed using hash-to-curve.
+    fn derive(seed: &[u8; 32]) -> Self {
+        let mut res = [G2Affine::default(); CHUNKS];
+        for (i, ui) in (0u32..).zip(res.iter_mut()) {
+            *ui = hash_to_g2(&seeded_parameter_input(seed, i), PARAMETERS_DST).into();
+        }
+        Parameters(res)
+    }
+
+    #[allow(clippy::wrong_self_convention)]
     pub fn to_bytes(&self) -> [u8; PARAMETERSIZE] {
         let mut res = [0u8; PARAMETERSIZE];
-        for i in 0..CHUNKS {
-            *array_mut_ref![&mut res, i * 96, 96] = self.0[i].to_compressed();
+        for (i, ui) in self.0.iter().enumerate() {
+            *array_mut_ref![&mut res, i * 96, 96] = ui.to_compressed();
         }
         res
     }
@@ -51,10 +75,10 @@ impl Parameters {
     pub fn from_bytes(bytes: &[u8; PARAMETERSIZE]) -> CtOption<Self> {
         let mut res = [G2Affine::default(); CHUNKS];
         let mut is_some = Choice::from(1u8);
-        for i in 0..CHUNKS {
+        for (i, ri) in res.iter_mut().enumerate() {
             is_some &= G2Affine::from_compressed(array_ref![bytes, i * 96, 96])
                 .map(|s| {
-                    res[i] = s;
+                    *ri = s;
                 })
                 .is_some();
         }
@@ -72,7 +96,48 @@ pub struct PublicKey {
     u: Parameters,
 }
 
+/// Public key with precomputed tables, for faster extraction and encryption using a long-lived public key.
+///
+/// Contains a fixed-base table for `g` and for every parameter used for entanglement,
+/// as well as the pairing of `g1` and `g2`. Takes about 500 KiB of memory.
+///
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+pub struct PreparedPublicKey {
+    pk: PublicKey,
+    g: G1Table,
+    u: Vec<G2Table>,
+    z: Gt,
+}
+
+/// Public key of which the parameters are derived from a public seed, see [`setup_compact`].
+///
+/// Must be expanded to a [`PublicKey`] before use.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CompactPublicKey {
+    seed: [u8; 32],
+    g: G1Affine,
+    g1: G1Affine,
+    g2: G2Affine,
+    uprime: G2Affine,
+}
+
 impl PublicKey {
+    /// Validate a public key obtained from an untrusted source.
+    ///
+    /// Checks that all points, including the parameters for every chunk of the identity,
+    /// are in the prime order subgroup and that none of them is the identity.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        check_g1(&self.g, "g", None)?;
+        check_g1(&self.g1, "g1", None)?;
+        check_g2(&self.g2, "g2", None)?;
+        check_g2(&self.uprime, "uprime", None)?;
+        for (i, ui) in self.u.0.iter().enumerate() {
+            check_g2(ui, "u", Some(i))?;
+        }
+        Ok(())
+    }
+
     pub fn to_bytes(&self) -> [u8; PUBLICKEYSIZE] {
         let mut res = [0u8; PUBLICKEYSIZE];
         let (g, g1, g2, uprime, u) = mut_array_refs![&mut res, 48, 48, 96, 96, PARAMETERSIZE];
@@ -119,11 +184,114 @@ impl PublicKey {
 pub struct Identity([Scalar; CHUNKS]);
 
 /// Secret key parameter generated by the PKG used to extract user secret keys.
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone)]
 pub struct SecretKey {
     g2prime: G2Affine,
 }
 
+zeroize_on_drop!(SecretKey: g2prime);
+impl_secret!(SecretKey: g2prime);
+impl_try_from_secret!(SecretKey, 96);
+
+#[cfg(feature = "alloc")]
+impl PreparedUserSecretKey {
+    /// Precompute the line functions of a user secret key.
+    pub fn new(usk: &UserSecretKey) -> Self {
+        PreparedUserSecretKey {
+            usk: usk.clone(),
+            d1: G2Prepared::from(usk.d1),
+        }
+    }
+
+    /// The user secret key of which the line functions were precomputed.
+    pub fn user_secret_key(&self) -> &UserSecretKey {
+        &self.usk
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl PreparedPublicKey {
+    /// Precompute the tables for a public key.
+    pub fn new(pk: &PublicKey) -> Self {
+        PreparedPublicKey {
+            pk: *pk,
+            g: G1Table::fixed_base(&pk.g, SCALAR_WINDOWS),
+            u: pk
+                .u
+                .0
+                .iter()
+                .map(|ui| G2Table::fixed_base(ui, BITSIZE / WINDOW))
+                .collect(),
+            z: irmaseal_curve::pairing(&pk.g1, &pk.g2),
+        }
+    }
+
+    /// The public key of which the tables were precomputed.
+    pub fn public_key(&self) -> &PublicKey {
+        &self.pk
+    }
+
+    /// Entangle the public key with an identity, as [`entangle`] does.
+    ///
+    /// The chunks of the identity are only `BITSIZE` bits long, hence their tables are short.
+    fn entangle(&self, v: &Identity) -> G2Projective {
+        let mut ucoll: G2Projective = self.pk.uprime.into();
+        for (ui, vi) in self.u.iter().zip(&v.0) {
+            ucoll += ui.mul(vi);
+        }
+        ucoll
+    }
+}
+
+impl CompactPublicKey {
+    /// Derive the full public key, recomputing the parameters from the seed.
+    ///
+    /// This is relatively expensive, hence the result should be kept around for repeated use.
+    pub fn expand(&self) -> PublicKey {
+        PublicKey {
+            g: self.g,
+            g1: self.g1,
+            g2: self.g2,
+            uprime: self.uprime,
+            u: Parameters::derive(&self.seed),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; COMPACTPUBLICKEYSIZE] {
+        let mut res = [0u8; COMPACTPUBLICKEYSIZE];
+        let (seed, g, g1, g2, uprime) = mut_array_refs![&mut res, 32, 48, 48, 96, 96];
+        *seed = self.seed;
+        *g = self.g.to_compressed();
+        *g1 = self.g1.to_compressed();
+        *g2 = self.g2.to_compressed();
+        *uprime = self.uprime.to_compressed();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; COMPACTPUBLICKEYSIZE]) -> CtOption<Self> {
+        let (seed, g, g1, g2, uprime) = array_refs![bytes, 32, 48, 48, 96, 96];
+
+        let g = G1Affine::from_compressed(g);
+        let g1 = G1Affine::from_compressed(g1);
+        let g2 = G2Affine::from_compressed(g2);
+        let uprime = G2Affine::from_compressed(uprime);
+
+        g.and_then(|g| {
+            g1.and_then(|g1| {
+                g2.and_then(|g2| {
+                    uprime.map(|uprime| CompactPublicKey {
+                        seed: *seed,
+                        g,
+                        g1,
+                        g2,
+                        uprime,
+                    })
+                })
+            })
+        })
+    }
+}
+
 impl SecretKey {
     pub fn to_bytes(&self) -> [u8; 96] {
         self.g2prime.to_compressed()
@@ -135,12 +303,31 @@ impl SecretKey {
 }
 
 /// Points on the paired curves that form the user secret key.
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone)]
 pub struct UserSecretKey {
     d1: G2Affine,
     d2: G1Affine,
 }
 
+zeroize_on_drop!(UserSecretKey: d1, d2);
+impl_secret!(UserSecretKey: d1, d2);
+impl_try_from_secret!(UserSecretKey, 144);
+
+/// User secret key with the precomputed line functions of `d1`, for faster repeated decryption.
+///
+/// Unlike for Waters, the prepared point `d1` is key material, as it contains the blinded master secret key.
+/// When the `zeroize` feature is enabled, only the contained user secret key is wiped when dropped,
+/// as the line functions cannot be wiped. These therefore remain in memory after use,
+/// and allow decryption of ciphertexts for this identity together with `d2`.
+///
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+#[derive(Clone)]
+pub struct PreparedUserSecretKey {
+    usk: UserSecretKey,
+    d1: G2Prepared,
+}
+
 impl UserSecretKey {
     pub fn to_bytes(&self) -> [u8; 144] {
         let mut res = [0u8; 144];
@@ -191,13 +378,16 @@ impl CipherText {
 
 /// A point on the paired curve that can be encrypted and decrypted.
 ///
-/// You can use the byte representation to derive an AES key.
-#[derive(Clone, Copy, Debug, PartialEq)]
+/// Use [`derive_key`](Self::derive_key) to derive symmetric keys, such as for AES.
+#[derive(Clone)]
 pub struct Message(Gt);
 
+zeroize_on_drop!(Message: 0);
+impl_secret!(Message: 0);
+
 impl Message {
     /// Generate a random point on the paired curve.
-    pub fn generate<R: Rng>(rng: &mut R) -> Self {
+    pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
         Self(rand_gt(rng))
     }
 
@@ -208,13 +398,25 @@ impl Message {
     pub fn from_bytes(bytes: &[u8; 288]) -> CtOption<Self> {
         Gt::from_compressed(bytes).map(Message)
     }
+
+    /// Derive symmetric key material bound to `c` and `info`, see [key derivation](crate#key-derivation).
+    pub fn derive_key(&self, c: &CipherText, info: &[u8], out: &mut [u8]) {
+        kdf(&self.to_bytes(), &c.to_bytes(), info, out)
+    }
 }
 
+/// The Waters-Naccache identity-based encryption scheme.
+///
+/// Implements [`IBE`] on top of the functions in this module,
+/// and [`IBKEM`] by encrypting a randomly generated [`Message`].
+#[derive(Clone, Copy, Debug)]
+pub struct WatersNaccache;
+
 /// Generate a keypair used by the Private Key Generator (PKG).
-pub fn setup<R: Rng>(rng: &mut R) -> (PublicKey, SecretKey) {
+pub fn setup<R: RngCore + CryptoRng>(rng: &mut R) -> (PublicKey, SecretKey) {
     let g: G1Affine = rand_g1(rng).into();
 
-    let alpha = rand_scalar(rng);
+    let mut alpha = rand_scalar(rng);
     let g1 = (g * alpha).into();
 
     let g2 = rand_g2(rng).into();
@@ -234,6 +436,46 @@ pub fn setup<R: Rng>(rng: &mut R) -> (PublicKey, SecretKey) {
     };
 
     let g2prime: G2Affine = (g2 * alpha).into();
+    alpha.wipe();
+
+    let sk = SecretKey { g2prime };
+
+    (pk, sk)
+}
+
+/// Deterministically derive a keypair for the PKG from a seed.
+///
+/// The same seed always results in the same keypair, hence the seed must be kept as secret as the master secret key.
+pub fn setup_from_seed(seed: &[u8; 32]) -> (PublicKey, SecretKey) {
+    setup(&mut XofRng::new(SEED_DST, seed))
+}
+
+/// Generate a keypair used by the Private Key Generator (PKG), with a compact public key.
+///
+/// The parameters used for entanglement with identities are derived by hash-to-curve from a random public seed,
+/// such that their discrete logarithms are unknown to anyone, including the PKG.
+pub fn setup_compact<R: RngCore + CryptoRng>(rng: &mut R) -> (CompactPublicKey, SecretKey) {
+    let mut seed = [0u8; 32];
+    rng.fill_bytes(&mut seed);
+
+    let g: G1Affine = rand_g1(rng).into();
+
+    let mut alpha = rand_scalar(rng);
+    let g1 = (g * alpha).into();
+
+    let g2 = rand_g2(rng).into();
+    let uprime = rand_g2(rng).into();
+
+    let pk = CompactPublicKey {
+        seed,
+        g,
+        g1,
+        g2,
+        uprime,
+    };
+
+    let g2prime: G2Affine = (g2 * alpha).into();
+    alpha.wipe();
 
     let sk = SecretKey { g2prime };
 
@@ -251,24 +493,37 @@ fn entangle(pk: &PublicKey, v: &Identity) -> G2Projective {
 }
 
 /// Extract an user secret key for a given identity.
-pub fn extract_usk<R: Rng>(
+pub fn extract_usk<R: RngCore + CryptoRng>(
     pk: &PublicKey,
     sk: &SecretKey,
     v: &Identity,
     rng: &mut R,
 ) -> UserSecretKey {
-    let r = rand_scalar(rng);
+    let mut r = rand_scalar(rng);
     let ucoll = entangle(pk, v);
     let d1 = (sk.g2prime + (ucoll * r)).into();
     let d2 = (pk.g * r).into();
+    r.wipe();
 
     UserSecretKey { d1, d2 }
 }
 
 /// Encrypt a message using the PKG public key and an identity.
-pub fn encrypt<R: Rng>(pk: &PublicKey, v: &Identity, m: &Message, rng: &mut R) -> CipherText {
-    let t = rand_scalar(rng);
+pub fn encrypt<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    v: &Identity,
+    m: &Message,
+    rng: &mut R,
+) -> CipherText {
+    let mut t = rand_scalar(rng);
+    let c = encrypt_with_randomness(pk, v, m, &t);
+    t.wipe();
 
+    c
+}
+
+/// Encrypt a message using the given randomness.
+fn encrypt_with_randomness(pk: &PublicKey, v: &Identity, m: &Message, t: &Scalar) -> CipherText {
     let c3coll = entangle(pk, v);
     let c1 = irmaseal_curve::pairing(&pk.g1, &pk.g2) * t + m.0;
     let c2 = (pk.g * t).into();
@@ -277,21 +532,136 @@ pub fn encrypt<R: Rng>(pk: &PublicKey, v: &Identity, m: &Message, rng: &mut R) -
     CipherText { c1, c2, c3 }
 }
 
+/// Extract an user secret key for a given identity using a prepared public key.
+///
+/// Results in the same key as [`extract_usk`] given the same randomness.
+#[cfg(feature = "alloc")]
+pub fn extract_usk_prepared<R: RngCore + CryptoRng>(
+    ppk: &PreparedPublicKey,
+    sk: &SecretKey,
+    v: &Identity,
+    rng: &mut R,
+) -> UserSecretKey {
+    let mut r = rand_scalar(rng);
+    let ucoll = ppk.entangle(v);
+    let d1 = (sk.g2prime + (ucoll * r)).into();
+    let d2 = ppk.g.mul(&r).into();
+    r.wipe();
+
+    UserSecretKey { d1, d2 }
+}
+
+/// Extract user secret keys for many identities at once.
+///
+/// Shares the fixed-base tables of a [`PreparedPublicKey`] between all identities,
+/// which avoids the full scalar multiplications of the parameters for every chunk of every identity.
+/// All keys are converted to affine coordinates using a single inversion,
+/// and are extracted in parallel when the `rayon` feature is enabled.
+/// Given the same randomness, results in the same keys as calling [`extract_usk`] for every identity in order.
+///
+/// Requires the `alloc` feature.
+#[cfg(feature = "alloc")]
+pub fn extract_usk_batch<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    sk: &SecretKey,
+    ids: &[Identity],
+    rng: &mut R,
+) -> Vec<UserSecretKey> {
+    let ppk = PreparedPublicKey::new(pk);
+    let mut rs = rand_scalars(ids.len(), rng);
+
+    let mut d1 = batch_map(ids, |i, v| sk.g2prime + ppk.entangle(v) * rs[i]);
+    let mut d2 = batch_map(&rs, |_, r| ppk.g.mul(r));
+    rs.wipe();
+
+    let mut d1 = batch_normalize_g2(&mut d1);
+    let mut d2 = batch_normalize_g1(&mut d2);
+    let res = d1
+        .iter()
+        .zip(d2.iter())
+        .map(|(d1, d2)| UserSecretKey { d1: *d1, d2: *d2 })
+        .collect();
+    d1.wipe();
+    d2.wipe();
+
+    res
+}
+
+/// Encrypt a message using a prepared public key and an identity.
+///
+/// Results in the same ciphertext as [`encrypt`] given the same randomness.
+#[cfg(feature = "alloc")]
+pub fn encrypt_prepared<R: RngCore + CryptoRng>(
+    ppk: &PreparedPublicKey,
+    v: &Identity,
+    m: &Message,
+    rng: &mut R,
+) -> CipherText {
+    let mut t = rand_scalar(rng);
+    let c3coll = ppk.entangle(v);
+    let c1 = ppk.z * t + m.0;
+    let c2 = ppk.g.mul(&t).into();
+    let c3 = (c3coll * t).into();
+    t.wipe();
+
+    CipherText { c1, c2, c3 }
+}
+
 /// Decrypt ciphertext to a message using a user secret key.
+///
+/// Computes `c1 + e(d2, c3) - e(c2, d1)` using a single Miller loop and final exponentiation.
 pub fn decrypt(usk: &UserSecretKey, c: &CipherText) -> Message {
-    let num = irmaseal_curve::pairing(&usk.d2, &c.c3);
-    let dem = irmaseal_curve::pairing(&c.c2, &usk.d1);
+    decrypt_with(usk, &G2Prepared::from(usk.d1), c)
+}
+
+/// Decrypt ciphertext to a message using a prepared user secret key.
+///
+/// Results in the same message as [`decrypt`].
+#[cfg(feature = "alloc")]
+pub fn decrypt_prepared(pusk: &PreparedUserSecretKey, c: &CipherText) -> Message {
+    decrypt_with(&pusk.usk, &pusk.d1, c)
+}
+
+fn decrypt_with(usk: &UserSecretKey, d1: &G2Prepared, c: &CipherText) -> Message {
+    let c3 = G2Prepared::from(c.c3);
+    let m = c.c1 + multi_miller_loop(&[(&usk.d2, &c3), (&-c.c2, d1)]).final_exponentiation();
 
-    let m = c.c1 + num - dem;
     Message(m)
 }
 
+/// Verify that a user secret key is valid for the given identity.
+///
+/// Checks that `e(g, d1) == e(g1, g2) · e(d2, entangle(pk, v))` using pairings, without requiring a ciphertext.
+pub fn verify_usk(pk: &PublicKey, v: &Identity, usk: &UserSecretKey) -> Choice {
+    let ucoll: G2Affine = entangle(pk, v).into();
+    let lhs = irmaseal_curve::pairing(&pk.g, &usk.d1) - irmaseal_curve::pairing(&usk.d2, &ucoll);
+
+    lhs.ct_eq(&irmaseal_curve::pairing(&pk.g1, &pk.g2))
+}
+
 impl Identity {
     /// Hash a byte slice to a set of Identity parameters, which acts as a user public key.
     /// Uses sha3-512 internally.
     pub fn derive(b: &[u8]) -> Identity {
-        let hash = sha3_512(b);
+        Self::from_hash(&sha3_512(b))
+    }
+
+    /// Hash an attribute to a set of Identity parameters, such that the identity starts with
+    /// the [`attribute_tag`](blind::attribute_tag) of the attribute type.
+    ///
+    /// Required for [blind extraction](blind), which reveals the tag to the PKG.
+    pub fn derive_attribute(attribute_type: &[u8], value: &[u8]) -> Identity {
+        let mut hash = sha3_512_multi(&[
+            &(attribute_type.len() as u64).to_be_bytes(),
+            attribute_type,
+            value,
+        ]);
+        *array_mut_ref![hash, 0, blind::TAG_BYTE_LEN] = blind::attribute_tag(attribute_type);
+
+        Self::from_hash(&hash)
+    }
 
+    fn from_hash(hash: &[u8; HASH_BYTE_LEN]) -> Identity {
         let mut result = [Scalar::zero(); CHUNKS];
         for (i, r) in result.iter_mut().enumerate().take(CHUNKS) {
             *r = u64::from(u32::from_le_bytes(*array_ref![
@@ -310,13 +680,272 @@ impl Identity {
     pub fn derive_str(s: &str) -> Identity {
         Self::derive(s.as_bytes())
     }
+
+    /// The hash this identity was derived from, which is stored in the first bytes of every scalar.
+    pub fn to_bytes(&self) -> [u8; HASH_BYTE_LEN] {
+        let mut res = [0u8; HASH_BYTE_LEN];
+        for (i, r) in self.0.iter().enumerate() {
+            let bytes = r.to_bytes();
+            *array_mut_ref![res, i * CHUNKSIZE, CHUNKSIZE] = *array_ref![bytes, 0, CHUNKSIZE];
+        }
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; HASH_BYTE_LEN]) -> CtOption<Self> {
+        CtOption::new(Self::from_hash(bytes), 1u8.into())
+    }
+}
+
+impl Derive for Identity {
+    fn derive(b: &[u8]) -> Identity {
+        Identity::derive(b)
+    }
+}
+
+impl IBE for WatersNaccache {
+    type PublicKey = PublicKey;
+    type SecretKey = SecretKey;
+    type UserSecretKey = UserSecretKey;
+    type Identity = Identity;
+    type Message = Message;
+    type CipherText = CipherText;
+
+    fn setup<R: RngCore + CryptoRng>(rng: &mut R) -> (PublicKey, SecretKey) {
+        setup(rng)
+    }
+
+    fn extract_usk<R: RngCore + CryptoRng>(
+        pk: &PublicKey,
+        sk: &SecretKey,
+        v: &Identity,
+        rng: &mut R,
+    ) -> UserSecretKey {
+        extract_usk(pk, sk, v, rng)
+    }
+
+    fn encrypt<R: RngCore + CryptoRng>(
+        pk: &PublicKey,
+        v: &Identity,
+        m: &Message,
+        rng: &mut R,
+    ) -> CipherText {
+        encrypt(pk, v, m, rng)
+    }
+
+    fn decrypt(usk: &UserSecretKey, c: &CipherText) -> Message {
+        decrypt(usk, c)
+    }
+}
+
+impl IBKEM for WatersNaccache {
+    type PublicKey = PublicKey;
+    type SecretKey = SecretKey;
+    type UserSecretKey = UserSecretKey;
+    type Identity = Identity;
+    type CipherText = CipherText;
+    type SharedSecret = Message;
+
+    fn setup<R: RngCore + CryptoRng>(rng: &mut R) -> (PublicKey, SecretKey) {
+        setup(rng)
+    }
+
+    fn extract_usk<R: RngCore + CryptoRng>(
+        pk: &PublicKey,
+        sk: &SecretKey,
+        v: &Identity,
+        rng: &mut R,
+    ) -> UserSecretKey {
+        extract_usk(pk, sk, v, rng)
+    }
+
+    fn encaps<R: RngCore + CryptoRng>(
+        pk: &PublicKey,
+        v: &Identity,
+        rng: &mut R,
+    ) -> (CipherText, Message) {
+        let m = Message::generate(rng);
+        let c = encrypt(pk, v, &m, rng);
+
+        (c, m)
+    }
+
+    fn decaps(_pk: &PublicKey, usk: &UserSecretKey, c: &CipherText) -> Message {
+        decrypt(usk, c)
+    }
+}
+
+impl DerandomizedIBE for WatersNaccache {
+    fn generate_message<R: RngCore + CryptoRng>(rng: &mut R) -> Message {
+        Message::generate(rng)
+    }
+
+    fn encrypt_derandomized(
+        pk: &PublicKey,
+        v: &Identity,
+        m: &Message,
+        coins: &[u8; 64],
+    ) -> CipherText {
+        encrypt_with_randomness(pk, v, m, &Scalar::from_bytes_wide(coins))
+    }
+}
+
+impl_serde!(PublicKey, PUBLICKEYSIZE);
+impl_serde!(CompactPublicKey, COMPACTPUBLICKEYSIZE);
+impl_serde!(SecretKey, 96);
+impl_serde!(UserSecretKey, 144);
+impl_serde!(Identity, HASH_BYTE_LEN);
+impl_serde!(Message, 288);
+impl_serde!(CipherText, 432);
+
+impl Compress for PublicKey {
+    type Output = [u8; PUBLICKEYSIZE];
+
+    fn to_bytes(&self) -> [u8; PUBLICKEYSIZE] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; PUBLICKEYSIZE]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for CompactPublicKey {
+    type Output = [u8; COMPACTPUBLICKEYSIZE];
+
+    fn to_bytes(&self) -> [u8; COMPACTPUBLICKEYSIZE] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; COMPACTPUBLICKEYSIZE]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Expand for CompactPublicKey {
+    type PublicKey = PublicKey;
+
+    fn expand(&self) -> PublicKey {
+        self.expand()
+    }
+}
+
+impl Compress for SecretKey {
+    type Output = [u8; 96];
+
+    fn to_bytes(&self) -> [u8; 96] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; 96]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for UserSecretKey {
+    type Output = [u8; 144];
+
+    fn to_bytes(&self) -> [u8; 144] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; 144]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for Identity {
+    type Output = [u8; HASH_BYTE_LEN];
+
+    fn to_bytes(&self) -> [u8; HASH_BYTE_LEN] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; HASH_BYTE_LEN]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for Message {
+    type Output = [u8; 288];
+
+    fn to_bytes(&self) -> [u8; 288] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; 288]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Compress for CipherText {
+    type Output = [u8; 432];
+
+    fn to_bytes(&self) -> [u8; 432] {
+        self.to_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8; 432]) -> CtOption<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl TryFrom<&[u8]> for PublicKey {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Error> {
+        let mut d = Decoder::new(bytes, PUBLICKEYSIZE)?;
+        let g = d.g1("g", None)?;
+        let g1 = d.g1("g1", None)?;
+        let g2 = d.g2("g2", None)?;
+        let uprime = d.g2("uprime", None)?;
+        let mut u = [G2Affine::default(); CHUNKS];
+        for (i, ui) in u.iter_mut().enumerate() {
+            *ui = d.g2("u", Some(i))?;
+        }
+
+        Ok(PublicKey {
+            g,
+            g1,
+            g2,
+            uprime,
+            u: Parameters(u),
+        })
+    }
+}
+
+impl TryFrom<&[u8]> for CompactPublicKey {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Error> {
+        let mut d = Decoder::new(bytes, COMPACTPUBLICKEYSIZE)?;
+        Ok(CompactPublicKey {
+            seed: *d.bytes(),
+            g: d.g1("g", None)?,
+            g1: d.g1("g1", None)?,
+            g2: d.g2("g2", None)?,
+            uprime: d.g2("uprime", None)?,
+        })
+    }
+}
+
+impl TryFrom<&[u8]> for CipherText {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Error> {
+        let mut d = Decoder::new(bytes, 432)?;
+        Ok(CipherText {
+            c1: d.gt("c1")?,
+            c2: d.g1("c2", None)?,
+            c3: d.g2("c3", None)?,
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    const ID: &'static str = "email:w.geraedts@sarif.nl";
+    const ID: &str = "email:w.geraedts@sarif.nl";
 
     #[allow(dead_code)]
     struct DefaultSubResults {
@@ -359,9 +988,56 @@ mod tests {
         assert_eq!(results.m, m2);
     }
 
+    #[test]
+    fn eq_compact_encrypt_decrypt() {
+        let mut rng = rand::thread_rng();
+        let kid = Identity::derive_str(ID);
+
+        let (cpk, sk) = setup_compact(&mut rng);
+        let cpk2 = CompactPublicKey::from_bytes(&cpk.to_bytes()).unwrap();
+        assert_eq!(cpk, cpk2);
+
+        let pk = cpk2.expand();
+        assert!(pk == cpk.expand());
+
+        let usk = extract_usk(&pk, &sk, &kid, &mut rng);
+        let m = Message::generate(&mut rng);
+        let c = encrypt(&pk, &kid, &m, &mut rng);
+        assert_eq!(m, decrypt(&usk, &c));
+    }
+
+    #[test]
+    fn verify_usk_identity() {
+        let mut rng = rand::thread_rng();
+        let results = perform_default();
+
+        let other = Identity::derive_str("email:other@example.com");
+        let usk_other = extract_usk(&results.pk, &results.sk, &other, &mut rng);
+
+        assert!(bool::from(verify_usk(
+            &results.pk,
+            &results.kid,
+            &results.usk
+        )));
+        assert!(!bool::from(verify_usk(
+            &results.pk,
+            &results.kid,
+            &usk_other
+        )));
+
+        let mut tampered = results.usk.clone();
+        tampered.d2 =
+            (irmaseal_curve::G1Projective::from(tampered.d2) + G1Affine::generator()).into();
+        assert!(!bool::from(verify_usk(
+            &results.pk,
+            &results.kid,
+            &tampered
+        )));
+    }
+
     #[test]
     fn stability_identity() {
-        const REFERENCE: &'static [u32; 16] = &[
+        const REFERENCE: &[u32; 16] = &[
             224058892, 3543031066, 2100894308, 1450993543, 380724969, 4144530249, 2749396120,
             320408521, 409248772, 2464563459, 877936958, 2596797041, 3979538376, 3505820338,
             590474010, 189115610,
@@ -378,6 +1054,78 @@ mod tests {
         }
     }
 
+    #[test]
+    fn eq_derive_key() {
+        let results = perform_default();
+        let (mut key, mut key2) = ([0u8; 32], [0u8; 32]);
+        results.m.derive_key(&results.c, b"test", &mut key);
+        decrypt(&results.usk, &results.c).derive_key(&results.c, b"test", &mut key2);
+        assert_eq!(key, key2);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn prepared_matches_unprepared() {
+        let results = perform_default();
+        let ppk = PreparedPublicKey::new(&results.pk);
+
+        let v = Identity([Scalar::from(u32::MAX as u64); CHUNKS]);
+        assert_eq!(ppk.entangle(&v), entangle(&results.pk, &v));
+
+        let mut rng = XofRng::new(b"ibe-waters-naccache-test", &[0u8; 32]);
+        let mut rng2 = XofRng::new(b"ibe-waters-naccache-test", &[0u8; 32]);
+
+        let usk = extract_usk(&results.pk, &results.sk, &results.kid, &mut rng);
+        let usk2 = extract_usk_prepared(&ppk, &results.sk, &results.kid, &mut rng2);
+        assert!(usk == usk2);
+
+        let c = encrypt(&results.pk, &results.kid, &results.m, &mut rng);
+        let c2 = encrypt_prepared(&ppk, &results.kid, &results.m, &mut rng2);
+        assert_eq!(c, c2);
+        assert_eq!(decrypt(&usk2, &c2), results.m);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn eq_prepared_decrypt() {
+        let results = perform_default();
+        let pusk = PreparedUserSecretKey::new(&results.usk);
+
+        assert_eq!(decrypt_prepared(&pusk, &results.c), results.m);
+        assert!(pusk.user_secret_key() == &results.usk);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn batch_matches_sequential() {
+        let (pk, sk) = setup_from_seed(&[1u8; 32]);
+        let ids = [
+            Identity::derive_str(ID),
+            Identity::derive_str("email:l.botros@cs.ru.nl"),
+        ];
+
+        let mut rng = XofRng::new(b"ibe-waters-naccache-test", &[0u8; 32]);
+        let mut rng2 = XofRng::new(b"ibe-waters-naccache-test", &[0u8; 32]);
+
+        let usks = extract_usk_batch(&pk, &sk, &ids, &mut rng);
+        assert_eq!(usks.len(), ids.len());
+        for (v, usk) in ids.iter().zip(usks.iter()) {
+            assert!(usk == &extract_usk(&pk, &sk, v, &mut rng2));
+        }
+    }
+
+    #[test]
+    fn setup_from_seed_is_deterministic() {
+        let (pk, sk) = setup_from_seed(&[1u8; 32]);
+        let (pk2, sk2) = setup_from_seed(&[1u8; 32]);
+        let (pk3, sk3) = setup_from_seed(&[2u8; 32]);
+
+        assert!(pk == pk2);
+        assert!(sk == sk2);
+        assert!(pk != pk3);
+        assert!(sk != sk3);
+    }
+
     #[test]
     fn eq_serialize_deserialize() {
         let result = perform_default();
@@ -400,4 +1148,13 @@ mod tests {
             CipherText::from_bytes(&result.c.to_bytes()).unwrap()
         );
     }
+
+    #[test]
+    fn identity_roundtrip() {
+        let kid = Identity::derive_str(ID);
+        let kid2 = Identity::from_bytes(&kid.to_bytes()).unwrap();
+
+        assert_eq!(kid.to_bytes(), sha3_512(ID.as_bytes()));
+        assert!(kid.0.iter().zip(kid2.0.iter()).all(|(a, b)| a == b));
+    }
 }
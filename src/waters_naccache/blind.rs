@@ -0,0 +1,475 @@
+//! Blind extraction of user secret keys for the Waters-Naccache scheme.
+//!
+//! Allows a user to obtain a user secret key from the PKG without revealing the identity,
+//! apart from a short tag identifying the type of attribute that the identity consists of.
+//! The PKG can thus decide whether to honour a request, for example after the user proved to own
+//! some attribute of that type, without learning the value of the attribute.
+//!
+//!  1. The user derives its identity using [`Identity::derive_attribute`],
+//!     and calls [`blind`] to obtain a [`BlindRequest`] to send to the PKG.
+//!  2. The PKG checks the [`attribute_tag`](BlindRequest::attribute_tag) of the request,
+//!     and calls [`extract_blind_usk`] to verify the request and obtain a [`BlindUserSecretKey`].
+//!  3. The user calls [`unblind`] to obtain a regular [`UserSecretKey`].
+//!
+//! The user commits to every bit of the hidden chunks of its identity separately, blinding each commitment with an
+//! independent generator, such that the commitments add up to the blinded point the identity is entangled into.
+//! For every commitment, the user proves in zero-knowledge that it hides either zero or one using a
+//! non-interactive OR-proof of knowledge of its blinding factor. This assures the PKG that every hidden chunk
+//! is a valid chunk of an identity, without learning anything about its value.
+//! As a consequence, a request takes about 84 KiB.
+
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use rand::{CryptoRng, RngCore};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption};
+
+use super::{
+    entangle, Identity, PublicKey, SecretKey, UserSecretKey, CHUNKS, CHUNKSIZE, HASH_BYTE_LEN,
+};
+use crate::util::*;
+use irmaseal_curve::{G1Affine, G2Affine, G2Projective, Scalar};
+use tiny_keccak::{Hasher, Sha3};
+
+/// Number of bytes of the identity that are revealed to the PKG.
+pub const TAG_BYTE_LEN: usize = 8;
+
+const TAG_CHUNKS: usize = TAG_BYTE_LEN / CHUNKSIZE;
+const HIDDEN_CHUNKS: usize = CHUNKS - TAG_CHUNKS;
+
+const CHUNK_BITS: usize = CHUNKSIZE * 8;
+const HIDDEN_BITS: usize = HIDDEN_CHUNKS * CHUNK_BITS;
+
+const PROOFSIZE: usize = (3 * HIDDEN_BITS + 1) * 32;
+const BLINDREQUESTSIZE: usize = TAG_BYTE_LEN + HIDDEN_BITS * 96 + PROOFSIZE;
+
+const GENERATOR_DST: &[u8] = b"ibe-waters-naccache-blind-generator";
+const CHALLENGE_DST: &[u8] = b"ibe-waters-naccache-blind-challenge";
+
+/// Blinding factor, kept by the user to unblind the blind user secret key.
+#[derive(Clone)]
+pub struct Blinding {
+    beta: Scalar,
+}
+
+zeroize_on_drop!(Blinding: beta);
+impl_secret!(Blinding: beta);
+
+/// Non-interactive proof that every commitment hides a bit, with knowledge of its blinding factor.
+///
+/// The `j`-th bit of a chunk is committed to relative to `2^j·u_i`, the base of that bit.
+/// Branch 0 proves that the commitment equals `ρ·x` and branch 1 that it equals the base plus `ρ·x`.
+/// The challenges `e0[n]` and `e - e0[n]` of both branches sum to the challenge `e`,
+/// such that one of them may be simulated.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Proof {
+    e: Scalar,
+    e0: [Scalar; HIDDEN_BITS],
+    s0: [Scalar; HIDDEN_BITS],
+    s1: [Scalar; HIDDEN_BITS],
+}
+
+/// Request for a blind user secret key, sent by the user to the PKG.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BlindRequest {
+    tag: [u8; TAG_BYTE_LEN],
+    commitments: [G2Affine; HIDDEN_BITS],
+    proof: Proof,
+}
+
+/// Blinded user secret key, sent by the PKG to the user.
+#[derive(Clone)]
+pub struct BlindUserSecretKey {
+    d1: G2Affine,
+    d2: G1Affine,
+    d3: G2Affine,
+}
+
+zeroize_on_drop!(BlindUserSecretKey: d1, d2, d3);
+impl_secret!(BlindUserSecretKey: d1, d2, d3);
+impl_try_from_secret!(BlindUserSecretKey, 240);
+
+/// Derive the tag for an attribute type, which forms the revealed part of the identity.
+pub fn attribute_tag(attribute_type: &[u8]) -> [u8; TAG_BYTE_LEN] {
+    let hash = sha3_512(attribute_type);
+    *array_ref![hash, 0, TAG_BYTE_LEN]
+}
+
+/// Independent generator with which the identity is blinded.
+fn generator() -> G2Projective {
+    hash_to_g2(b"", GENERATOR_DST)
+}
+
+/// The part of the entangled identity that is revealed to the PKG.
+fn entangle_tag(pk: &PublicKey, tag: &[u8; TAG_BYTE_LEN]) -> G2Projective {
+    let mut hash = [0u8; HASH_BYTE_LEN];
+    hash[..TAG_BYTE_LEN].copy_from_slice(tag);
+
+    let mut v = Identity::from_hash(&hash);
+    for vi in v.0.iter_mut().skip(TAG_CHUNKS) {
+        *vi = Scalar::zero();
+    }
+    entangle(pk, &v)
+}
+
+/// The bases of the bits of the hidden chunks,
+/// such that a chunk is entangled as the sum of the bases of its set bits.
+fn bases(pk: &PublicKey) -> impl Iterator<Item = G2Projective> + '_ {
+    pk.u.0.iter().skip(TAG_CHUNKS).flat_map(|ui| {
+        (0..CHUNK_BITS).scan(G2Projective::from(ui), |base, _| {
+            let res = *base;
+            *base = base.double();
+            Some(res)
+        })
+    })
+}
+
+/// The bits of the hidden chunks of an identity, starting at the least significant bit of each chunk.
+fn hidden_bits(v: &Identity) -> impl Iterator<Item = Choice> + '_ {
+    v.0.iter().skip(TAG_CHUNKS).flat_map(|vi| {
+        let bytes = vi.to_bytes();
+        (0..CHUNK_BITS).map(move |j| Choice::from((bytes[j / 8] >> (j % 8)) & 1))
+    })
+}
+
+/// Start the Fiat-Shamir transcript, which commits to the full public key that defines the proven statement.
+fn transcript(
+    pk: &PublicKey,
+    tag: &[u8; TAG_BYTE_LEN],
+    commitments: &[G2Affine; HIDDEN_BITS],
+) -> Sha3 {
+    let mut transcript = Sha3::v512();
+    transcript.update(CHALLENGE_DST);
+    transcript.update(&pk.to_bytes());
+    transcript.update(tag);
+    for ci in commitments.iter() {
+        transcript.update(&ci.to_compressed());
+    }
+    transcript
+}
+
+fn absorb(transcript: &mut Sha3, t: &G2Projective) {
+    transcript.update(&G2Affine::from(t).to_compressed());
+}
+
+fn challenge(transcript: Sha3) -> Scalar {
+    let mut buf = [0u8; 64];
+    transcript.finalize(&mut buf);
+    Scalar::from_bytes_wide(&buf)
+}
+
+/// The blinded point the identity is entangled into, which is the sum of the commitments and the revealed tag.
+fn blinded_entangle(pk: &PublicKey, request: &BlindRequest) -> G2Projective {
+    let mut r = entangle_tag(pk, &request.tag);
+    for ci in request.commitments.iter() {
+        r += ci;
+    }
+    r
+}
+
+/// Verify that every commitment of the request hides a bit.
+fn verify(pk: &PublicKey, request: &BlindRequest) -> bool {
+    let Proof { e, e0, s0, s1 } = &request.proof;
+    let x = generator();
+
+    let mut transcript = transcript(pk, &request.tag, &request.commitments);
+    for (n, (ci, base)) in request.commitments.iter().zip(bases(pk)).enumerate() {
+        let ci = G2Projective::from(ci);
+        absorb(&mut transcript, &(x * s0[n] - ci * e0[n]));
+        absorb(&mut transcript, &(x * s1[n] - (ci - base) * (e - e0[n])));
+    }
+
+    bool::from(challenge(transcript).ct_eq(e))
+}
+
+/// Blind an identity, yielding the blinding factor to keep and the request to send to the PKG.
+///
+/// The first [`TAG_BYTE_LEN`] bytes of the identity are revealed to the PKG.
+pub fn blind<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    v: &Identity,
+    rng: &mut R,
+) -> (Blinding, BlindRequest) {
+    let mut tag = [0u8; TAG_BYTE_LEN];
+    for (i, vi) in v.0.iter().take(TAG_CHUNKS).enumerate() {
+        let bytes = vi.to_bytes();
+        *array_mut_ref![tag, i * CHUNKSIZE, CHUNKSIZE] = *array_ref![bytes, 0, CHUNKSIZE];
+    }
+
+    let x = generator();
+
+    let mut rho = [Scalar::zero(); HIDDEN_BITS];
+    let mut commitments = [G2Affine::identity(); HIDDEN_BITS];
+    for ((ci, rhoi), (base, vn)) in commitments
+        .iter_mut()
+        .zip(rho.iter_mut())
+        .zip(bases(pk).zip(hidden_bits(v)))
+    {
+        *rhoi = rand_scalar(rng);
+        let c = x * *rhoi;
+        *ci = G2Projective::conditional_select(&c, &(c + base), vn).into();
+    }
+
+    // Per bit, the nonce of the proven branch, and the challenge and response of the simulated branch.
+    let mut k = [Scalar::zero(); HIDDEN_BITS];
+    let mut e_sim = [Scalar::zero(); HIDDEN_BITS];
+    let mut s_sim = [Scalar::zero(); HIDDEN_BITS];
+
+    let mut transcript = transcript(pk, &tag, &commitments);
+    for (n, (ci, (base, vn))) in commitments
+        .iter()
+        .zip(bases(pk).zip(hidden_bits(v)))
+        .enumerate()
+    {
+        k[n] = rand_scalar(rng);
+        e_sim[n] = rand_scalar(rng);
+        s_sim[n] = rand_scalar(rng);
+
+        // The simulated branch claims that the commitment equals the base plus `ρ·x` for a zero bit,
+        // or `ρ·x` for a one bit.
+        let ci = G2Projective::from(ci);
+        let d = G2Projective::conditional_select(&(ci - base), &ci, vn);
+        let t = x * k[n];
+        let t_sim = x * s_sim[n] - d * e_sim[n];
+
+        absorb(
+            &mut transcript,
+            &G2Projective::conditional_select(&t, &t_sim, vn),
+        );
+        absorb(
+            &mut transcript,
+            &G2Projective::conditional_select(&t_sim, &t, vn),
+        );
+    }
+    let e = challenge(transcript);
+
+    let mut proof = Proof {
+        e,
+        e0: [Scalar::zero(); HIDDEN_BITS],
+        s0: [Scalar::zero(); HIDDEN_BITS],
+        s1: [Scalar::zero(); HIDDEN_BITS],
+    };
+    for (n, vn) in hidden_bits(v).enumerate() {
+        let e_real = e - e_sim[n];
+        let s_real = k[n] + e_real * rho[n];
+
+        proof.e0[n] = Scalar::conditional_select(&e_real, &e_sim[n], vn);
+        proof.s0[n] = Scalar::conditional_select(&s_real, &s_sim[n], vn);
+        proof.s1[n] = Scalar::conditional_select(&s_sim[n], &s_real, vn);
+    }
+
+    let beta = rho.iter().sum();
+    rho.wipe();
+    k.wipe();
+
+    let request = BlindRequest {
+        tag,
+        commitments,
+        proof,
+    };
+
+    (Blinding { beta }, request)
+}
+
+/// Verify a blind request and extract a blind user secret key for it.
+///
+/// The PKG should check the [`attribute_tag`](BlindRequest::attribute_tag) of the request beforehand.
+/// Returns `None` if the proof of the request does not verify.
+pub fn extract_blind_usk<R: RngCore + CryptoRng>(
+    pk: &PublicKey,
+    sk: &SecretKey,
+    request: &BlindRequest,
+    rng: &mut R,
+) -> Option<BlindUserSecretKey> {
+    if !verify(pk, request) {
+        return None;
+    }
+
+    let mut r = rand_scalar(rng);
+    let d1 = (sk.g2prime + (blinded_entangle(pk, request) * r)).into();
+    let d2 = (pk.g * r).into();
+    let d3 = (generator() * r).into();
+    r.wipe();
+
+    Some(BlindUserSecretKey { d1, d2, d3 })
+}
+
+/// Unblind a blind user secret key into a regular user secret key.
+pub fn unblind(blinding: &Blinding, busk: &BlindUserSecretKey) -> UserSecretKey {
+    UserSecretKey {
+        d1: (busk.d1 - busk.d3 * blinding.beta).into(),
+        d2: busk.d2,
+    }
+}
+
+impl Blinding {
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.beta.to_bytes()
+    }
+
+    pub fn from_bytes(bytes: &[u8; 32]) -> CtOption<Self> {
+        Scalar::from_bytes(bytes).map(|beta| Blinding { beta })
+    }
+}
+
+impl BlindRequest {
+    /// The revealed part of the identity, to be compared with the [`attribute_tag`] of an authorized attribute type.
+    pub fn attribute_tag(&self) -> [u8; TAG_BYTE_LEN] {
+        self.tag
+    }
+
+    pub fn to_bytes(&self) -> [u8; BLINDREQUESTSIZE] {
+        let mut res = [0u8; BLINDREQUESTSIZE];
+        let (tag, commitments, e, proof) = mut_array_refs![
+            &mut res,
+            TAG_BYTE_LEN,
+            HIDDEN_BITS * 96,
+            32,
+            HIDDEN_BITS * 96
+        ];
+        *tag = self.tag;
+        for (n, cn) in self.commitments.iter().enumerate() {
+            *array_mut_ref![commitments, n * 96, 96] = cn.to_compressed();
+        }
+        *e = self.proof.e.to_bytes();
+        for n in 0..HIDDEN_BITS {
+            let (e0, s0, s1) = mut_array_refs![array_mut_ref![proof, n * 96, 96], 32, 32, 32];
+            *e0 = self.proof.e0[n].to_bytes();
+            *s0 = self.proof.s0[n].to_bytes();
+            *s1 = self.proof.s1[n].to_bytes();
+        }
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; BLINDREQUESTSIZE]) -> CtOption<Self> {
+        let (tag, commitments, e, proof) =
+            array_refs![bytes, TAG_BYTE_LEN, HIDDEN_BITS * 96, 32, HIDDEN_BITS * 96];
+
+        let mut is_some = Choice::from(1u8);
+        let mut cres = [G2Affine::identity(); HIDDEN_BITS];
+        for (n, cn) in cres.iter_mut().enumerate() {
+            is_some &= G2Affine::from_compressed(array_ref![commitments, n * 96, 96])
+                .map(|x| {
+                    *cn = x;
+                })
+                .is_some();
+        }
+
+        let mut pres = Proof {
+            e: Scalar::zero(),
+            e0: [Scalar::zero(); HIDDEN_BITS],
+            s0: [Scalar::zero(); HIDDEN_BITS],
+            s1: [Scalar::zero(); HIDDEN_BITS],
+        };
+        is_some &= Scalar::from_bytes(e).map(|x| pres.e = x).is_some();
+        for n in 0..HIDDEN_BITS {
+            let (e0, s0, s1) = array_refs![array_ref![proof, n * 96, 96], 32, 32, 32];
+            is_some &= Scalar::from_bytes(e0).map(|x| pres.e0[n] = x).is_some();
+            is_some &= Scalar::from_bytes(s0).map(|x| pres.s0[n] = x).is_some();
+            is_some &= Scalar::from_bytes(s1).map(|x| pres.s1[n] = x).is_some();
+        }
+
+        CtOption::new(
+            BlindRequest {
+                tag: *tag,
+                commitments: cres,
+                proof: pres,
+            },
+            is_some,
+        )
+    }
+}
+
+impl BlindUserSecretKey {
+    pub fn to_bytes(&self) -> [u8; 240] {
+        let mut res = [0u8; 240];
+        let (d1, d2, d3) = mut_array_refs![&mut res, 96, 48, 96];
+        *d1 = self.d1.to_compressed();
+        *d2 = self.d2.to_compressed();
+        *d3 = self.d3.to_compressed();
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8; 240]) -> CtOption<Self> {
+        let (d1, d2, d3) = array_refs![bytes, 96, 48, 96];
+
+        let d1 = G2Affine::from_compressed(d1);
+        let d2 = G1Affine::from_compressed(d2);
+        let d3 = G2Affine::from_compressed(d3);
+
+        d1.and_then(|d1| d2.and_then(|d2| d3.map(|d3| BlindUserSecretKey { d1, d2, d3 })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{decrypt, encrypt, setup, Message};
+    use super::*;
+
+    const ATTRIBUTE_TYPE: &[u8] = b"pbdf.sidn-pbdf.email.email";
+    const VALUE: &[u8] = b"w.geraedts@sarif.nl";
+
+    #[test]
+    fn eq_blind_extract_decrypt() {
+        let mut rng = rand::thread_rng();
+
+        let kid = Identity::derive_attribute(ATTRIBUTE_TYPE, VALUE);
+        let m = Message::generate(&mut rng);
+
+        let (pk, sk) = setup(&mut rng);
+        let (blinding, request) = blind(&pk, &kid, &mut rng);
+
+        assert_eq!(request.attribute_tag(), attribute_tag(ATTRIBUTE_TYPE));
+
+        let busk = extract_blind_usk(&pk, &sk, &request, &mut rng).unwrap();
+        let usk = unblind(&blinding, &busk);
+
+        let c = encrypt(&pk, &kid, &m, &mut rng);
+        assert_eq!(m, decrypt(&usk, &c));
+    }
+
+    #[test]
+    fn reject_malformed_request() {
+        let mut rng = rand::thread_rng();
+
+        let kid = Identity::derive_attribute(ATTRIBUTE_TYPE, VALUE);
+        let (pk, sk) = setup(&mut rng);
+        let (_, request) = blind(&pk, &kid, &mut rng);
+
+        // Claiming another attribute type invalidates the proof.
+        let mut other = request;
+        other.tag = attribute_tag(b"pbdf.gemeente.personalData.bsn");
+        assert!(extract_blind_usk(&pk, &sk, &other, &mut rng).is_none());
+
+        // Shifting a commitment towards another identity invalidates the proof.
+        let mut other = request;
+        other.commitments[0] =
+            (G2Projective::from(other.commitments[0]) + pk.u.0[TAG_CHUNKS]).into();
+        assert!(extract_blind_usk(&pk, &sk, &other, &mut rng).is_none());
+
+        // Moving weight between the commitments of a chunk invalidates the proof,
+        // even though the commitments still add up to the same point.
+        let mut other = request;
+        let u = pk.u.0[TAG_CHUNKS];
+        other.commitments[0] = (G2Projective::from(other.commitments[0]) + u).into();
+        other.commitments[1] = (G2Projective::from(other.commitments[1]) - u).into();
+        assert!(extract_blind_usk(&pk, &sk, &other, &mut rng).is_none());
+    }
+
+    #[test]
+    fn eq_serialize_deserialize() {
+        let mut rng = rand::thread_rng();
+
+        let kid = Identity::derive_attribute(ATTRIBUTE_TYPE, VALUE);
+        let (pk, sk) = setup(&mut rng);
+        let (_, request) = blind(&pk, &kid, &mut rng);
+        let busk = extract_blind_usk(&pk, &sk, &request, &mut rng).unwrap();
+
+        assert_eq!(
+            request,
+            BlindRequest::from_bytes(&request.to_bytes()).unwrap()
+        );
+        assert_eq!(
+            busk,
+            BlindUserSecretKey::from_bytes(&busk.to_bytes()).unwrap()
+        );
+    }
+}